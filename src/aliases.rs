@@ -0,0 +1,138 @@
+use std::path::{Path, PathBuf};
+use std::fs;
+use std::io;
+
+use crate::{groups, history, logs, mods, saves, sync, Alias, Config};
+
+/// The keys an alias can have, in the order `alias edit` shows them.
+pub const KEYS: [&str; 11] = ["path", "runner", "args", "env", "tags", "log", "pre_launch", "post_exit", "saves", "auto_backup", "steam_appid"];
+
+/// Fails if `name` can't be an alias. Alias names become folder names for logs, backups
+/// and mods, so they must stay a single folder.
+pub fn validate_name(name: &str) -> io::Result<()> {
+    if name.trim().is_empty() || name == "." || name.contains("..") || name.contains(['/', '\\']) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+            "`{name}` is not a valid alias name, it can't be empty or have `/`, `\\` or `..` in it"
+        )));
+    }
+    Ok(())
+}
+
+
+/// Everything plz keeps for one alias, in the data directory and in `saves.sync_dir`.
+fn alias_data_dirs(config: &Config, alias: &str) -> Vec<PathBuf> {
+    let mut dirs = vec![logs::alias_log_dir(alias), saves::backups_dir(alias), mods::mods_dir().join(alias)];
+    if let Some(sync_dir) = &config.saves.sync_dir {
+        dirs.push(sync::alias_sync_dir(Path::new(sync_dir), alias));
+    }
+    dirs
+}
+
+
+/// Renames `old` to `new`, taking its history, logs, save backups (synced ones too) and mods
+/// along. Groups launching it launch it under the new name.
+pub fn rename(config: &mut Config, old: &str, new: &str) -> io::Result<()> {
+    // Checked up front so nothing gets moved halfway.
+    if !config.aliases.contains_key(old) {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("Alias `{old}` doesn't exist")));
+    }
+    validate_name(new)?;
+    if config.aliases.contains_key(new) {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("Alias `{new}` already exists")));
+    }
+    if let Some(dir) = alias_data_dirs(config, new).into_iter().find(|dir| dir.exists()) {
+        return Err(io::Error::other(format!("`{}` already exists, remove it first", dir.display())));
+    }
+    history::rename(old, new)?;
+    for (from, to) in alias_data_dirs(config, old).into_iter().zip(alias_data_dirs(config, new)) {
+        if from.exists() {
+            fs::rename(&from, &to)?;
+        }
+    }
+    let entry = config.aliases.remove(old).unwrap();
+    config.aliases.insert(new.to_owned(), entry);
+    for member in config.groups.values_mut().flat_map(|group| group.members.iter_mut()) {
+        if member.alias.as_deref() == Some(old) {
+            member.alias = Some(new.to_owned());
+        }
+    }
+    Ok(())
+}
+
+
+/// Removes `alias` from the config, unless a group still launches it.
+pub fn remove(config: &mut Config, alias: &str) -> io::Result<()> {
+    if !config.aliases.contains_key(alias) {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("Alias `{alias}` doesn't exist")));
+    }
+    let groups = groups::groups_using(config, alias);
+    if !groups.is_empty() {
+        return Err(io::Error::other(format!(
+            "Alias `{alias}` is launched by group(s) `{}`, take it out of them first", groups.join("`, `")
+        )));
+    }
+    config.aliases.remove(alias);
+    Ok(())
+}
+
+
+/// Reads an alias written as a TOML table, like `alias edit` shows it.
+pub fn parse_alias(contents: &str) -> Result<Alias, String> {
+    // Unknown keys would otherwise get dropped silently, typos included.
+    let table: toml::Table = contents.parse().map_err(|err: toml::de::Error| err.to_string())?;
+    if let Some(key) = table.keys().find(|key| !KEYS.contains(&key.as_str())) {
+        return Err(format!("`{key}` is not a valid key"));
+    }
+    let alias: Alias = toml::from_str(contents).map_err(|err| err.to_string())?;
+    if alias.path.trim().is_empty() {
+        return Err(String::from("path can't be empty"));
+    }
+    Ok(alias)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_to_rename_missing_or_onto_taken_aliases() {
+        let mut config: Config = toml::from_str(crate::DEFAULT_CONFIG).unwrap();
+        config.aliases.insert(String::from("a"), Alias::new(String::from("/games/a.exe")));
+        config.aliases.insert(String::from("b"), Alias::new(String::from("/games/b.exe")));
+
+        let err = rename(&mut config, "nope", "c").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        let err = rename(&mut config, "a", "b").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(config.aliases["b"].path, "/games/b.exe");
+        let err = rename(&mut config, "a", "../../a").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(config.aliases.contains_key("a"));
+    }
+
+    #[test]
+    fn keeps_alias_names_to_one_folder() {
+        for name in ["", " ", ".", "..", "../x", "a/b", "a\\b", "x.."] {
+            assert!(validate_name(name).is_err(), "`{name}` was accepted");
+        }
+        for name in ["hk", "Hollow Knight", "v1.2", "lc:modded"] {
+            validate_name(name).unwrap();
+        }
+    }
+
+    #[test]
+    fn refuses_to_remove_aliases_groups_launch() {
+        let mut config: Config = toml::from_str(crate::DEFAULT_CONFIG).unwrap();
+        config.aliases.insert(String::from("server"), Alias::new(String::from("/games/server.exe")));
+        config.aliases.insert(String::from("client"), Alias::new(String::from("/games/client.exe")));
+        let member = groups::Member { alias: Some(String::from("server")), ..Default::default() };
+        config.groups.insert(String::from("lan"), groups::Group { members: vec![member] });
+
+        let err = remove(&mut config, "server").unwrap_err();
+        assert_eq!(err.to_string(), "Alias `server` is launched by group(s) `lan`, take it out of them first");
+        assert!(config.aliases.contains_key("server"));
+        remove(&mut config, "client").unwrap();
+        assert!(!config.aliases.contains_key("client"));
+    }
+}
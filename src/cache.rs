@@ -0,0 +1,121 @@
+use reqwest::header::{HeaderMap, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+use std::time::UNIX_EPOCH;
+use std::path::{Path, PathBuf};
+use std::fs;
+use std::io;
+
+use crate::history::now;
+use crate::selfupdate::sha256_hex;
+use crate::data_dir;
+
+/// An answer kept on disk, with what's needed to ask the server whether it changed since.
+#[derive(Serialize, Deserialize)]
+pub struct Entry<T> {
+    pub key: String,
+    /// When the server last gave or confirmed the value, in seconds since the epoch.
+    pub stored: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    pub value: T
+}
+
+impl<T> Entry<T> {
+    /// A new entry for `value`, keeping the validators from the `headers` of the response it came from.
+    pub fn new(key: &str, value: T, headers: &HeaderMap) -> Entry<T> {
+        let header = |name| headers.get(name).and_then(|value: &HeaderValue| value.to_str().ok()).map(str::to_owned);
+        Entry { key: key.to_owned(), stored: now(), etag: header(ETAG), last_modified: header(LAST_MODIFIED), value }
+    }
+
+    pub fn is_fresh(&self, ttl_secs: u64) -> bool {
+        now().saturating_sub(self.stored) < ttl_secs
+    }
+
+    /// Headers that make the server answer `304 Not Modified` if the value is still current.
+    pub fn conditional_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(value) = self.etag.as_deref().and_then(|etag| HeaderValue::from_str(etag).ok()) {
+            headers.insert(IF_NONE_MATCH, value);
+        }
+        if let Some(value) = self.last_modified.as_deref().and_then(|date| HeaderValue::from_str(date).ok()) {
+            headers.insert(IF_MODIFIED_SINCE, value);
+        }
+        headers
+    }
+}
+
+/// How much one kind of answer takes up in the cache.
+pub struct Stats {
+    pub namespace: String,
+    pub entries: usize,
+    pub bytes: u64,
+    /// When the oldest entry was stored, in seconds since the epoch.
+    pub oldest: Option<u64>
+}
+
+
+pub fn cache_dir() -> PathBuf {
+    data_dir().join("cache")
+}
+
+
+fn entry_path(dir: &Path, namespace: &str, key: &str) -> PathBuf {
+    dir.join(namespace).join(format!("{}.json", sha256_hex(key.as_bytes())))
+}
+
+
+/// The entry for `key` in the cache at `dir` (normally `cache_dir()`), or `None` if there
+/// isn't one or it can't be read anymore.
+pub fn read<T: DeserializeOwned>(dir: &Path, namespace: &str, key: &str) -> Option<Entry<T>> {
+    let contents = fs::read(entry_path(dir, namespace, key)).ok()?;
+    serde_json::from_slice::<Entry<T>>(&contents).ok().filter(|entry| entry.key == key)
+}
+
+
+pub fn write<T: Serialize>(dir: &Path, namespace: &str, entry: &Entry<T>) -> io::Result<()> {
+    fs::create_dir_all(dir.join(namespace))?;
+    fs::write(entry_path(dir, namespace, &entry.key), serde_json::to_vec(entry).map_err(io::Error::other)?)
+}
+
+
+/// Removes every cached answer, returns how many there were.
+pub fn clear() -> io::Result<usize> {
+    let entries = stats()?.iter().map(|stats| stats.entries).sum();
+    match fs::remove_dir_all(cache_dir()) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(entries)
+    }
+}
+
+
+/// What's in the cache, by namespace.
+pub fn stats() -> io::Result<Vec<Stats>> {
+    let mut all = Vec::new();
+    let namespaces = match fs::read_dir(cache_dir()) {
+        Ok(namespaces) => namespaces,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(all),
+        Err(err) => return Err(err)
+    };
+    for namespace in namespaces {
+        let namespace = namespace?;
+        if !namespace.file_type()?.is_dir() {
+            continue;
+        }
+        let mut stats = Stats { namespace: namespace.file_name().to_string_lossy().into_owned(), entries: 0, bytes: 0, oldest: None };
+        for entry in fs::read_dir(namespace.path())? {
+            let metadata = entry?.metadata()?;
+            stats.entries += 1;
+            stats.bytes += metadata.len();
+            let modified = metadata.modified().ok().and_then(|modified| modified.duration_since(UNIX_EPOCH).ok());
+            if let Some(modified) = modified.map(|since| since.as_secs()) {
+                stats.oldest = Some(stats.oldest.map_or(modified, |oldest| oldest.min(modified)));
+            }
+        }
+        all.push(stats);
+    }
+    all.sort_by(|a, b| a.namespace.cmp(&b.namespace));
+    Ok(all)
+}
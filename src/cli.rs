@@ -0,0 +1,90 @@
+//! Everything that talks to the terminal: prompts, progress and printing what the library returns.
+
+use std::collections::HashMap;
+use std::process::exit;
+use anstyle::AnsiColor;
+use std::io::Write;
+use std::io;
+
+use plz::http::{self, HttpSettings};
+use plz::{try_read_config, Config};
+
+pub mod aliases;
+pub mod cache;
+pub mod compat;
+pub mod doctor;
+pub mod fetch;
+pub mod groups;
+pub mod info;
+pub mod logs;
+pub mod run;
+pub mod selfupdate;
+
+
+pub fn read_config(default_content: &str) -> Config {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    match try_read_config() {
+        Ok(Some(config)) => config,
+        Ok(None) => {
+            eprintln!("{error}Could find the config file, creating new one");
+            let data: Config = toml::from_str(default_content).unwrap();
+            save_config(&data);
+            data
+        }
+        Err(err) => {
+            eprintln!("{error}Unable to load the config file. {}", err);
+            exit(1);
+        }
+    }
+}
+
+
+pub fn save_config(data: &Config) {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    if let Err(err) = plz::save_config(data) {
+        eprintln!("{error}Failed to save the config file. {}", err);
+    }
+}
+
+
+/// The client for every request, or exits if the `[http]` settings can't be used.
+pub fn http_client(settings: &HttpSettings) -> http::Client {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    match http::client(settings) {
+        Ok(client) => client,
+        Err(err) => {
+            eprintln!("{error}{}", err);
+            exit(1);
+        }
+    }
+}
+
+
+pub fn sort_by_key_length<T>(mut hash_map: HashMap<String, T>) -> Vec<(String, T)> {
+    let mut vec: Vec<(String, T)> = hash_map.drain().collect();
+    vec.sort_by_key(|(key, _)| std::cmp::Reverse(key.len()));
+    vec.into_iter().collect()
+}
+
+
+pub fn user_input(message: String) -> bool {
+    print!("{}", message);
+    io::stdout().flush().unwrap();
+    let mut buf = String::new();
+    match io::stdin().read_line(&mut buf) {
+        Ok(_) => {},
+        Err(err) => {
+            eprintln!("{}", err);
+            exit(1);
+        }
+    }
+
+    let input = buf.trim().to_lowercase();
+    if input == "y" || input == "yes" {
+        return true;
+    }
+    false
+}
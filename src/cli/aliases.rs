@@ -0,0 +1,134 @@
+use anstyle::{AnsiColor, Style};
+use std::path::Path;
+use std::io::Write;
+use std::fs;
+use std::io;
+
+use plz::{aliases, history, hooks, scan, Alias, Config};
+
+use super::user_input;
+
+pub fn show(config: &Config, alias: &str) {
+    let bold = Style::new().bold();
+    let gray = AnsiColor::BrightBlack.on_default();
+    let entry = &config.aliases[alias];
+    let list = |values: &[String]| if values.is_empty() { format!("{gray}none{gray:#}") } else { values.join(", ") };
+    let (playtime, sessions) = history::playtime(&history::read_history().unwrap_or_default()).remove(alias).unwrap_or_default();
+    let mut env: Vec<String> = entry.env.iter().map(|(name, value)| format!("{name}={value}")).collect();
+    env.sort();
+
+    println!("{bold}{}{bold:#}", alias);
+    println!(" {bold}path:{bold:#} {}", entry.path);
+    let target = config.expand_path(&entry.path);
+    if target != entry.path {
+        println!(" {bold}resolved path:{bold:#} {}", target);
+    }
+    println!(" {bold}runner:{bold:#} {}", entry.runner.as_deref().unwrap_or(&format!("{gray}none{gray:#}")));
+    println!(" {bold}args:{bold:#} {}", list(&entry.args));
+    println!(" {bold}env:{bold:#} {}", list(&env));
+    println!(" {bold}tags:{bold:#} {}", list(&entry.tags));
+    println!(" {bold}log:{bold:#} {}", entry.log);
+    println!(" {bold}pre_launch:{bold:#} {}", list(&entry.pre_launch));
+    println!(" {bold}post_exit:{bold:#} {}", list(&entry.post_exit));
+    println!(" {bold}saves:{bold:#} {}", list(&entry.saves));
+    match entry.auto_backup {
+        Some(auto_backup) => println!(" {bold}auto_backup:{bold:#} {}", auto_backup),
+        None => println!(" {bold}auto_backup:{bold:#} {} {gray}(from saves.auto_backup){gray:#}", config.saves.auto_backup)
+    }
+    match entry.steam_appid {
+        Some(appid) => println!(" {bold}steam_appid:{bold:#} {}", appid),
+        None => println!(" {bold}steam_appid:{bold:#} {gray}none{gray:#}")
+    }
+    println!(" {bold}playtime:{bold:#} {} over {} session(s)", history::format_duration(playtime), sessions);
+}
+
+
+/// `$VISUAL`, then `$EDITOR`, then something that's always there.
+fn editor() -> String {
+    std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| String::from(if cfg!(windows) { "notepad" } else { "vi" }))
+}
+
+
+fn open_editor(file: &Path) -> io::Result<()> {
+    // Through the shell like git does, so an editor such as `code --wait` works. The file
+    // goes in as an argument, the shell never reads its path as part of the command.
+    let mut command = if cfg!(windows) {
+        hooks::shell(&format!("{} \"{}\"", editor(), file.display()))
+    } else {
+        let mut command = hooks::shell(&format!("{} \"$1\"", editor()));
+        command.arg("sh").arg(file);
+        command
+    };
+    let status = command.status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("The editor exited with {status}")));
+    }
+    Ok(())
+}
+
+
+/// Opens `alias` in the user's editor as TOML. Returns the edited entry, or `None`
+/// if it didn't change or the user gave up fixing it.
+pub fn edit(config: &Config, alias: &str) -> io::Result<Option<Alias>> {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let original = format!(
+        "# Editing alias `{alias}`, save and close the editor when done.\n# Keys: {}\n{}",
+        aliases::KEYS.join(", "),
+        toml::to_string(&config.aliases[alias]).unwrap()
+    );
+    let file = std::env::temp_dir().join(format!("plz-alias-{}.toml", std::process::id()));
+    fs::write(&file, &original)?;
+
+    let result = loop {
+        if let Err(err) = open_editor(&file) {
+            break Err(err);
+        }
+        let contents = match fs::read_to_string(&file) {
+            Ok(contents) => contents,
+            Err(err) => break Err(err)
+        };
+        if contents == original {
+            break Ok(None);
+        }
+        match aliases::parse_alias(&contents) {
+            Ok(alias) => break Ok(Some(alias)),
+            Err(err) => {
+                eprintln!("{error}The alias is not valid. {}", err.trim());
+                if !user_input(String::from("Edit it again? (y/n) ")) {
+                    break Ok(None);
+                }
+            }
+        }
+    };
+    fs::remove_file(&file).ok();
+    result
+}
+
+
+/// Asks for an alias name for every executable in games_dir that doesn't have one yet.
+/// Skipped executables are remembered in `autoadd_ignore`.
+pub fn autoadd(config: &mut Config) -> io::Result<()> {
+    let v = AnsiColor::BrightYellow.on_default();
+    for path in scan::find_new_executables(config)? {
+        let file_path = path.display().to_string();
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        print!("Alias name for `{v}{}{v:#}` (enter to skip): ", file_name);
+        io::stdout().flush().unwrap();
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        let name = input.trim();
+
+        if name.is_empty() {
+            config.autoadd_ignore.push(file_path);
+        } else if let Err(err) = aliases::validate_name(name) {
+            let red = AnsiColor::BrightRed.on_default().bold();
+            eprintln!("{red}error:{red:#} {}", err);
+        } else if !config.aliases.contains_key(name) || user_input(format!("Overwrite alias `{v}{}{v:#}`? (y/n) ", name)) {
+            config.aliases.insert(name.to_string(), Alias::new(config.portable_alias_path(&file_path)));
+        }
+    }
+    Ok(())
+}
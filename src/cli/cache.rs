@@ -0,0 +1,33 @@
+use anstyle::{AnsiColor, Style};
+use std::process::exit;
+
+use plz::{cache, history};
+
+pub fn print_stats() {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let bold = Style::new().bold();
+    let gray = AnsiColor::BrightBlack.on_default();
+    let v = AnsiColor::BrightYellow.on_default();
+
+    let stats = match cache::stats() {
+        Ok(stats) => stats,
+        Err(err) => {
+            eprintln!("{error}Failed to read the cache. {}", err);
+            exit(1);
+        }
+    };
+    println!("{bold}Cache:{bold:#} {}", cache::cache_dir().display());
+    if stats.is_empty() {
+        println!(" Nothing cached yet");
+    }
+    for stats in stats {
+        let oldest = stats.oldest
+            .map(|oldest| format!(", oldest is {} old", history::format_duration(history::now().saturating_sub(oldest))))
+            .unwrap_or_default();
+        println!(
+            " {bold}{}:{bold:#} {v}{}{v:#} answer(s) {gray}({:.1} KiB{}){gray:#}",
+            stats.namespace, stats.entries, stats.bytes as f64 / 1024.0, oldest
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use anstyle::{AnsiColor, Style};
+use std::process::exit;
+
+use plz::http::Client;
+use plz::{cache, compat, info, Config};
+
+use super::{http_client, save_config};
+
+/// The Steam app ID of `alias`: `appid` if given, the one it has, or the one PCGamingWiki lists
+/// for `title` (or the name of its folder). New ones are kept on the alias.
+async fn steam_appid(client: &Client, config: &mut Config, alias: &str, appid: Option<u32>, title: Option<&str>) -> u32 {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let green = AnsiColor::BrightGreen.on_default().bold();
+    let success = format!("{green}success:{green:#} ");
+    let v = AnsiColor::BrightYellow.on_default();
+
+    if let Some(appid) = appid.or(config.aliases[alias].steam_appid) {
+        if config.aliases[alias].steam_appid != Some(appid) {
+            config.aliases.get_mut(alias).unwrap().steam_appid = Some(appid);
+            save_config(config);
+            println!("{success}Set the Steam app ID of `{v}{}{v:#}` to {v}{}{v:#}", alias, appid);
+        }
+        return appid;
+    }
+    let title = title.map(str::to_owned).unwrap_or_else(|| info::guess_title(config, alias));
+    match info::lookup_cached(client, &cache::cache_dir(), &config.info, &title, false).await {
+        Ok(game) if game.steam_appid.is_some() => {
+            let appid = game.steam_appid.unwrap();
+            config.aliases.get_mut(alias).unwrap().steam_appid = Some(appid);
+            save_config(config);
+            println!("{success}Found Steam app ID {v}{}{v:#} for `{v}{}{v:#}` on PCGamingWiki", appid, alias);
+            appid
+        }
+        Ok(game) => {
+            eprintln!("{error}PCGamingWiki doesn't list a Steam app ID for `{}`", game.page);
+            eprintln!("{error}Set it with `{v}--appid{v:#}`");
+            exit(1);
+        }
+        Err(err) => {
+            eprintln!("{error}{}", err);
+            eprintln!("{error}Set the Steam app ID with `{v}--appid{v:#}`, or pass the game's name with `{v}--title{v:#}`");
+            exit(1);
+        }
+    }
+}
+
+
+/// `plz compat`: how the game behind `alias` runs through Proton according to ProtonDB, and
+/// the runner settings for it. `apply` puts them on the alias.
+pub async fn compat(config: &mut Config, alias: &str, appid: Option<u32>, title: Option<&str>, refresh: bool, apply: bool) {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let green = AnsiColor::BrightGreen.on_default().bold();
+    let success = format!("{green}success:{green:#} ");
+    let yellow = AnsiColor::BrightYellow.on_default().bold();
+    let warning = format!("{yellow}warning:{yellow:#} ");
+    let bold = Style::new().bold();
+    let gray = AnsiColor::BrightBlack.on_default();
+    let v = AnsiColor::BrightYellow.on_default();
+
+    if !config.aliases.contains_key(alias) {
+        eprintln!("{error}Alias `{v}{}{v:#}` not found", alias);
+        exit(1);
+    }
+    let client = http_client(&config.http);
+    let appid = steam_appid(&client, config, alias, appid, title).await;
+    let result = match compat::lookup_cached(&client, &cache::cache_dir(), &config.compat, appid, refresh).await {
+        Ok(result) => result,
+        Err(err) => {
+            eprintln!("{error}{}", err);
+            exit(1);
+        }
+    };
+
+    let tier_color = match result.tier.as_str() {
+        "native" | "platinum" | "gold" => AnsiColor::BrightGreen.on_default().bold(),
+        "silver" | "bronze" => AnsiColor::BrightYellow.on_default().bold(),
+        "borked" => AnsiColor::BrightRed.on_default().bold(),
+        _ => bold
+    };
+    println!(
+        "{bold}ProtonDB:{bold:#} {tier_color}{}{tier_color:#} {gray}(https://www.protondb.com/app/{}){gray:#}",
+        result.tier, appid
+    );
+    if let Some(trending) = result.trending_tier.as_ref().filter(|trending| **trending != result.tier) {
+        println!(" {bold}Trending:{bold:#} {}", trending);
+    }
+    println!(" {bold}Reports:{bold:#} {} {gray}({} confidence){gray:#}", result.total, result.confidence);
+    if let Some(version) = &result.proton_version {
+        println!(" {bold}Recommended Proton:{bold:#} {}", version);
+    }
+
+    match result.tier.as_str() {
+        "native" => println!("\n{gray}The game has a native Linux version{gray:#}"),
+        "borked" => eprintln!("\n{warning}ProtonDB reports say the game doesn't run through Proton"),
+        _ => {}
+    }
+    let Some(suggestion) = result.suggestion() else {
+        return;
+    };
+    if cfg!(windows) || !config.alias_path(alias).is_some_and(|path| path.to_lowercase().ends_with(".exe")) {
+        return;
+    }
+    let entry = &config.aliases[alias];
+    if suggestion.is_applied(entry) {
+        return;
+    }
+    if !apply {
+        println!("{bold}Suggested runner settings:{bold:#}");
+        println!(" {bold}runner:{bold:#} {}", suggestion.runner);
+        for (name, value) in &suggestion.env {
+            println!(" {bold}env:{bold:#} {}={}", name, value);
+        }
+        println!("\n{yellow}hint:{yellow:#} Run `{v}plz compat {} --apply{v:#}` to run the game like this", alias);
+        return;
+    }
+    suggestion.apply(config.aliases.get_mut(alias).unwrap());
+    save_config(config);
+    println!("{success}`{v}{}{v:#}` now runs through `{v}{}{v:#}`", alias, suggestion.runner);
+}
@@ -0,0 +1,53 @@
+use anstyle::{AnsiColor, Style};
+use std::fs;
+
+use plz::doctor::{self, Check, Status};
+use plz::{data_dir, history, Config};
+
+pub fn print_checks(checks: &[Check]) {
+    let green = AnsiColor::BrightGreen.on_default().bold();
+    let yellow = AnsiColor::BrightYellow.on_default().bold();
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let gray = AnsiColor::BrightBlack.on_default();
+    let bold = Style::new().bold();
+
+    println!("{bold}Checks:{bold:#}");
+    for check in checks {
+        let status = match check.status {
+            Status::Ok => format!("{green}ok{green:#}     "),
+            Status::Warning => format!("{yellow}warning{yellow:#}"),
+            Status::Error => format!("{red}error{red:#}  ")
+        };
+        println!(" {status} {}", check.name);
+        for problem in &check.problems {
+            println!("         {gray}-{gray:#} {problem}");
+        }
+        for note in &check.notes {
+            println!("         {gray}note:{gray:#} {note}");
+        }
+        for fix in &check.fixed {
+            println!("         {green}fixed:{green:#} {fix}");
+        }
+    }
+}
+
+
+/// The cheap version of the checks that runs after other commands, at most once a day.
+/// It only prints a hint pointing to `plz doctor`.
+pub fn hint(config: &Config) {
+    const INTERVAL: u64 = 24 * 60 * 60;
+    let yellow = AnsiColor::BrightYellow.on_default().bold();
+    let v = AnsiColor::BrightYellow.on_default();
+    let stamp = data_dir().join("last_doctor_hint");
+    let now = history::now();
+    let last = fs::read_to_string(&stamp).ok().and_then(|last| last.trim().parse::<u64>().ok());
+    if last.is_some_and(|last| now.saturating_sub(last) < INTERVAL) {
+        return;
+    }
+    fs::write(&stamp, now.to_string()).ok();
+
+    let problems: usize = doctor::quick_checks(config).iter().map(|check| check.problems.len()).sum();
+    if problems > 0 {
+        eprintln!("\n{yellow}hint:{yellow:#} Found {problems} problem(s) with your config, run `{v}plz doctor{v:#}` for details");
+    }
+}
@@ -0,0 +1,239 @@
+use anstyle::{AnsiColor, Style};
+use tokio::time::{sleep, timeout};
+use std::time::{Duration, Instant};
+use std::io::{IsTerminal, Write};
+use tokio::task::JoinSet;
+use std::future::pending;
+use std::process::exit;
+use std::io;
+
+use plz::fetch::{self, Fetched, FetchProvider, FetchResult, SearchHit};
+use plz::{cache, Config};
+
+use super::http_client;
+
+#[derive(Default)]
+pub struct FetchOptions {
+    /// Stop waiting for the providers after this long.
+    pub deadline: Option<Duration>,
+    /// Ask the providers even if the cache has the links.
+    pub refresh: bool,
+    /// Search result to fetch, counting from 1, instead of asking.
+    pub pick: Option<usize>
+}
+
+pub fn print_result(result: &FetchResult) {
+    let bold = Style::new().bold();
+    println!("{bold}{}{bold:#}", result.title);
+    for group in &result.groups {
+        if let Some(name) = &group.name {
+            println!("{bold}{}:{bold:#}", name);
+        }
+        for link in &group.links {
+            println!(" {bold}{}:{bold:#} {}", link.host, link.url);
+        }
+    }
+}
+
+
+fn print_fetched(provider: &dyn FetchProvider, slug: &str, fetched: &Fetched, perf: Instant) {
+    let green = AnsiColor::BrightGreen.on_default().bold();
+    let success = format!("{green}success:{green:#} ");
+    let v = AnsiColor::BrightYellow.on_default();
+    let gray = AnsiColor::BrightBlack.on_default();
+    print_result(&fetched.result);
+    let cached = if fetched.cached { format!(" {gray}(cached){gray:#}") } else { String::new() };
+    println!(
+        "{success}Fetched {} for `{v}{}{v:#}` in {v}{:.2}{v:#}s{cached}\n",
+        provider.name(), slug, perf.elapsed().as_secs_f64()
+    );
+}
+
+
+/// `provider` followed by the fallback providers in the config, or exits if `provider` isn't one.
+pub fn provider_order(provider: &str, config: &Config) -> Vec<&'static dyn FetchProvider> {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let v = AnsiColor::BrightYellow.on_default();
+    match fetch::fallback_order(provider, &config.fetch) {
+        Ok(order) => order,
+        Err(err) => {
+            eprintln!("{error}{err}");
+            let available: Vec<String> = fetch::providers().iter().map(|provider| format!("{v}{}{v:#}", provider.name())).collect();
+            eprintln!("{error}Avaliable: [{}]", available.join(", "));
+            exit(1);
+        }
+    }
+}
+
+
+/// Fetches `name` from `provider`, falling back to the providers in the config if it fails,
+/// until one has it or `deadline` passes.
+pub async fn fetch(name: &str, provider: &str, config: &Config, options: &FetchOptions) {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let v = AnsiColor::BrightYellow.on_default();
+
+    let order = provider_order(provider, config);
+    let client = http_client(&config.http);
+    let attempts = async {
+        for provider in order {
+            let perf = Instant::now();
+            match fetch::fetch_cached(&client, &cache::cache_dir(), provider, name, &config.fetch, options.refresh).await {
+                Ok(fetched) => {
+                    print_fetched(provider, &provider.slug(name), &fetched, perf);
+                    return;
+                }
+                Err(err) => eprintln!("{error}{err}")
+            }
+        }
+    };
+    match options.deadline {
+        Some(deadline) => {
+            if timeout(deadline, attempts).await.is_err() {
+                eprintln!("{error}No provider had the links within {v}{}{v:#}s", deadline.as_secs());
+            }
+        }
+        None => attempts.await
+    }
+}
+
+
+/// Resolves once `deadline` has passed, never without one.
+async fn until(deadline: Option<Duration>) {
+    match deadline {
+        Some(deadline) => sleep(deadline).await,
+        None => pending().await
+    }
+}
+
+
+/// Fetches `name` from every provider at once, printing each one's links as they come in.
+pub async fn fetch_all(name: &str, config: &Config, options: &FetchOptions) {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let v = AnsiColor::BrightYellow.on_default();
+
+    let perf = Instant::now();
+    let mut tasks = fetch::fetch_all(&http_client(&config.http), &cache::cache_dir(), name, &config.fetch, options.refresh);
+    let mut waiting: Vec<&str> = fetch::providers().iter().map(|provider| provider.name()).collect();
+    let deadline = until(options.deadline);
+    tokio::pin!(deadline);
+
+    loop {
+        tokio::select! {
+            joined = tasks.join_next() => match joined {
+                Some(Ok((provider, result))) => {
+                    waiting.retain(|name| *name != provider.name());
+                    match result {
+                        Ok(fetched) => print_fetched(provider, &provider.slug(name), &fetched, perf),
+                        Err(err) => eprintln!("{error}{}: {err}", provider.name())
+                    }
+                }
+                Some(Err(err)) => eprintln!("{error}{err}"),
+                None => break
+            },
+            _ = &mut deadline => {
+                let waiting: Vec<String> = waiting.iter().map(|name| format!("{v}{name}{v:#}")).collect();
+                eprintln!("{error}Gave up on {} after {v}{:.2}{v:#}s", waiting.join(", "), perf.elapsed().as_secs_f64());
+                break;
+            }
+        }
+    }
+}
+
+
+/// The number of the search result to fetch, from `--pick` or by asking. `None` to fetch nothing.
+fn pick(options: &FetchOptions, count: usize) -> Option<usize> {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let v = AnsiColor::BrightYellow.on_default();
+    if let Some(pick) = options.pick {
+        if !(1..=count).contains(&pick) {
+            eprintln!("{error}There is no result `{v}{}{v:#}`, pick one from {v}1{v:#} to {v}{}{v:#}", pick, count);
+            exit(1);
+        }
+        return Some(pick - 1);
+    }
+    if !io::stdin().is_terminal() {
+        return None;
+    }
+    print!("Pick a number to fetch (enter to skip): ");
+    io::stdout().flush().unwrap();
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).ok();
+    input.trim().parse::<usize>().ok()
+        .filter(|choice| (1..=count).contains(choice))
+        .map(|choice| choice - 1)
+}
+
+
+/// Lists the games matching `query` on each of `providers`, then fetches the one picked.
+/// Providers still searching when the deadline passes are left out of the list.
+pub async fn search(query: &str, providers: Vec<&'static dyn FetchProvider>, config: &Config, options: &FetchOptions) {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let bold = Style::new().bold();
+    let gray = AnsiColor::BrightBlack.on_default();
+    let v = AnsiColor::BrightYellow.on_default();
+
+    let client = http_client(&config.http);
+    let mut tasks = JoinSet::new();
+    for (idx, &provider) in providers.iter().enumerate() {
+        let client = client.clone();
+        let query = query.to_owned();
+        tasks.spawn(async move { (idx, fetch::search(&client, provider, &query).await) });
+    }
+    let perf = Instant::now();
+    let deadline = until(options.deadline);
+    tokio::pin!(deadline);
+    let mut results = Vec::new();
+    loop {
+        tokio::select! {
+            joined = tasks.join_next() => match joined {
+                Some(Ok(result)) => results.push(result),
+                Some(Err(err)) => eprintln!("{error}{err}"),
+                None => break
+            },
+            _ = &mut deadline => {
+                let waiting: Vec<String> = providers.iter().enumerate()
+                    .filter(|(idx, _)| !results.iter().any(|(done, _)| done == idx))
+                    .map(|(_, provider)| format!("{v}{}{v:#}", provider.name()))
+                    .collect();
+                eprintln!("{error}Gave up on {} after {v}{:.2}{v:#}s", waiting.join(", "), perf.elapsed().as_secs_f64());
+                break;
+            }
+        }
+    }
+    results.sort_by_key(|(idx, _)| *idx);
+
+    let mut candidates: Vec<(&dyn FetchProvider, SearchHit)> = Vec::new();
+    for (idx, result) in results {
+        let provider = providers[idx];
+        match result {
+            Ok(hits) if hits.is_empty() => println!("{bold}{}:{bold:#} {gray}no results{gray:#}", provider.name()),
+            Ok(hits) => {
+                println!("{bold}{}:{bold:#}", provider.name());
+                for hit in hits {
+                    println!(" {bold}{}.{bold:#} {} {gray}({}){gray:#}", candidates.len() + 1, hit.title, hit.slug);
+                    candidates.push((provider, hit));
+                }
+            }
+            Err(err) => eprintln!("{error}{}: {err}", provider.name())
+        }
+    }
+    if candidates.is_empty() {
+        eprintln!("{error}No games found for `{v}{}{v:#}`", query);
+        return;
+    }
+
+    let Some(choice) = pick(options, candidates.len()) else {
+        return;
+    };
+    let (provider, hit) = &candidates[choice];
+    let perf = Instant::now();
+    match fetch::fetch_slug(&client, &cache::cache_dir(), *provider, &hit.slug, &config.fetch, options.refresh).await {
+        Ok(fetched) => print_fetched(*provider, &hit.slug, &fetched, perf),
+        Err(err) => eprintln!("{error}{err}")
+    }
+}
@@ -0,0 +1,208 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+use std::process::{Child, exit};
+use anstyle::{AnsiColor, Style};
+use std::path::Path;
+use std::thread;
+use std::io;
+
+use plz::groups::{self, Member};
+use plz::history::{now, record_session, Session};
+use plz::{hooks, instances, launch, logs, Config};
+
+use super::run;
+
+struct Running<'a> {
+    member: &'a Member,
+    child: Child,
+    started: u64,
+    perf: Instant
+}
+
+/// Set by Ctrl+C, the members are in their own process groups and don't get it themselves.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+
+fn spawn_member(config: &Config, member: &Member, allow_multiple: bool) -> io::Result<Child> {
+    let mut command = match &member.alias {
+        // Outlives the group, so a supervisor records its session like `run --detach` does.
+        Some(alias) if member.keep_running => {
+            config.resolve(alias)?;
+            let (_, log) = logs::new_log_file(alias, &config.logs)?;
+            run::supervise_command(alias, None, allow_multiple, log)?
+        }
+        Some(alias) => {
+            launch::game_command(config, alias, &config.resolve(alias)?)?
+        }
+        None => hooks::shell(member.command.as_deref().unwrap_or_default())
+    };
+    launch::new_process_group(&mut command);
+    match &member.alias {
+        Some(alias) if !member.keep_running => instances::start(alias, allow_multiple, || command.spawn()),
+        _ => command.spawn()
+    }
+}
+
+
+fn port_open(port: &str) -> bool {
+    let address = if port.contains(':') { port.to_owned() } else { format!("127.0.0.1:{port}") };
+    address.to_socket_addrs()
+        .map(|mut addrs| addrs.any(|addr| TcpStream::connect_timeout(&addr, Duration::from_millis(500)).is_ok()))
+        .unwrap_or(false)
+}
+
+
+/// Blocks until the member's port or file is there.
+fn wait_ready(config: &Config, running: &mut Running) -> Result<(), String> {
+    let member = running.member;
+    let timeout = Duration::from_secs(member.wait_timeout.unwrap_or(60));
+    let file = member.wait_file.as_ref().map(|file| config.expand_path(file));
+    let ready = || {
+        member.wait_port.as_deref().is_none_or(port_open)
+            && file.as_ref().is_none_or(|file| Path::new(file).exists())
+    };
+    let waited = Instant::now();
+    while !ready() {
+        if INTERRUPTED.load(Ordering::Relaxed) {
+            return Err(String::from("Interrupted"));
+        }
+        if let Ok(Some(status)) = running.child.try_wait() {
+            return Err(format!("`{}` exited with {} before it was ready", member.label(), status));
+        }
+        if waited.elapsed() > timeout {
+            return Err(format!("`{}` wasn't ready after {}s", member.label(), timeout.as_secs()));
+        }
+        thread::sleep(Duration::from_millis(250));
+    }
+    Ok(())
+}
+
+
+/// Records the session of an alias member that exited, and runs its post_exit hooks.
+/// Members kept running have a supervisor that does that.
+fn finish(config: &Config, running: &Running, exit_code: Option<i32>) {
+    let Some(alias) = running.member.alias.as_ref().filter(|_| !running.member.keep_running) else {
+        return;
+    };
+    instances::unregister(running.child.id());
+    let session = Session {
+        alias: alias.clone(),
+        started: running.started,
+        duration: running.perf.elapsed().as_secs(),
+        exit_code
+    };
+    if let Err(err) = record_session(session.clone()) {
+        let red = AnsiColor::BrightRed.on_default().bold();
+        eprintln!("{red}error:{red:#} Failed to save the history file. {err}");
+    }
+    run::post_exit(config, alias, Path::new(&config.alias_path(alias).unwrap_or_default()), Some(&session));
+}
+
+
+/// How long members get to exit on their own before they're killed.
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Stops every member that isn't marked `keep_running`.
+fn stop(config: &Config, running: Vec<Running>) {
+    let gray = AnsiColor::BrightBlack.on_default();
+    for mut running in running {
+        if running.member.keep_running {
+            println!("{gray}Leaving `{}` running{gray:#}", running.member.label());
+            continue;
+        }
+        println!("{gray}Stopping `{}`{gray:#}", running.member.label());
+        launch::kill_tree(running.child.id(), false).ok();
+        let asked = Instant::now();
+        while matches!(running.child.try_wait(), Ok(None)) && asked.elapsed() < STOP_TIMEOUT {
+            thread::sleep(Duration::from_millis(100));
+        }
+        // Whatever is left of the tree gets killed, even if the member itself exited.
+        launch::kill_tree(running.child.id(), true).ok();
+        let code = running.child.wait().ok().and_then(|status| status.code());
+        finish(config, &running, code);
+    }
+}
+
+
+/// Starts the members of `name` in order and stops them together once the primary exits.
+pub fn run(config: &Config, name: &str, allow_multiple: bool) {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let v = AnsiColor::BrightYellow.on_default();
+    let bold = Style::new().bold();
+    let group = &config.groups[name];
+    let problems = groups::validate(config, name);
+    if !problems.is_empty() {
+        for problem in problems {
+            eprintln!("{error}{problem}");
+        }
+        exit(1);
+    }
+
+    if !allow_multiple {
+        for alias in group.members.iter().filter_map(|member| member.alias.as_ref()) {
+            run::refuse_if_running(alias);
+        }
+    }
+
+    ctrlc::set_handler(|| INTERRUPTED.store(true, Ordering::Relaxed)).ok();
+
+    // Hooks run before anything starts, so a failing one doesn't leave members behind.
+    for alias in group.members.iter().filter_map(|member| member.alias.as_ref()) {
+        run::pre_launch(config, alias, Path::new(&config.alias_path(alias).unwrap_or_default()));
+    }
+
+    let primary = &group.members[groups::primary_index(group)];
+    let mut running: Vec<Running> = Vec::new();
+    for member in &group.members {
+        let delay = Instant::now();
+        while delay.elapsed().as_secs() < member.delay && !INTERRUPTED.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(100));
+        }
+        if INTERRUPTED.load(Ordering::Relaxed) {
+            stop(config, running);
+            exit(130);
+        }
+        println!("{bold}Running:{bold:#} `{v}{}{v:#}`", member.label());
+        let child = match spawn_member(config, member, allow_multiple) {
+            Ok(child) => child,
+            Err(err) => {
+                eprintln!("{error}Failed to run `{v}{}{v:#}`: {}", member.label(), err);
+                stop(config, running);
+                exit(1);
+            }
+        };
+        running.push(Running { member, child, started: now(), perf: Instant::now() });
+        if let Err(err) = wait_ready(config, running.last_mut().unwrap()) {
+            eprintln!("{error}{err}");
+            stop(config, running);
+            exit(1);
+        }
+    }
+
+    loop {
+        let mut primary_exited = false;
+        let mut idx = 0;
+        while idx < running.len() {
+            match running[idx].child.try_wait() {
+                Ok(Some(status)) => {
+                    let exited = running.remove(idx);
+                    primary_exited |= std::ptr::eq(exited.member, primary);
+                    finish(config, &exited, status.code());
+                }
+                _ => idx += 1
+            }
+        }
+        if primary_exited || INTERRUPTED.load(Ordering::Relaxed) {
+            break;
+        }
+        thread::sleep(Duration::from_millis(250));
+    }
+    if INTERRUPTED.load(Ordering::Relaxed) {
+        stop(config, running);
+        exit(130);
+    }
+    println!("{bold}Exited:{bold:#} `{v}{}{v:#}`", primary.label());
+    stop(config, running);
+}
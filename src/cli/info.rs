@@ -0,0 +1,83 @@
+use anstyle::{AnsiColor, Style};
+use std::process::exit;
+
+use plz::info::{self, GamePath};
+use plz::{cache, Config};
+
+use super::{http_client, save_config};
+
+fn print_paths(heading: &str, paths: &[GamePath]) {
+    let bold = Style::new().bold();
+    let gray = AnsiColor::BrightBlack.on_default();
+    println!("{bold}{heading}:{bold:#}");
+    if paths.is_empty() {
+        println!(" {gray}none listed{gray:#}");
+    }
+    for path in paths {
+        println!(" {bold}{}:{bold:#} {}", path.platform, path.display());
+    }
+}
+
+
+/// `plz info`: what PCGamingWiki knows about the game behind `alias`, looked up by `title`
+/// or the name of its folder. `add_saves` adds the save locations it lists to the alias.
+pub async fn info(config: &mut Config, alias: &str, title: Option<&str>, refresh: bool, add_saves: bool) {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let green = AnsiColor::BrightGreen.on_default().bold();
+    let success = format!("{green}success:{green:#} ");
+    let yellow = AnsiColor::BrightYellow.on_default().bold();
+    let bold = Style::new().bold();
+    let gray = AnsiColor::BrightBlack.on_default();
+    let v = AnsiColor::BrightYellow.on_default();
+
+    if !config.aliases.contains_key(alias) {
+        eprintln!("{error}Alias `{v}{}{v:#}` not found", alias);
+        exit(1);
+    }
+    let title = title.map(str::to_owned).unwrap_or_else(|| info::guess_title(config, alias));
+    let client = http_client(&config.http);
+    let game = match info::lookup_cached(&client, &cache::cache_dir(), &config.info, &title, refresh).await {
+        Ok(game) => game,
+        Err(err) => {
+            eprintln!("{error}{}", err);
+            eprintln!("{error}If the game is called something else, pass its name with `{v}--title{v:#}`");
+            exit(1);
+        }
+    };
+
+    println!("{bold}{}{bold:#} {gray}({}){gray:#}", game.page, game.url);
+    if !game.engines.is_empty() {
+        println!(" {bold}Engine:{bold:#} {}", game.engines.join(", "));
+    }
+    if let Some(appid) = game.steam_appid {
+        println!(" {bold}Steam app ID:{bold:#} {}", appid);
+    }
+    print_paths("Save game locations", &game.saves);
+    print_paths("Config file locations", &game.config);
+    if !game.fixes.is_empty() {
+        println!("{bold}Fixes:{bold:#}");
+        for fix in &game.fixes {
+            println!(" - {}", fix);
+        }
+    }
+
+    let locations: Vec<String> = game.save_locations(config, alias).unwrap_or_default().into_iter()
+        .filter(|location| !config.aliases[alias].saves.contains(location))
+        .collect();
+    if locations.is_empty() {
+        return;
+    }
+    if !add_saves {
+        println!(
+            "\n{yellow}hint:{yellow:#} Run `{v}plz info {} --add-saves{v:#}` to back up {} save location(s) with `plz saves`",
+            alias, locations.len()
+        );
+        return;
+    }
+    for location in locations {
+        println!("{success}Added save location `{v}{}{v:#}` to `{v}{}{v:#}`", location, alias);
+        config.aliases.get_mut(alias).unwrap().saves.push(location);
+    }
+    save_config(config);
+}
@@ -0,0 +1,85 @@
+use std::io::{Read, Seek, SeekFrom};
+use anstyle::{AnsiColor, Style};
+use std::time::Duration;
+use std::process::exit;
+use std::path::PathBuf;
+use std::fs::File;
+use std::fs;
+use std::io;
+
+use plz::logs;
+
+fn alias_logs(alias: &str) -> Vec<(PathBuf, u64)> {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let v = AnsiColor::BrightYellow.on_default();
+    let logs = logs::list_alias_logs(alias).unwrap_or_default();
+    if logs.is_empty() {
+        eprintln!("{error}No logs found for alias `{v}{}{v:#}`", alias);
+        exit(1);
+    }
+    logs
+}
+
+
+pub fn print_list(alias: &str) {
+    let bold = Style::new().bold();
+    let gray = AnsiColor::BrightBlack.on_default();
+    println!("{bold}Logs of `{alias}`:{bold:#}");
+    for (path, size) in alias_logs(alias) {
+        println!(" {} {gray}({:.1} KiB){gray:#}", path.display(), size as f64 / 1024.0);
+    }
+}
+
+
+pub fn print_last(alias: &str) {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let (path, _) = alias_logs(alias).pop().unwrap();
+    let mut file = match File::open(&path) {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("{error}Failed to open `{}`. {err}", path.display());
+            exit(1);
+        }
+    };
+    if let Err(err) = io::copy(&mut file, &mut io::stdout()) {
+        eprintln!("{error}Failed to read `{}`. {err}", path.display());
+    }
+}
+
+
+/// Prints the newest log and keeps printing whatever gets appended to it, like `tail -f`.
+pub fn follow(alias: &str) -> ! {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let (path, _) = alias_logs(alias).pop().unwrap();
+    let mut file = match File::open(&path) {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("{error}Failed to open `{}`. {err}", path.display());
+            exit(1);
+        }
+    };
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match file.read_to_end(&mut buf) {
+            Ok(0) => std::thread::sleep(Duration::from_millis(250)),
+            Ok(_) => {
+                io::Write::write_all(&mut io::stdout(), &buf).ok();
+                io::Write::flush(&mut io::stdout()).ok();
+            }
+            Err(err) => {
+                eprintln!("{error}Failed to read `{}`. {err}", path.display());
+                exit(1);
+            }
+        }
+        // The log got truncated or replaced, start over.
+        if let (Ok(pos), Ok(meta)) = (file.stream_position(), fs::metadata(&path)) {
+            if meta.len() < pos {
+                file.seek(SeekFrom::Start(0)).ok();
+            }
+        }
+    }
+}
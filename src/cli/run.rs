@@ -0,0 +1,332 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::process::{Command, Stdio, exit};
+use anstyle::{AnsiColor, Style};
+use std::path::{Path, PathBuf};
+use std::fs::File;
+use std::io;
+
+use plz::history::{self, Session};
+use plz::sync::{self, SyncStatus};
+use plz::{launch, logs, mods, repair, Config};
+
+use super::user_input;
+
+#[derive(Default)]
+pub struct RunOptions {
+    /// Return immediately and leave the game to a supervisor process.
+    pub detach: bool,
+    /// Capture the game's output into a log file, even if the alias doesn't ask for it.
+    pub log: bool,
+    /// Mod profile to deploy before launching.
+    pub profile: Option<String>,
+    /// Switch back to the previously enabled mods once the game exits.
+    pub revert: bool,
+    /// Start the game even if it's already running.
+    pub allow_multiple: bool
+}
+
+/// The pid of the game being waited for. It's in a process group of its own, so the
+/// terminal's Ctrl+C is passed on to it from here.
+static GAME: AtomicU32 = AtomicU32::new(0);
+
+
+fn enter_game_dir(path: &Path) {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let v = AnsiColor::BrightYellow.on_default();
+    let dir = match path.parent() {
+        Some(path) => path,
+        None => {
+            eprintln!("{error}Path: `{v}{}{v:#}`. Failed to get the parent of path", path.display());
+            exit(1);
+        }
+    };
+    if let Err(err) = std::env::set_current_dir(dir) {
+        eprintln!("{error}Path: `{v}{}{v:#}`. {}", path.display(), err);
+        exit(1);
+    }
+}
+
+
+/// Warns (or asks, with `on_conflict = "prompt"`) when the synced saves are newer or diverged.
+fn check_sync(config: &Config, alias: &str) {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let yellow = AnsiColor::BrightYellow.on_default().bold();
+    let warning = format!("{yellow}warning:{yellow:#} ");
+    let green = AnsiColor::BrightGreen.on_default().bold();
+    let success = format!("{green}success:{green:#} ");
+    let v = AnsiColor::BrightYellow.on_default();
+    let prompt = config.saves.on_conflict == "prompt";
+
+    let (status, synced) = match sync::status(config, alias) {
+        Ok(Some((status, Some(synced)))) => (status, synced),
+        Ok(_) => return,
+        Err(err) => {
+            eprintln!("{warning}Failed to compare saves with sync_dir. {err}");
+            return;
+        }
+    };
+    match status {
+        SyncStatus::SyncNewer => {
+            eprintln!("{warning}Synced saves from `{v}{}{v:#}` are newer than the local ones", synced.timestamp);
+            if prompt && user_input(String::from("Restore them before launching? (y/n) ")) {
+                match sync::pull(config, alias, &synced) {
+                    Ok(_) => println!("{success}Restored saves from `{v}{}{v:#}`", synced.timestamp),
+                    Err(err) => {
+                        eprintln!("{error}Failed to restore saves. {err}");
+                        exit(1);
+                    }
+                }
+            }
+        }
+        SyncStatus::Diverged => {
+            eprintln!("{warning}Local saves and synced saves from `{v}{}{v:#}` have diverged", synced.timestamp);
+            if prompt && !user_input(String::from("Launch anyway? (y/n) ")) {
+                exit(1);
+            }
+        }
+        _ => {}
+    }
+}
+
+
+/// Runs the pre-launch hooks, exiting if one of them fails.
+pub fn pre_launch(config: &Config, alias: &str, path: &Path) {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    if let Err(err) = launch::pre_launch(config, alias, path) {
+        eprintln!("{error}{err}");
+        exit(1);
+    }
+}
+
+
+/// Backs up the saves and runs the post-exit hooks, reporting how that went.
+pub fn post_exit(config: &Config, alias: &str, path: &Path, session: Option<&Session>) {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let yellow = AnsiColor::BrightYellow.on_default().bold();
+    let warning = format!("{yellow}warning:{yellow:#} ");
+    let green = AnsiColor::BrightGreen.on_default().bold();
+    let success = format!("{green}success:{green:#} ");
+    let after = launch::post_exit(config, alias, path, session);
+    match after.backup {
+        Some(Ok(backup)) => println!("{success}Backed up saves to `{}`", backup.archive.display()),
+        Some(Err(err)) => eprintln!("{error}Failed to back up saves. {err}"),
+        None => {}
+    }
+    for failed in after.failed_hooks {
+        eprintln!("{warning}{failed}");
+    }
+}
+
+
+fn apply_profile(config: &Config, alias: &str, profile: &str) -> Vec<String> {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let bold = Style::new().bold();
+    let v = AnsiColor::BrightYellow.on_default();
+    match mods::apply_profile(config, &mods::mods_dir(), alias, profile) {
+        Ok(previous) => {
+            println!("{bold}Profile:{bold:#} `{v}{}:{}{v:#}`", alias, profile);
+            previous
+        }
+        Err(err) => {
+            eprintln!("{error}Failed to apply profile `{v}{}:{}{v:#}`. {}", alias, profile, err);
+            exit(1);
+        }
+    }
+}
+
+
+fn revert_profile(config: &Config, alias: &str, mods: &[String]) {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    if let Err(err) = mods::deploy_set(config, &mods::mods_dir(), alias, mods) {
+        eprintln!("{error}Failed to switch back to the previous mods. {err}");
+    }
+}
+
+
+/// Exits if `alias` is already running, used unless `--allow-multiple` is passed.
+pub fn refuse_if_running(alias: &str) {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let v = AnsiColor::BrightYellow.on_default();
+    if let Err(err) = launch::ensure_not_running(alias) {
+        eprintln!(
+            "{error}{}, pass `{v}--allow-multiple{v:#}` to start another one or stop it with `{v}plz kill {}{v:#}`",
+            err, alias
+        );
+        exit(1);
+    }
+}
+
+
+fn open_log(config: &Config, alias: &str) -> (PathBuf, File) {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    match logs::new_log_file(alias, &config.logs) {
+        Ok(log) => log,
+        Err(err) => {
+            eprintln!("{error}Failed to create a log file. {err}");
+            exit(1);
+        }
+    }
+}
+
+
+/// Runs the game, waits for it to exit and records the session. Ctrl+C asks the game
+/// to exit instead of leaving it behind.
+fn run_and_record(config: &Config, alias: &str, log: Option<File>, allow_multiple: bool) -> Option<Session> {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let v = AnsiColor::BrightYellow.on_default();
+    ctrlc::set_handler(|| {
+        let pid = GAME.load(Ordering::Relaxed);
+        if pid != 0 {
+            launch::kill_tree(pid, false).ok();
+        }
+    }).ok();
+    let started = launch::start(config, alias, log, allow_multiple).and_then(|game| {
+        GAME.store(game.pid(), Ordering::Relaxed);
+        game.wait()
+    });
+    match started {
+        Ok(session) => {
+            if let Err(err) = history::record_session(session.clone()) {
+                eprintln!("{error}Failed to save the history file. {err}");
+            }
+            Some(session)
+        }
+        Err(err) => {
+            eprintln!("{error}Failed to run alias `{v}{}{v:#}`: {}", alias, err);
+            None
+        }
+    }
+}
+
+
+pub fn run_alias(config: &Config, alias: &str, options: &RunOptions) {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let v = AnsiColor::BrightYellow.on_default();
+    let bold = Style::new().bold();
+    let Some(entry) = config.aliases.get(alias) else {
+        eprintln!("{error}Alias `{v}{}{v:#}` not found", alias);
+        exit(1);
+    };
+    if !options.allow_multiple {
+        refuse_if_running(alias);
+    }
+    let target = config.expand_path(&entry.path);
+    let path = Path::new(&target);
+    enter_game_dir(path);
+    repair::record_fingerprint(&target).ok();
+    check_sync(config, alias);
+    let previous_mods = options.profile.as_ref().map(|profile| apply_profile(config, alias, profile));
+    if let Err(err) = launch::pre_launch(config, alias, path) {
+        eprintln!("{error}{err}");
+        // The game never ran, so it doesn't keep the profile either.
+        if let Some(mods) = previous_mods {
+            revert_profile(config, alias, &mods);
+        }
+        exit(1);
+    }
+    let revert_mods = previous_mods.filter(|_| options.revert);
+
+    if options.detach {
+        spawn_detached(config, alias, path, revert_mods.as_deref(), options.allow_multiple);
+        return;
+    }
+
+    let log = if options.log || entry.log {
+        let (log_path, log) = open_log(config, alias);
+        println!("{bold}Logging to:{bold:#} `{v}{}{v:#}`", log_path.display());
+        Some(log)
+    } else {
+        None
+    };
+    println!("{bold}Running:{bold:#} `{v}{}{v:#}`", path.display());
+    let session = run_and_record(config, alias, log, options.allow_multiple);
+    post_exit(config, alias, path, session.as_ref());
+    if let Some(mods) = revert_mods {
+        revert_profile(config, alias, &mods);
+    }
+}
+
+
+/// Entry point of the hidden `supervise` subcommand, which babysits a detached game.
+/// Its output already goes to a log file, so the game's output is not captured separately.
+pub fn supervise(config: &Config, alias: &str, revert_mods: Option<Vec<String>>, allow_multiple: bool) -> ! {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let Some(target) = config.alias_path(alias) else {
+        eprintln!("{error}Alias `{}` not found", alias);
+        exit(1);
+    };
+    let path = Path::new(&target);
+    enter_game_dir(path);
+    println!("[plz] Started `{}` at {}", path.display(), chrono::Local::now());
+    let session = run_and_record(config, alias, None, allow_multiple);
+    let code = session.as_ref().and_then(|session| session.exit_code);
+    match code {
+        Some(code) => println!("[plz] Exited with code {} at {}", code, chrono::Local::now()),
+        None => println!("[plz] Exited without an exit code at {}", chrono::Local::now())
+    }
+    post_exit(config, alias, path, session.as_ref());
+    if let Some(mods) = revert_mods {
+        revert_profile(config, alias, &mods);
+    }
+    exit(code.unwrap_or(1));
+}
+
+
+/// The hidden `supervise` subcommand for `alias`, writing to `log`.
+pub fn supervise_command(alias: &str, revert_mods: Option<&[String]>, allow_multiple: bool, log: File) -> io::Result<Command> {
+    let mut command = Command::new(std::env::current_exe()?);
+    command.arg("supervise").arg(alias);
+    if allow_multiple {
+        command.arg("--allow-multiple");
+    }
+    if let Some(mods) = revert_mods {
+        // An empty value still tells the supervisor to revert, to no mods at all.
+        command.arg("--revert-mods").arg("");
+        for name in mods {
+            command.arg("--revert-mods").arg(name);
+        }
+    }
+    command
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    Ok(command)
+}
+
+
+fn spawn_detached(config: &Config, alias: &str, path: &Path, revert_mods: Option<&[String]>, allow_multiple: bool) {
+    let red = AnsiColor::BrightRed.on_default().bold();
+    let error = format!("{red}error:{red:#} ");
+    let green = AnsiColor::BrightGreen.on_default().bold();
+    let success = format!("{green}success:{green:#} ");
+    let v = AnsiColor::BrightYellow.on_default();
+
+    let (log_path, log) = open_log(config, alias);
+    let mut command = match supervise_command(alias, revert_mods, allow_multiple, log) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("{error}Failed to get executable file. {err}");
+            exit(1);
+        }
+    };
+    launch::detach_from_terminal(&mut command);
+
+    match command.spawn() {
+        Ok(child) => println!(
+            "{success}Launched `{v}{}{v:#}` in the background (pid {v}{}{v:#}), output goes to `{v}{}{v:#}`",
+            alias, child.id(), log_path.display()
+        ),
+        Err(err) => eprintln!("{error}Failed to run alias `{v}{}{v:#}` from `{v}{}{v:#}`: {}", alias, path.display(), err)
+    }
+}
@@ -0,0 +1,44 @@
+use anstyle::AnsiColor;
+
+use plz::updates::{self, UpdateSettings};
+use plz::http::{self, HttpSettings};
+use plz::selfupdate;
+
+/// `plz self-update`: replaces this executable with the newest release on the configured
+/// channel, after checking it against the checksum published with it.
+pub async fn run(settings: &UpdateSettings, http: &HttpSettings, check: bool) -> Result<(), String> {
+    let green = AnsiColor::BrightGreen.on_default().bold();
+    let v = AnsiColor::BrightYellow.on_default();
+    let current = env!("CARGO_PKG_VERSION");
+    let client = http::client(http)?;
+    let release = updates::fetch_latest(&client, settings).await?;
+    let newer = updates::is_newer(&release.tag_name, current);
+    let asset = selfupdate::pick_asset(&release.assets);
+
+    if check {
+        println!("Current: {v}v{current}{v:#}");
+        println!("Latest ({}): {v}{}{v:#}", settings.channel, release.tag_name);
+        match asset {
+            Some(asset) => println!("Asset: {v}{}{v:#}", asset.name),
+            None => println!("Asset: none for {}-{}", std::env::consts::OS, std::env::consts::ARCH)
+        }
+        if newer {
+            println!("{green}Run `plz self-update` to update{green:#}");
+        }
+        return Ok(());
+    }
+    if !newer {
+        println!("plz {v}v{current}{v:#} is up to date");
+        return Ok(());
+    }
+
+    let asset = asset.ok_or_else(|| format!(
+        "Release {} has no build for {}-{}", release.tag_name, std::env::consts::OS, std::env::consts::ARCH
+    ))?;
+    println!("Downloading `{v}{}{v:#}`", asset.name);
+    let exe = std::env::current_exe().map_err(|err| err.to_string())?;
+    let old = selfupdate::update(&client, &release, asset, &exe).await?;
+    println!("{green}Updated plz from v{current} to {}{green:#}", release.tag_name);
+    println!("The previous version is kept at `{v}{}{v:#}`, `plz self-update --rollback` puts it back", old.display());
+    Ok(())
+}
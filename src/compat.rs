@@ -0,0 +1,303 @@
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::http::Client;
+use crate::{cache, Alias};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CompatSettings {
+    /// Where ProtonDB keeps the summary of every game, as `<summary_url>/<app id>.json`.
+    #[serde(default = "default_summary_url")]
+    pub summary_url: String,
+    /// Where the reports of a game can be read, as `<reports_url>/<app id>/reports`.
+    #[serde(default = "default_reports_url")]
+    pub reports_url: String,
+    /// How long looked up ratings are reused without asking ProtonDB again, in hours.
+    #[serde(default = "default_cache_ttl_hours")]
+    pub cache_ttl_hours: u64
+}
+
+impl Default for CompatSettings {
+    fn default() -> Self {
+        CompatSettings {
+            summary_url: default_summary_url(),
+            reports_url: default_reports_url(),
+            cache_ttl_hours: default_cache_ttl_hours()
+        }
+    }
+}
+
+fn default_summary_url() -> String {
+    String::from("https://www.protondb.com/api/v1/reports/summaries")
+}
+
+fn default_reports_url() -> String {
+    String::from("https://protondb.max-p.me/games")
+}
+
+fn default_cache_ttl_hours() -> u64 {
+    24
+}
+
+/// The cache namespace of looked up ratings.
+const CACHE: &str = "compat";
+
+/// How many of the newest working reports the recommended Proton version is picked from.
+const RECENT_REPORTS: usize = 50;
+
+/// How well a game runs on Linux according to ProtonDB.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Compat {
+    pub steam_appid: u32,
+    /// `native`, `platinum`, `gold`, `silver`, `bronze`, `borked` or `pending`.
+    pub tier: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trending_tier: Option<String>,
+    /// How sure ProtonDB is about the tier, like `strong`.
+    pub confidence: String,
+    /// How many reports the tier comes from.
+    pub total: u64,
+    /// The Proton version recent reports that ran well used the most.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proton_version: Option<String>
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Summary {
+    tier: String,
+    trending_tier: Option<String>,
+    #[serde(default)]
+    confidence: String,
+    #[serde(default)]
+    total: u64
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Report {
+    #[serde(default)]
+    rating: String,
+    #[serde(default)]
+    proton_version: String,
+    #[serde(default)]
+    timestamp: u64
+}
+
+/// Runner settings that should make a game run the way ProtonDB says it can.
+#[derive(PartialEq, Debug)]
+pub struct Suggestion {
+    pub runner: String,
+    pub env: Vec<(String, String)>
+}
+
+
+impl Compat {
+    /// Runs the game through Proton with umu-launcher, using the recommended version when
+    /// it's a GE-Proton one umu can download and the newest GE-Proton otherwise.
+    /// `None` for native games and ones that don't run at all.
+    pub fn suggestion(&self) -> Option<Suggestion> {
+        if matches!(self.tier.as_str(), "native" | "borked") {
+            return None;
+        }
+        let proton = self.proton_version.as_deref()
+            .filter(|version| version.starts_with("GE-Proton"))
+            .unwrap_or("GE-Proton");
+        Some(Suggestion {
+            runner: String::from("umu-run"),
+            env: vec![
+                (String::from("GAMEID"), format!("umu-{}", self.steam_appid)),
+                (String::from("PROTONPATH"), proton.to_owned())
+            ]
+        })
+    }
+}
+
+
+impl Suggestion {
+    /// Whether `alias` already runs like this.
+    pub fn is_applied(&self, alias: &Alias) -> bool {
+        alias.runner.as_deref() == Some(self.runner.as_str())
+            && self.env.iter().all(|(name, value)| alias.env.get(name) == Some(value))
+    }
+
+    pub fn apply(&self, alias: &mut Alias) {
+        alias.runner = Some(self.runner.clone());
+        alias.env.extend(self.env.iter().cloned());
+    }
+}
+
+
+/// The version most of the newest reports rated platinum or gold ran with, the newer one on a tie.
+fn recommended_version(mut reports: Vec<Report>) -> Option<String> {
+    reports.retain(|report| {
+        matches!(report.rating.to_lowercase().as_str(), "platinum" | "gold") && !report.proton_version.trim().is_empty()
+    });
+    reports.sort_by_key(|report| std::cmp::Reverse(report.timestamp));
+    let mut counts: HashMap<&str, (usize, u64)> = HashMap::new();
+    for report in reports.iter().take(RECENT_REPORTS) {
+        let count = counts.entry(report.proton_version.trim()).or_insert((0, report.timestamp));
+        count.0 += 1;
+    }
+    counts.into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(version, _)| version.to_owned())
+}
+
+
+/// Asks ProtonDB how the game with the Steam app ID `appid` runs. The recommended version
+/// is left out when the reports can't be read, the tier is enough to go on.
+pub async fn lookup(client: &Client, settings: &CompatSettings, appid: u32) -> Result<Compat, String> {
+    let url = format!("{}/{appid}.json", settings.summary_url.trim_end_matches('/'));
+    let res = client.get(&url).await.map_err(|err| format!("Error sending request: {err}"))?;
+    match res.status() {
+        StatusCode::NOT_FOUND => return Err(format!("ProtonDB has no reports for app {appid}")),
+        status if !status.is_success() => return Err(format!("ProtonDB answered with {status}")),
+        _ => {}
+    }
+    let summary: Summary = res.json().await.map_err(|err| format!("ProtonDB sent something unexpected. {err}"))?;
+
+    let url = format!("{}/{appid}/reports", settings.reports_url.trim_end_matches('/'));
+    let reports = match client.get(&url).await {
+        Ok(res) if res.status().is_success() => res.json::<Vec<Report>>().await.unwrap_or_default(),
+        _ => Vec::new()
+    };
+    Ok(Compat {
+        steam_appid: appid,
+        tier: summary.tier,
+        trending_tier: summary.trending_tier,
+        confidence: summary.confidence,
+        total: summary.total,
+        proton_version: recommended_version(reports)
+    })
+}
+
+
+/// `lookup`, reusing the last answer for `settings.cache_ttl_hours` unless `refresh` is set.
+pub async fn lookup_cached(client: &Client, cache_dir: &Path, settings: &CompatSettings, appid: u32, refresh: bool) -> Result<Compat, String> {
+    let key = appid.to_string();
+    if !refresh {
+        let cached: Option<cache::Entry<Compat>> = cache::read(cache_dir, CACHE, &key);
+        if let Some(entry) = cached.filter(|entry| entry.is_fresh(settings.cache_ttl_hours * 60 * 60)) {
+            return Ok(entry.value);
+        }
+    }
+    let entry = cache::Entry::new(&key, lookup(client, settings, appid).await?, &HeaderMap::new());
+    cache::write(cache_dir, CACHE, &entry).ok();
+    Ok(entry.value)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{http, test_server};
+    use crate::test_dir::TestDir;
+
+    const SUMMARY: &str = r#"{"bestReportedTier":"platinum","confidence":"strong","score":0.84,"tier":"gold","total":212,"trendingTier":"platinum"}"#;
+
+    const REPORTS: &str = r#"[
+        {"appId":"367520","rating":"Platinum","protonVersion":"GE-Proton9-5","timestamp":1717000000},
+        {"appId":"367520","rating":"Gold","protonVersion":"8.0-5","timestamp":1716000000},
+        {"appId":"367520","rating":"Platinum","protonVersion":"8.0-5","timestamp":1715000000},
+        {"appId":"367520","rating":"Borked","protonVersion":"9.0-1","timestamp":1718000000},
+        {"appId":"367520","rating":"Borked","protonVersion":"9.0-1","timestamp":1718500000},
+        {"appId":"367520","rating":"Platinum","protonVersion":"","timestamp":1719000000}
+    ]"#;
+
+    #[tokio::test]
+    async fn looks_ratings_up_on_the_api() {
+        let base = test_server::serve(vec![
+            ("/summaries/367520.json", 200, SUMMARY.as_bytes().to_vec()),
+            ("/games/367520/reports", 200, REPORTS.as_bytes().to_vec()),
+            ("/summaries/70.json", 200, br#"{"tier":"native","confidence":"good","total":3}"#.to_vec())
+        ]).await;
+        let settings = CompatSettings {
+            summary_url: format!("{base}/summaries"),
+            reports_url: format!("{base}/games/"),
+            ..Default::default()
+        };
+        let client = http::client(&http::HttpSettings::default()).unwrap();
+
+        let compat = lookup(&client, &settings, 367520).await.unwrap();
+        assert_eq!(compat.tier, "gold");
+        assert_eq!(compat.trending_tier.as_deref(), Some("platinum"));
+        assert_eq!(compat.confidence, "strong");
+        assert_eq!(compat.total, 212);
+        assert_eq!(compat.proton_version.as_deref(), Some("8.0-5"));
+
+        // Without reports there's just no recommended version.
+        let compat = lookup(&client, &settings, 70).await.unwrap();
+        assert_eq!(compat.tier, "native");
+        assert_eq!(compat.proton_version, None);
+
+        let err = lookup(&client, &settings, 1).await.err().unwrap();
+        assert_eq!(err, "ProtonDB has no reports for app 1");
+    }
+
+    #[tokio::test]
+    async fn reuses_looked_up_ratings() {
+        let base = test_server::serve(vec![
+            ("/summaries/367520.json", 200, SUMMARY.as_bytes().to_vec()),
+            ("/games/367520/reports", 200, REPORTS.as_bytes().to_vec())
+        ]).await;
+        let settings = CompatSettings {
+            summary_url: format!("{base}/summaries"),
+            reports_url: format!("{base}/games"),
+            ..Default::default()
+        };
+        let client = http::client(&http::HttpSettings::default()).unwrap();
+        let dir = TestDir::new();
+
+        let compat = lookup_cached(&client, dir.path(), &settings, 367520, false).await.unwrap();
+        assert_eq!(compat.tier, "gold");
+
+        // Changing the cached rating shows whether ProtonDB is asked again.
+        let mut entry: cache::Entry<Compat> = cache::read(dir.path(), CACHE, "367520").unwrap();
+        entry.value.tier = String::from("borked");
+        cache::write(dir.path(), CACHE, &entry).unwrap();
+        let compat = lookup_cached(&client, dir.path(), &settings, 367520, false).await.unwrap();
+        assert_eq!(compat.tier, "borked");
+
+        let compat = lookup_cached(&client, dir.path(), &settings, 367520, true).await.unwrap();
+        assert_eq!(compat.tier, "gold");
+
+        entry.stored = 0;
+        cache::write(dir.path(), CACHE, &entry).unwrap();
+        let compat = lookup_cached(&client, dir.path(), &settings, 367520, false).await.unwrap();
+        assert_eq!(compat.tier, "gold");
+    }
+
+    #[test]
+    fn suggests_runner_settings() {
+        let compat = |tier: &str, proton_version: Option<&str>| Compat {
+            steam_appid: 367520,
+            tier: tier.to_owned(),
+            trending_tier: None,
+            confidence: String::from("strong"),
+            total: 10,
+            proton_version: proton_version.map(str::to_owned)
+        };
+        assert_eq!(compat("native", None).suggestion(), None);
+        assert_eq!(compat("borked", Some("GE-Proton9-5")).suggestion(), None);
+
+        let suggestion = compat("gold", Some("GE-Proton9-5")).suggestion().unwrap();
+        assert_eq!(suggestion.runner, "umu-run");
+        assert_eq!(suggestion.env, [
+            (String::from("GAMEID"), String::from("umu-367520")),
+            (String::from("PROTONPATH"), String::from("GE-Proton9-5"))
+        ]);
+        // Versions of Valve's Proton aren't something umu can get by name.
+        let suggestion = compat("silver", Some("8.0-5")).suggestion().unwrap();
+        assert_eq!(suggestion.env[1].1, "GE-Proton");
+
+        let mut alias = Alias::new(String::from("/games/hk/hollow_knight.exe"));
+        assert!(!suggestion.is_applied(&alias));
+        suggestion.apply(&mut alias);
+        assert!(suggestion.is_applied(&alias));
+        assert_eq!(alias.runner.as_deref(), Some("umu-run"));
+    }
+}
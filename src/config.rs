@@ -0,0 +1,210 @@
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::fs;
+use std::io;
+
+use crate::{compat, fetch, groups, hooks, http, info, logs, mods, saves, updates};
+
+#[derive(Serialize, Deserialize)]
+pub struct Config {
+    pub games_dir: String,
+    pub check_for_updates: bool,
+    pub default_fetch_provider: String,
+    pub autoadd_ignore: Vec<String>,
+    /// Game folders besides games_dir, by name. Alias paths can refer to them as `${library:name}`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub libraries: HashMap<String, String>,
+    #[serde(default)]
+    pub http: http::HttpSettings,
+    #[serde(default)]
+    pub updates: updates::UpdateSettings,
+    #[serde(default)]
+    pub logs: logs::LogSettings,
+    #[serde(default)]
+    pub hooks: hooks::HookSettings,
+    #[serde(default)]
+    pub saves: saves::SaveSettings,
+    #[serde(default)]
+    pub mods: mods::ModSettings,
+    #[serde(default)]
+    pub fetch: fetch::FetchSettings,
+    #[serde(default)]
+    pub info: info::InfoSettings,
+    #[serde(default)]
+    pub compat: compat::CompatSettings,
+    /// Aliases and commands that get launched together, run like an alias.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub groups: HashMap<String, groups::Group>,
+    #[serde(serialize_with = "serialize_aliases", deserialize_with = "deserialize_aliases")]
+    pub aliases: HashMap<String, Alias>
+}
+
+/// An alias is stored as a plain path string unless it has extra settings,
+/// in which case it becomes a table with a `path` key.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Alias {
+    /// Can use `${games_dir}`, `${library:name}`, `${HOME}` and `${env:NAME}`.
+    pub path: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub log: bool,
+    /// Commands to run before the game starts, after the global ones.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_launch: Vec<String>,
+    /// Commands to run after the game exits, before the global ones.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_exit: Vec<String>,
+    /// Glob patterns of the save files, can use `${HOME}`, `${WINEPREFIX}` and `${game_dir}`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub saves: Vec<String>,
+    /// Overrides `saves.auto_backup` for this alias.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_backup: Option<bool>,
+    /// Program to launch the executable through, like `wine` or `gamemoderun`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runner: Option<String>,
+    /// Extra arguments passed to the executable.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// Labels to pick aliases by, like `alias export --tag`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Environment variables set for the game. Values can use the same variables as `path`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+    /// The game's app ID on Steam, for looking it up on ProtonDB.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub steam_appid: Option<u32>
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum AliasEntry {
+    Path(String),
+    Full(Box<Alias>)
+}
+
+pub const DEFAULT_CONFIG: &str = "games_dir = \"\"\ndefault_fetch_provider = \"SteamRIP\"\ncheck_for_updates = true\nautoadd_ignore = []\n[aliases]";
+
+
+
+impl Config {
+    /// Variables usable in alias paths, `${env:NAME}` aside.
+    pub fn path_vars(&self) -> HashMap<String, String> {
+        let mut vars = HashMap::from([
+            (String::from("HOME"), saves::home_dir()),
+            (String::from("games_dir"), self.games_dir.trim_end_matches(['/', '\\']).to_owned())
+        ]);
+        for (name, root) in &self.libraries {
+            vars.insert(format!("library:{name}"), root.trim_end_matches(['/', '\\']).to_owned());
+        }
+        vars
+    }
+
+    pub fn expand_path(&self, path: &str) -> String {
+        saves::expand_vars(path, &self.path_vars())
+    }
+
+    /// Where the target of `alias` is on this machine, or `None` if there's no such alias.
+    pub fn alias_path(&self, alias: &str) -> Option<String> {
+        self.aliases.get(alias).map(|entry| self.expand_path(&entry.path))
+    }
+
+    /// Where the target of `alias` is, or why it can't be launched on this machine.
+    pub fn resolve(&self, alias: &str) -> io::Result<PathBuf> {
+        let Some(target) = self.alias_path(alias) else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("Alias `{alias}` not found")));
+        };
+        if target.contains("${") {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("`{target}` uses an unknown or unset variable")));
+        }
+        Ok(PathBuf::from(target))
+    }
+
+    /// `path` relative to the library (or games_dir) it's in, so the alias survives
+    /// the library moving or the config being used on another machine.
+    pub fn portable_alias_path(&self, path: &str) -> String {
+        let mut roots = self.path_vars();
+        roots.remove("HOME");
+        saves::portable_path(path, &roots)
+    }
+}
+
+
+impl Alias {
+    pub fn new(path: String) -> Alias {
+        Alias { path, ..Default::default() }
+    }
+}
+
+
+pub(crate) fn serialize_aliases<S: serde::Serializer>(aliases: &HashMap<String, Alias>, serializer: S) -> Result<S::Ok, S::Error> {
+    let entries: HashMap<&String, AliasEntry> = aliases.iter()
+        .map(|(name, alias)| {
+            if *alias == Alias::new(alias.path.clone()) {
+                (name, AliasEntry::Path(alias.path.clone()))
+            } else {
+                (name, AliasEntry::Full(Box::new(alias.clone())))
+            }
+        })
+        .collect();
+    entries.serialize(serializer)
+}
+
+
+pub(crate) fn deserialize_aliases<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<HashMap<String, Alias>, D::Error> {
+    let entries: HashMap<String, AliasEntry> = HashMap::deserialize(deserializer)?;
+    Ok(entries.into_iter()
+        .map(|(name, entry)| match entry {
+            AliasEntry::Path(path) => (name, Alias::new(path)),
+            AliasEntry::Full(alias) => (name, *alias)
+        })
+        .collect())
+}
+
+static ROOT_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Makes plz use the config and data in `dir` instead of the ones next to the running
+/// executable, for programs embedding plz. Only the first call counts, returns whether it did.
+pub fn set_root_dir(dir: PathBuf) -> bool {
+    ROOT_DIR.set(dir).is_ok()
+}
+
+
+/// Directory holding `config.toml`, next to the plz executable unless `set_root_dir` was called.
+pub fn exe_dir() -> PathBuf {
+    if let Some(dir) = ROOT_DIR.get() {
+        return dir.clone();
+    }
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+
+/// Directory where plz keeps everything that isn't config (logs, history, ...).
+/// If it can't be created, whatever tries to write into it reports the error.
+pub fn data_dir() -> PathBuf {
+    let path = exe_dir().join("data");
+    fs::create_dir_all(&path).ok();
+    path
+}
+
+
+/// Loads the config file, or `None` if there isn't one yet.
+pub fn try_read_config() -> Result<Option<Config>, String> {
+    let contents = match fs::read_to_string(exe_dir().join("config.toml")) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.to_string())
+    };
+    toml::from_str(&contents).map(Some).map_err(|err| err.to_string())
+}
+
+
+pub fn save_config(data: &Config) -> io::Result<()> {
+    let contents = toml::to_string(data).unwrap();
+    fs::write(exe_dir().join("config.toml"), contents)
+}
@@ -0,0 +1,437 @@
+use std::path::{MAIN_SEPARATOR, Path};
+use std::collections::HashMap;
+use serde::Serialize;
+use std::fs;
+
+use crate::{exe_dir, save_config, try_read_config, fetch, groups, http, launch, mods, updates, Config, DEFAULT_CONFIG};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Warning,
+    Error
+}
+
+#[derive(Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub problems: Vec<String>,
+    /// Worth knowing, but not a problem.
+    pub notes: Vec<String>,
+    /// What `--fix` changed.
+    pub fixed: Vec<String>
+}
+
+impl Check {
+    fn new(name: &'static str) -> Check {
+        Check { name, status: Status::Ok, problems: Vec::new(), notes: Vec::new(), fixed: Vec::new() }
+    }
+
+    fn warn(&mut self, problem: String) {
+        self.problems.push(problem);
+        if self.status < Status::Warning {
+            self.status = Status::Warning;
+        }
+    }
+
+    fn fail(&mut self, problem: String) {
+        self.problems.push(problem);
+        self.status = Status::Error;
+    }
+
+    fn note(&mut self, note: String) {
+        self.notes.push(note);
+    }
+
+    fn fixed(&mut self, fix: String) {
+        self.fixed.push(fix);
+    }
+}
+
+
+fn is_writable(dir: &Path) -> bool {
+    let probe = dir.join(".plz-write-test");
+    let writable = fs::write(&probe, b"").is_ok();
+    fs::remove_file(probe).ok();
+    writable
+}
+
+
+fn check_config_parse(fix: bool) -> (Check, Option<Config>) {
+    let mut check = Check::new("config parse");
+    match try_read_config() {
+        Ok(Some(config)) => (check, Some(config)),
+        Ok(None) if fix => {
+            let config: Config = toml::from_str(DEFAULT_CONFIG).unwrap();
+            match save_config(&config) {
+                Ok(_) => check.fixed(String::from("Created a new config file")),
+                Err(err) => check.fail(format!("The config file doesn't exist and can't be created: {err}"))
+            }
+            (check, Some(config))
+        }
+        Ok(None) => {
+            check.fail(String::from("The config file doesn't exist"));
+            (check, None)
+        }
+        Err(err) => {
+            check.fail(format!("The config file can't be loaded: {err}"));
+            (check, None)
+        }
+    }
+}
+
+
+fn check_config_location() -> Check {
+    let mut check = Check::new("config location");
+    let dir = exe_dir();
+    if !is_writable(&dir) {
+        check.fail(format!("`{}` is not writable, plz won't be able to save its config", dir.display()));
+    }
+    if let Ok(cwd) = std::env::current_dir() {
+        if cwd != dir && cwd.join("config.toml").is_file() {
+            check.warn(format!(
+                "There's a config.toml in `{}`, but plz only reads the one next to its executable in `{}`",
+                cwd.display(), dir.display()
+            ));
+        }
+    }
+    check
+}
+
+
+fn check_config_values(config: &Config) -> Check {
+    let mut check = Check::new("config values");
+    if fetch::provider(&config.default_fetch_provider).is_none() {
+        check.fail(format!("default_fetch_provider `{}` is not a known fetch provider", config.default_fetch_provider));
+    }
+    for name in config.fetch.fallback.iter().filter(|name| fetch::provider(name).is_none()) {
+        check.warn(format!("fetch.fallback has `{}` which is not a known fetch provider", name));
+    }
+    if !mods::METHODS.contains(&config.mods.method.as_str()) {
+        check.fail(format!("mods.method `{}` needs to be one of {}", config.mods.method, mods::METHODS.join(", ")));
+    }
+    if !config.updates.release_url.starts_with("https://") && !config.updates.release_url.starts_with("http://") {
+        check.fail(format!("updates.release_url `{}` needs to be an http(s) URL", config.updates.release_url));
+    }
+    if !config.info.api_url.starts_with("https://") && !config.info.api_url.starts_with("http://") {
+        check.fail(format!("info.api_url `{}` needs to be an http(s) URL", config.info.api_url));
+    }
+    for (key, url) in [("compat.summary_url", &config.compat.summary_url), ("compat.reports_url", &config.compat.reports_url)] {
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            check.fail(format!("{key} `{url}` needs to be an http(s) URL"));
+        }
+    }
+    if !updates::CHANNELS.contains(&config.updates.channel.as_str()) {
+        check.fail(format!("updates.channel `{}` needs to be one of {}", config.updates.channel, updates::CHANNELS.join(", ")));
+    }
+    if let Err(err) = http::client(&config.http) {
+        check.fail(err);
+    }
+    if !["warn", "prompt"].contains(&config.saves.on_conflict.as_str()) {
+        check.fail(format!("saves.on_conflict `{}` needs to be either warn or prompt", config.saves.on_conflict));
+    }
+    check
+}
+
+
+fn check_games_dir(config: &Config) -> Check {
+    let mut check = Check::new("games_dir");
+    let path = Path::new(&config.games_dir);
+    if config.games_dir.is_empty() {
+        check.note(String::from("games_dir is not set, `alias autoadd` won't work"));
+    } else if !path.exists() {
+        check.fail(format!("games_dir `{}` does not exist", config.games_dir));
+    } else if !path.is_dir() {
+        check.fail(format!("games_dir `{}` is not a directory", config.games_dir));
+    } else if !config.games_dir.contains(MAIN_SEPARATOR) {
+        check.warn(format!("games_dir `{}` doesn't use the system's main separator ({})", config.games_dir, MAIN_SEPARATOR));
+    }
+    check
+}
+
+
+fn check_libraries(config: &Config) -> Check {
+    let mut check = Check::new("libraries");
+    let mut libraries: Vec<_> = config.libraries.iter().collect();
+    libraries.sort();
+    for (name, root) in libraries {
+        if !Path::new(root).is_dir() {
+            check.fail(format!("Library `{}` points to `{}` which is not a directory", name, root));
+        }
+    }
+    check
+}
+
+
+fn check_alias_targets(config: &Config) -> Check {
+    let mut check = Check::new("alias targets");
+    let mut aliases: Vec<_> = config.aliases.iter().collect();
+    aliases.sort_by_key(|(name, _)| *name);
+    for (alias, entry) in aliases {
+        let target = config.expand_path(&entry.path);
+        let path = Path::new(&target);
+        if target.contains("${") {
+            check.fail(format!("Alias `{}` points to `{}` which uses an unknown or unset variable", alias, target));
+        } else if !path.exists() {
+            check.fail(format!("Alias `{}` points to `{}` which does not exist", alias, target));
+        } else if !path.is_file() {
+            check.fail(format!("Alias `{}` points to `{}` which is not a file", alias, target));
+        }
+    }
+    check
+}
+
+
+fn check_groups(config: &Config) -> Check {
+    let mut check = Check::new("groups");
+    let mut names: Vec<_> = config.groups.keys().collect();
+    names.sort();
+    for name in names {
+        for problem in groups::validate(config, name) {
+            check.fail(problem);
+        }
+    }
+    check
+}
+
+
+fn check_duplicate_targets(config: &Config) -> Check {
+    let mut check = Check::new("duplicate targets");
+    let mut targets: HashMap<String, Vec<&str>> = HashMap::new();
+    for (alias, entry) in &config.aliases {
+        targets.entry(config.expand_path(&entry.path)).or_default().push(alias);
+    }
+    let mut duplicates: Vec<_> = targets.into_iter().filter(|(_, aliases)| aliases.len() > 1).collect();
+    duplicates.sort();
+    for (path, mut aliases) in duplicates {
+        aliases.sort();
+        check.warn(format!("`{}` is the target of {}", path, aliases.join(", ")));
+    }
+    check
+}
+
+
+fn find_in_path(program: &str) -> bool {
+    let Some(paths) = std::env::var_os("PATH") else {
+        return false;
+    };
+    std::env::split_paths(&paths).any(|dir| dir.join(program).is_file())
+}
+
+
+/// Whether any alias without its own runner points to a Windows executable.
+fn uses_wine(config: &Config) -> bool {
+    !cfg!(windows) && config.aliases.iter()
+        .filter(|(_, entry)| entry.runner.is_none())
+        .any(|(_, entry)| config.expand_path(&entry.path).to_lowercase().ends_with(".exe"))
+}
+
+
+fn check_runners(config: &Config) -> Check {
+    let mut check = Check::new("runners");
+    if uses_wine(config) && !find_in_path("wine") {
+        check.fail(String::from("Some aliases point to Windows executables, but `wine` is not in PATH"));
+    }
+    let mut aliases: Vec<_> = config.aliases.iter().collect();
+    aliases.sort_by_key(|(name, _)| *name);
+    for (alias, entry) in aliases {
+        let Some(runner) = entry.runner.as_deref() else {
+            continue;
+        };
+        let program = match launch::split_runner(runner) {
+            Ok(parts) => match parts.into_iter().next() {
+                Some(program) => program,
+                None => continue
+            },
+            Err(err) => {
+                check.fail(format!("Alias `{}` can't be launched, its {}", alias, err));
+                continue;
+            }
+        };
+        let program = config.expand_path(&program);
+        let found = if program.contains(['/', '\\']) { Path::new(&program).is_file() } else { find_in_path(&program) };
+        if !found {
+            check.fail(format!("Alias `{}` runs through `{}` which is not installed", alias, program));
+        }
+    }
+    check
+}
+
+
+fn check_wine_prefix(config: &Config) -> Check {
+    let mut check = Check::new("wine prefix");
+    if !uses_wine(config) {
+        return check;
+    }
+    let prefix = match std::env::var("WINEPREFIX") {
+        Ok(prefix) => prefix,
+        Err(_) => {
+            let home = std::env::var("HOME").unwrap_or_default();
+            Path::new(&home).join(".wine").display().to_string()
+        }
+    };
+    let path = Path::new(&prefix);
+    if !path.is_dir() {
+        check.warn(format!("Wine prefix `{}` does not exist yet, Wine will create it on first launch", prefix));
+    } else if !path.join("drive_c").is_dir() || !path.join("system.reg").is_file() {
+        check.fail(format!("Wine prefix `{}` is missing drive_c or system.reg", prefix));
+    } else if !is_writable(path) {
+        check.fail(format!("Wine prefix `{}` is not writable", prefix));
+    }
+    check
+}
+
+
+fn check_data_dirs(config: &Config) -> Check {
+    let mut check = Check::new("data dirs");
+    let data = exe_dir().join("data");
+    if data.exists() && !is_writable(&data) {
+        check.fail(format!("Data directory `{}` is not writable", data.display()));
+    } else if data.exists() {
+        for sub in ["logs", "saves", "mods"] {
+            let dir = data.join(sub);
+            if dir.exists() && !is_writable(&dir) {
+                check.fail(format!("`{}` is not writable", dir.display()));
+            }
+        }
+    }
+    if let Some(sync_dir) = &config.saves.sync_dir {
+        let path = Path::new(sync_dir);
+        if !path.is_dir() {
+            check.fail(format!("saves.sync_dir `{}` does not exist", sync_dir));
+        } else if !is_writable(path) {
+            check.fail(format!("saves.sync_dir `{}` is not writable", sync_dir));
+        }
+    }
+    check
+}
+
+
+fn check_ignore_entries(config: &mut Config, fix: bool) -> Check {
+    let mut check = Check::new("stale ignore entries");
+    let stale: Vec<String> = config.autoadd_ignore.iter()
+        .filter(|path| !Path::new(path).exists())
+        .cloned()
+        .collect();
+    if stale.is_empty() {
+        return check;
+    }
+    if fix {
+        config.autoadd_ignore.retain(|path| !stale.contains(path));
+        match save_config(config) {
+            Ok(_) => check.fixed(format!("Removed {} ignore entries that no longer exist", stale.len())),
+            Err(err) => check.fail(format!("Failed to save the config file: {err}"))
+        }
+    } else {
+        for path in stale {
+            check.warn(format!("autoadd_ignore has `{}` which no longer exists", path));
+        }
+    }
+    check
+}
+
+
+/// Runs every check, applying the safe fixes when `fix` is set.
+pub fn run_checks(fix: bool) -> Vec<Check> {
+    let (parse, config) = check_config_parse(fix);
+    let mut checks = vec![parse, check_config_location()];
+    if let Some(mut config) = config {
+        checks.push(check_config_values(&config));
+        checks.push(check_games_dir(&config));
+        checks.push(check_libraries(&config));
+        checks.push(check_alias_targets(&config));
+        checks.push(check_duplicate_targets(&config));
+        checks.push(check_groups(&config));
+        checks.push(check_runners(&config));
+        checks.push(check_wine_prefix(&config));
+        checks.push(check_data_dirs(&config));
+        checks.push(check_ignore_entries(&mut config, fix));
+    }
+    checks
+}
+
+
+/// The cheap checks, for a quick look after other commands.
+pub fn quick_checks(config: &Config) -> Vec<Check> {
+    vec![check_games_dir(config), check_alias_targets(config)]
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use crate::Alias;
+
+    fn config() -> Config {
+        toml::from_str(DEFAULT_CONFIG).unwrap()
+    }
+
+    #[test]
+    fn accepts_the_default_config() {
+        let check = check_config_values(&config());
+        assert_eq!(check.status, Status::Ok, "{:?}", check.problems);
+
+        let mut config = config();
+        config.mods.method = String::from("teleport");
+        config.saves.on_conflict = String::from("ignore");
+        let check = check_config_values(&config);
+        assert_eq!(check.status, Status::Error);
+        assert_eq!(check.problems.len(), 2);
+    }
+
+    #[test]
+    fn notes_an_unset_games_dir() {
+        let check = check_games_dir(&config());
+        assert_eq!(check.status, Status::Ok);
+        assert!(check.problems.is_empty());
+        assert_eq!(check.notes.len(), 1);
+
+        let dir = TestDir::new();
+        let mut config = config();
+        config.games_dir = dir.path().display().to_string();
+        assert_eq!(check_games_dir(&config).status, Status::Ok);
+        config.games_dir = dir.path().join("missing").display().to_string();
+        assert_eq!(check_games_dir(&config).status, Status::Error);
+        fs::write(dir.path().join("file"), "").unwrap();
+        config.games_dir = dir.path().join("file").display().to_string();
+        assert_eq!(check_games_dir(&config).status, Status::Error);
+    }
+
+    #[test]
+    fn finds_broken_alias_targets() {
+        let dir = TestDir::new();
+        fs::write(dir.path().join("hk.exe"), "").unwrap();
+        let mut config = config();
+        config.libraries.insert(String::from("main"), dir.path().display().to_string());
+        let mut add = |alias: &str, path: String| config.aliases.insert(alias.to_owned(), Alias::new(path));
+        add("hk", String::from("${library:main}/hk.exe"));
+        add("also-hk", dir.path().join("hk.exe").display().to_string());
+        add("gone", dir.path().join("gone.exe").display().to_string());
+        add("folder", dir.path().display().to_string());
+        add("unset", String::from("${library:other}/sl"));
+
+        let check = check_alias_targets(&config);
+        assert_eq!(check.status, Status::Error);
+        let aliases: Vec<&str> = check.problems.iter().map(|problem| problem.split('`').nth(1).unwrap()).collect();
+        assert_eq!(aliases, ["folder", "gone", "unset"]);
+        assert_eq!(check_libraries(&config).status, Status::Ok);
+
+        let check = check_duplicate_targets(&config);
+        assert_eq!(check.status, Status::Warning);
+        assert!(check.problems[0].ends_with("is the target of also-hk, hk"), "{}", check.problems[0]);
+    }
+
+    #[test]
+    fn reports_stale_ignore_entries() {
+        let dir = TestDir::new();
+        let mut config = config();
+        config.autoadd_ignore = vec![dir.path().display().to_string(), dir.path().join("gone").display().to_string()];
+        let check = check_ignore_entries(&mut config, false);
+        assert_eq!(check.status, Status::Warning);
+        assert_eq!(check.problems.len(), 1);
+        assert_eq!(config.autoadd_ignore.len(), 2);
+    }
+}
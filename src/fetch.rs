@@ -0,0 +1,418 @@
+use scraper::{ElementRef, Html, Selector};
+use serde::{Serialize, Deserialize};
+use reqwest::header::HeaderMap;
+use reqwest::{Response, StatusCode, Url};
+use tokio::task::JoinSet;
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+use std::path::Path;
+use std::fmt;
+
+use crate::history::now;
+use crate::http::Client;
+use crate::cache;
+
+mod game3rb;
+mod gog_games;
+mod steamrip;
+
+pub use game3rb::Game3rb;
+pub use gog_games::GogGames;
+pub use steamrip::SteamRip;
+
+/// A site `fetch` can get download links from.
+pub trait FetchProvider: Sync {
+    /// The name used in the config and the output, like `SteamRIP`.
+    fn name(&self) -> &'static str;
+
+    /// The subcommand that fetches from this provider first, like `fetchrip`.
+    fn command(&self) -> &'static str;
+
+    /// The part of the game page URL that comes from the game's name.
+    fn slug(&self, name: &str) -> String {
+        normalize_slug(name, '-')
+    }
+
+    fn game_url(&self, slug: &str) -> String;
+
+    /// Where the provider lists the games matching `query`.
+    fn search_url(&self, query: &str) -> String;
+
+    /// Reads a game page.
+    fn parse(&self, html: &str) -> Result<Parsed, ParseError>;
+
+    /// Reads the page a game page pointed to with `Parsed::Follow`.
+    fn parse_followed(&self, title: String, html: &str) -> Result<FetchResult, ParseError> {
+        let _ = (title, html);
+        Err(ParseError::NoFollowedPage)
+    }
+
+    /// Reads a search results page.
+    fn parse_search(&self, html: &str) -> Result<Vec<SearchHit>, ParseError>;
+}
+
+/// Why a page couldn't be read, usually because the site changed its layout.
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    /// Every page of the provider has an element matching this selector, but this one doesn't.
+    Missing(&'static str),
+    /// The page is for a game but has no download links.
+    NoLinks,
+    /// `parse_followed` was called on a provider whose game pages have the links themselves.
+    NoFollowedPage
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Missing(selector) => write!(f, "The page has no `{selector}`, the layout might have changed"),
+            ParseError::NoLinks => write!(f, "The page has no download links"),
+            ParseError::NoFollowedPage => write!(f, "The provider doesn't link to other pages")
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// What reading a game page gave.
+pub enum Parsed {
+    Links(FetchResult),
+    /// The links are on another page.
+    Follow { title: String, url: String }
+}
+
+/// What a provider has for a game.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FetchResult {
+    pub title: String,
+    pub groups: Vec<LinkGroup>
+}
+
+/// Links that belong together, like the mirrors of one installer.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LinkGroup {
+    /// Unnamed when the provider only has a single list of links.
+    pub name: Option<String>,
+    pub links: Vec<Link>
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Link {
+    /// The file host, like `Mega`.
+    pub host: String,
+    pub url: String
+}
+
+/// A game on a search results page.
+pub struct SearchHit {
+    pub title: String,
+    pub slug: String
+}
+
+/// Links from `fetch_cached`.
+pub struct Fetched {
+    pub result: FetchResult,
+    /// Whether the links came from the cache instead of the provider.
+    pub cached: bool
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FetchSettings {
+    /// Providers to try, in order, when default_fetch_provider doesn't have the game.
+    #[serde(default = "default_fallback")]
+    pub fallback: Vec<String>,
+    /// How long fetched links are reused without asking the provider, in hours. 0 turns the cache off.
+    #[serde(default = "default_cache_ttl_hours")]
+    pub cache_ttl_hours: u64
+}
+
+impl Default for FetchSettings {
+    fn default() -> Self {
+        FetchSettings { fallback: default_fallback(), cache_ttl_hours: default_cache_ttl_hours() }
+    }
+}
+
+fn default_fallback() -> Vec<String> {
+    PROVIDERS.iter().map(|provider| provider.name().to_owned()).collect()
+}
+
+fn default_cache_ttl_hours() -> u64 {
+    24
+}
+
+/// The cache namespace of fetched links.
+const CACHE: &str = "fetch";
+
+/// Every provider `fetch` knows.
+static PROVIDERS: [&dyn FetchProvider; 3] = [&SteamRip, &Game3rb, &GogGames];
+
+
+pub fn providers() -> &'static [&'static dyn FetchProvider] {
+    &PROVIDERS
+}
+
+
+/// The provider called `name`, ignoring case.
+pub fn provider(name: &str) -> Option<&'static dyn FetchProvider> {
+    PROVIDERS.iter().copied().find(|provider| provider.name().eq_ignore_ascii_case(name))
+}
+
+
+/// The provider whose subcommand is `command`.
+pub fn provider_for_command(command: &str) -> Option<&'static dyn FetchProvider> {
+    PROVIDERS.iter().copied().find(|provider| provider.command() == command)
+}
+
+
+/// `first`, then the fallback providers that aren't `first`. Unknown fallback names are left
+/// out, `plz doctor` points them out.
+pub fn fallback_order(first: &str, settings: &FetchSettings) -> Result<Vec<&'static dyn FetchProvider>, String> {
+    let first = provider(first).ok_or_else(|| format!("Fetch provider is not valid `{first}`"))?;
+    let mut order = vec![first];
+    for provider in settings.fallback.iter().filter_map(|name| provider(name)) {
+        if !order.iter().any(|known| known.name() == provider.name()) {
+            order.push(provider);
+        }
+    }
+    Ok(order)
+}
+
+
+/// Turns a game's name into a URL slug the way the sites do: accents are dropped, other
+/// punctuation like apostrophes and colons is removed, and words are joined by a single `separator`.
+pub fn normalize_slug(name: &str, separator: char) -> String {
+    let mut slug = String::new();
+    let mut pending_separator = false;
+    for char in name.nfd().filter(|char| !is_combining_mark(*char)) {
+        if char.is_alphanumeric() {
+            if pending_separator && !slug.is_empty() {
+                slug.push(separator);
+            }
+            pending_separator = false;
+            slug.extend(char.to_lowercase());
+        } else if char.is_whitespace() || ['-', '_', '.', '/'].contains(&char) {
+            pending_separator = true;
+        }
+    }
+    slug
+}
+
+
+fn titlecase(string: &str) -> String {
+    let mut chars = string.chars();
+    let first = chars.next().map(|first| first.to_uppercase().collect::<String>()).unwrap_or_default();
+    first + chars.as_str()
+}
+
+
+fn select<'a>(element: ElementRef<'a>, selector: &str) -> impl Iterator<Item = ElementRef<'a>> {
+    element.select(&Selector::parse(selector).unwrap()).collect::<Vec<_>>().into_iter()
+}
+
+
+fn text(soup: &Html, selector: &'static str) -> Result<String, ParseError> {
+    select(soup.root_element(), selector)
+        .next()
+        .map(|element| element.text().collect())
+        .ok_or(ParseError::Missing(selector))
+}
+
+
+/// The last part of the path of `url`, which is what the providers use as slugs.
+fn slug_of(url: &str) -> Option<String> {
+    url.split(['?', '#']).next()?
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .filter(|slug| !slug.is_empty() && !slug.contains(':'))
+        .map(str::to_owned)
+}
+
+
+async fn get_response(client: &Client, url: &str, headers: HeaderMap, provider: &str) -> Result<Response, String> {
+    let res = client.get_with(url, headers).await.map_err(|err| format!("Error sending request: {err}"))?;
+    if res.status().as_u16() == 404 {
+        return Err(format!("Failed to fetch {provider}"));
+    }
+    Ok(res)
+}
+
+
+async fn get_page(client: &Client, url: &str, provider: &str) -> Result<String, String> {
+    let res = get_response(client, url, HeaderMap::new(), provider).await?;
+    res.text().await.map_err(|err| err.to_string())
+}
+
+
+/// Fetches the links for the game called `name` from `provider`.
+pub async fn fetch_from(client: &Client, provider: &dyn FetchProvider, name: &str) -> Result<FetchResult, String> {
+    fetch_page(client, provider, &provider.game_url(&provider.slug(name))).await
+}
+
+
+/// `fetch_from` through the cache. Links younger than `cache_ttl_hours` are used as they are,
+/// older ones are only downloaded again if the provider says the page changed.
+/// `refresh` ignores the cache, but the new links still go into it.
+pub async fn fetch_cached(client: &Client, cache_dir: &Path, provider: &dyn FetchProvider, name: &str, settings: &FetchSettings, refresh: bool) -> Result<Fetched, String> {
+    fetch_slug(client, cache_dir, provider, &provider.slug(name), settings, refresh).await
+}
+
+
+/// `fetch_cached` for the game page at `slug`, like one from `search`.
+pub async fn fetch_slug(client: &Client, cache_dir: &Path, provider: &dyn FetchProvider, slug: &str, settings: &FetchSettings, refresh: bool) -> Result<Fetched, String> {
+    fetch_page_cached(client, cache_dir, provider, &format!("{}/{}", provider.name(), slug), &provider.game_url(slug), settings, refresh).await
+}
+
+
+async fn fetch_page_cached(client: &Client, cache_dir: &Path, provider: &dyn FetchProvider, key: &str, url: &str, settings: &FetchSettings, refresh: bool) -> Result<Fetched, String> {
+    let ttl = settings.cache_ttl_hours * 60 * 60;
+    if ttl == 0 {
+        return fetch_page(client, provider, url).await.map(|result| Fetched { result, cached: false });
+    }
+    let cached: Option<cache::Entry<FetchResult>> = if refresh { None } else { cache::read(cache_dir, CACHE, key) };
+    let headers = match &cached {
+        Some(entry) if entry.is_fresh(ttl) => return Ok(Fetched { result: entry.value.clone(), cached: true }),
+        Some(entry) => entry.conditional_headers(),
+        None => HeaderMap::new()
+    };
+
+    let res = get_response(client, url, headers, provider.name()).await?;
+    if let (StatusCode::NOT_MODIFIED, Some(mut entry)) = (res.status(), cached) {
+        entry.stored = now();
+        cache::write(cache_dir, CACHE, &entry).ok();
+        return Ok(Fetched { result: entry.value, cached: true });
+    }
+    let headers = res.headers().clone();
+    let html = res.text().await.map_err(|err| err.to_string())?;
+    let entry = cache::Entry::new(key, read_page(client, provider, url, &html).await?, &headers);
+    // Not being able to cache the links is no reason to not show them.
+    cache::write(cache_dir, CACHE, &entry).ok();
+    Ok(Fetched { result: entry.value, cached: false })
+}
+
+
+/// Fetches `name` from every provider at the same time, through the cache. The tasks finish
+/// in whatever order the providers answer, and dropping the set cancels the ones still running.
+pub fn fetch_all(client: &Client, cache_dir: &Path, name: &str, settings: &FetchSettings, refresh: bool) -> JoinSet<(&'static dyn FetchProvider, Result<Fetched, String>)> {
+    let mut tasks = JoinSet::new();
+    for &provider in providers() {
+        let client = client.clone();
+        let cache_dir = cache_dir.to_path_buf();
+        let name = name.to_owned();
+        let settings = settings.clone();
+        tasks.spawn(async move { (provider, fetch_cached(&client, &cache_dir, provider, &name, &settings, refresh).await) });
+    }
+    tasks
+}
+
+
+/// Fetches the links from the game page at `url`, following it if the links are on another page.
+pub async fn fetch_page(client: &Client, provider: &dyn FetchProvider, url: &str) -> Result<FetchResult, String> {
+    let html = get_page(client, url, provider.name()).await?;
+    read_page(client, provider, url, &html).await
+}
+
+
+/// The links on the game page at `url`, which has `html`.
+async fn read_page(client: &Client, provider: &dyn FetchProvider, url: &str, html: &str) -> Result<FetchResult, String> {
+    match provider.parse(html).map_err(|err| err.to_string())? {
+        Parsed::Links(result) => Ok(result),
+        Parsed::Follow { title, url: next } => {
+            // The link can be relative to the game page.
+            let next = Url::parse(url).and_then(|url| url.join(&next)).map(String::from).unwrap_or(next);
+            let html = get_page(client, &next, provider.name()).await
+                .map_err(|_| String::from("Failed to get to the links website"))?;
+            provider.parse_followed(title, &html).map_err(|err| err.to_string())
+        }
+    }
+}
+
+
+/// The games matching `query` on `provider`.
+pub async fn search(client: &Client, provider: &dyn FetchProvider, query: &str) -> Result<Vec<SearchHit>, String> {
+    let html = get_page(client, &provider.search_url(query), provider.name()).await?;
+    provider.parse_search(&html).map_err(|err| err.to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use crate::{http, test_server};
+
+    #[tokio::test]
+    async fn fetches_and_follows_pages_over_http() {
+        let base = test_server::serve(vec![
+            ("/hollow-knight", 200, include_bytes!("fetch/fixtures/steamrip.html").to_vec()),
+            ("/changed", 200, include_bytes!("fetch/fixtures/steamrip_changed.html").to_vec()),
+            ("/hollow-knight/", 200, include_bytes!("fetch/fixtures/game3rb.html").to_vec()),
+            ("/links/hollow-knight/", 200, include_bytes!("fetch/fixtures/game3rb_links.html").to_vec())
+        ]).await;
+        let client = http::client(&http::HttpSettings::default()).unwrap();
+
+        let result = fetch_page(&client, &SteamRip, &format!("{base}/hollow-knight")).await.unwrap();
+        assert_eq!(result.title, "Hollow Knight");
+        assert_eq!(result.groups[0].links.len(), 3);
+
+        let result = fetch_page(&client, &Game3rb, &format!("{base}/hollow-knight/")).await.unwrap();
+        assert_eq!(result.title, "Hollow Knight + Online");
+        assert_eq!(result.groups[0].links[1].url, "https://pixeldrain.com/u/Zx9Yw8");
+
+        let err = fetch_page(&client, &SteamRip, &format!("{base}/changed")).await.err();
+        assert_eq!(err, Some(ParseError::Missing("h1.post-title").to_string()));
+        let err = fetch_page(&client, &SteamRip, &format!("{base}/missing")).await.err();
+        assert_eq!(err.as_deref(), Some("Failed to fetch SteamRIP"));
+    }
+
+    #[tokio::test]
+    async fn caches_links_and_revalidates_them() {
+        let tmp = TestDir::new();
+        let cache_dir = tmp.path();
+        let base = test_server::serve(vec![
+            ("/hollow-knight", 200, include_bytes!("fetch/fixtures/steamrip.html").to_vec())
+        ]).await;
+        let client = http::client(&http::HttpSettings::default()).unwrap();
+        let settings = FetchSettings::default();
+        let (key, url) = ("SteamRIP/cache-test", format!("{base}/hollow-knight"));
+
+        let fetched = fetch_page_cached(&client, cache_dir, &SteamRip, key, &url, &settings, false).await.unwrap();
+        assert!(!fetched.cached);
+        assert!(fetch_page_cached(&client, cache_dir, &SteamRip, key, &url, &settings, false).await.unwrap().cached);
+
+        // Once it's stale, the server is asked and says it didn't change.
+        let mut entry: cache::Entry<FetchResult> = cache::read(cache_dir, CACHE, key).unwrap();
+        assert!(entry.etag.is_some());
+        entry.stored = 0;
+        cache::write(cache_dir, CACHE, &entry).unwrap();
+        let fetched = fetch_page_cached(&client, cache_dir, &SteamRip, key, &url, &settings, false).await.unwrap();
+        assert!(fetched.cached);
+        assert_eq!(fetched.result.groups[0].links.len(), 3);
+        assert!(cache::read::<FetchResult>(cache_dir, CACHE, key).unwrap().is_fresh(60));
+
+        assert!(!fetch_page_cached(&client, cache_dir, &SteamRip, key, &url, &settings, true).await.unwrap().cached);
+        let uncached = FetchSettings { cache_ttl_hours: 0, ..Default::default() };
+        assert!(!fetch_page_cached(&client, cache_dir, &SteamRip, key, &url, &uncached, false).await.unwrap().cached);
+    }
+
+    #[test]
+    fn normalizes_slugs() {
+        assert_eq!(normalize_slug("Baldur's Gate 3", '-'), "baldurs-gate-3");
+        assert_eq!(normalize_slug("Half-Life 2: Episode One", '-'), "half-life-2-episode-one");
+        assert_eq!(normalize_slug("  S.T.A.L.K.E.R. -- Shadow of Chernobyl ", '-'), "s-t-a-l-k-e-r-shadow-of-chernobyl");
+        assert_eq!(normalize_slug("Pokémon Mystery Dungeon™", '_'), "pokemon_mystery_dungeon");
+        assert_eq!(normalize_slug("Ōkami HD & Friends!", '-'), "okami-hd-friends");
+        assert_eq!(normalize_slug("東方 Project", '-'), "東方-project");
+    }
+
+    #[test]
+    fn falls_back_in_the_configured_order() {
+        let settings = FetchSettings {
+            fallback: vec![String::from("GOG Games"), String::from("nope"), String::from("SteamRIP")],
+            ..Default::default()
+        };
+        let order: Vec<&str> = fallback_order("steamrip", &settings).unwrap().iter().map(|provider| provider.name()).collect();
+        assert_eq!(order, ["SteamRIP", "GOG Games"]);
+        assert!(fallback_order("nope", &settings).is_err());
+    }
+}
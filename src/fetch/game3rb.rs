@@ -0,0 +1,125 @@
+use scraper::Html;
+use reqwest::Url;
+
+use super::{select, slug_of, text, titlecase, FetchProvider, FetchResult, Link, LinkGroup, ParseError, Parsed, SearchHit};
+
+pub struct Game3rb;
+
+fn clean_title(title: &str) -> String {
+    title.replace("Download ", "")
+        .replace(" + OnLine", " + Online")
+        .trim().to_owned()
+}
+
+
+impl FetchProvider for Game3rb {
+    fn name(&self) -> &'static str {
+        "Game3rb"
+    }
+
+    fn command(&self) -> &'static str {
+        "fetchrb"
+    }
+
+    fn game_url(&self, slug: &str) -> String {
+        format!("https://game3rb.com/{}", slug)
+    }
+
+    fn search_url(&self, query: &str) -> String {
+        Url::parse_with_params("https://game3rb.com/", [("s", query)]).unwrap().to_string()
+    }
+
+    /// The game page only links to a page with the actual links.
+    fn parse(&self, html: &str) -> Result<Parsed, ParseError> {
+        let soup = Html::parse_document(html);
+        let title = clean_title(&text(&soup, "h1.post-title")?);
+        let url = select(soup.root_element(), "a#download-link.direct")
+            .next()
+            .and_then(|item| item.value().attr("href"))
+            .ok_or(ParseError::Missing("a#download-link.direct"))?;
+        Ok(Parsed::Follow { title, url: url.to_owned() })
+    }
+
+    fn parse_followed(&self, title: String, html: &str) -> Result<FetchResult, ParseError> {
+        let soup = Html::parse_document(html);
+        let mut links = Vec::new();
+        for link in select(soup.root_element(), "ol li a") {
+            let Some(url) = link.attr("href") else {
+                continue;
+            };
+            let Some(mut idx) = url.find("://").map(|idx| idx + 3) else {
+                continue;
+            };
+            if url[idx..].starts_with("www.") {
+                idx += 4;
+            }
+            let dot = url[idx..].find('.').map_or(url.len(), |dot| dot + idx);
+            links.push(Link { host: titlecase(&url[idx..dot]), url: url.to_owned() });
+        }
+        if links.is_empty() {
+            return Err(ParseError::NoLinks);
+        }
+        Ok(FetchResult { title, groups: vec![LinkGroup { name: None, links }] })
+    }
+
+    fn parse_search(&self, html: &str) -> Result<Vec<SearchHit>, ParseError> {
+        let soup = Html::parse_document(html);
+        let mut hits: Vec<SearchHit> = Vec::new();
+        for link in select(soup.root_element(), "h2.entry-title a, h3.entry-title a, h2.post-title a") {
+            let Some(slug) = link.attr("href").and_then(slug_of) else {
+                continue;
+            };
+            if !hits.iter().any(|hit| hit.slug == slug) {
+                hits.push(SearchHit { title: clean_title(&link.text().collect::<String>()), slug });
+            }
+        }
+        Ok(hits)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_the_game_page_to_the_links() {
+        match Game3rb.parse(include_str!("fixtures/game3rb.html")).unwrap() {
+            Parsed::Follow { title, url } => {
+                assert_eq!(title, "Hollow Knight + Online");
+                assert_eq!(url, "/links/hollow-knight/");
+            }
+            Parsed::Links(_) => panic!("Game3rb pages link to the links")
+        }
+
+        let result = Game3rb.parse_followed(String::from("Hollow Knight"), include_str!("fixtures/game3rb_links.html")).unwrap();
+        let links: Vec<(&str, &str)> = result.groups[0].links.iter().map(|link| (link.host.as_str(), link.url.as_str())).collect();
+        assert_eq!(links, [
+            ("Mediafire", "https://www.mediafire.com/file/abc123/HK.zip"),
+            ("Pixeldrain", "https://pixeldrain.com/u/Zx9Yw8")
+        ]);
+    }
+
+    #[test]
+    fn reports_changed_layouts() {
+        assert_eq!(
+            Game3rb.parse(include_str!("fixtures/game3rb_changed.html")).err(),
+            Some(ParseError::Missing("a#download-link.direct"))
+        );
+        assert_eq!(
+            Game3rb.parse_followed(String::new(), "<ul><li><a href=\"/report\">Report</a></li></ul>").err(),
+            Some(ParseError::NoLinks)
+        );
+        assert_eq!(Game3rb.parse("<h1 class=\"post-title\">Download <b>Hollow").err(), Some(ParseError::Missing("a#download-link.direct")));
+    }
+
+    #[test]
+    fn parses_search_results() {
+        let hits = Game3rb.parse_search(include_str!("fixtures/game3rb_search.html")).unwrap();
+        let hits: Vec<(&str, &str)> = hits.iter().map(|hit| (hit.title.as_str(), hit.slug.as_str())).collect();
+        assert_eq!(hits, [
+            ("Hollow Knight + Online", "hollow-knight"),
+            ("Hollow Knight Voidheart Edition", "hollow-knight-voidheart-edition")
+        ]);
+    }
+}
@@ -0,0 +1,118 @@
+use scraper::Html;
+use reqwest::Url;
+
+use super::{normalize_slug, select, slug_of, text, FetchProvider, FetchResult, Link, LinkGroup, ParseError, Parsed, SearchHit};
+
+pub struct GogGames;
+
+impl FetchProvider for GogGames {
+    fn name(&self) -> &'static str {
+        "GOG Games"
+    }
+
+    fn command(&self) -> &'static str {
+        "fetchgog"
+    }
+
+    /// Game pages use underscores instead of dashes.
+    fn slug(&self, name: &str) -> String {
+        normalize_slug(name, '_')
+    }
+
+    fn game_url(&self, slug: &str) -> String {
+        format!("https://gog-games.to/game/{}", slug)
+    }
+
+    fn search_url(&self, query: &str) -> String {
+        Url::parse_with_params("https://gog-games.to/", [("search", query)]).unwrap().to_string()
+    }
+
+    fn parse(&self, html: &str) -> Result<Parsed, ParseError> {
+        let soup = Html::parse_document(html);
+        let title = text(&soup, "div.index h1")?;
+
+        let block = select(soup.root_element(), "div.items-links-block")
+            .next()
+            .ok_or(ParseError::Missing("div.items-links-block"))?;
+        let mut groups = Vec::new();
+        for group in select(block, "div") {
+            let Some(name) = select(group, "div.title").next() else {
+                continue;
+            };
+            let links = select(group, "div.item-expand.wrap")
+                .filter_map(|link| {
+                    let host = select(link, "label").next()?.attr("title")?;
+                    let url = select(link, "div.items-group a").next()?.attr("href")?;
+                    Some(Link { host: host.to_owned(), url: url.to_owned() })
+                })
+                .collect();
+            groups.push(LinkGroup { name: Some(name.text().collect()), links });
+        }
+        if groups.iter().all(|group: &LinkGroup| group.links.is_empty()) {
+            return Err(ParseError::NoLinks);
+        }
+        Ok(Parsed::Links(FetchResult { title, groups }))
+    }
+
+    fn parse_search(&self, html: &str) -> Result<Vec<SearchHit>, ParseError> {
+        let soup = Html::parse_document(html);
+        let mut hits: Vec<SearchHit> = Vec::new();
+        for link in select(soup.root_element(), "a[href*=\"/game/\"]") {
+            let title = link.text().collect::<String>().trim().to_owned();
+            let Some(slug) = link.attr("href").and_then(slug_of) else {
+                continue;
+            };
+            if !title.is_empty() && !hits.iter().any(|hit| hit.slug == slug) {
+                hits.push(SearchHit { title, slug });
+            }
+        }
+        Ok(hits)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_grouped_links() {
+        let Parsed::Links(result) = GogGames.parse(include_str!("fixtures/gog_games.html")).unwrap() else {
+            panic!("GOG Games pages have the links themselves");
+        };
+        assert_eq!(result.title, "Hollow Knight");
+        let groups: Vec<(Option<&str>, Vec<&str>)> = result.groups.iter()
+            .map(|group| (group.name.as_deref(), group.links.iter().map(|link| link.url.as_str()).collect()))
+            .collect();
+        assert_eq!(groups, [
+            (Some("Game Download"), vec!["https://gofile.io/d/abc123", "https://pixeldrain.com/u/def456"]),
+            (Some("Goodies"), vec!["https://gofile.io/d/ghi789"])
+        ]);
+        assert_eq!(result.groups[0].links[1].host, "Pixeldrain");
+    }
+
+    #[test]
+    fn reports_changed_layouts() {
+        assert_eq!(
+            GogGames.parse(include_str!("fixtures/gog_games_changed.html")).err(),
+            Some(ParseError::Missing("div.items-links-block"))
+        );
+        assert_eq!(
+            GogGames.parse("<div class=\"index\"><h1>Hollow Knight</h1><div class=\"items-links-block\"><div><div class=\"title\">Game").err(),
+            Some(ParseError::NoLinks)
+        );
+        assert_eq!(GogGames.parse("<html><h1>Hollow Knight</h1>").err(), Some(ParseError::Missing("div.index h1")));
+    }
+
+    #[test]
+    fn slugs_use_underscores() {
+        assert_eq!(GogGames.slug("Hollow Knight - Voidheart"), "hollow_knight_voidheart");
+    }
+
+    #[test]
+    fn parses_search_results() {
+        let hits = GogGames.parse_search(include_str!("fixtures/gog_games_search.html")).unwrap();
+        let hits: Vec<(&str, &str)> = hits.iter().map(|hit| (hit.title.as_str(), hit.slug.as_str())).collect();
+        assert_eq!(hits, [("Hollow Knight", "hollow_knight"), ("Hollow Knight: Silksong", "hollow_knight_silksong")]);
+    }
+}
@@ -0,0 +1,109 @@
+use scraper::Html;
+use reqwest::Url;
+
+use super::{select, slug_of, text, titlecase, FetchProvider, FetchResult, Link, LinkGroup, ParseError, Parsed, SearchHit};
+
+pub struct SteamRip;
+
+impl FetchProvider for SteamRip {
+    fn name(&self) -> &'static str {
+        "SteamRIP"
+    }
+
+    fn command(&self) -> &'static str {
+        "fetchrip"
+    }
+
+    fn game_url(&self, slug: &str) -> String {
+        format!("https://steamrip.com/{}", slug)
+    }
+
+    fn search_url(&self, query: &str) -> String {
+        Url::parse_with_params("https://steamrip.com/", [("s", query)]).unwrap().to_string()
+    }
+
+    fn parse(&self, html: &str) -> Result<Parsed, ParseError> {
+        let soup = Html::parse_document(html);
+        let title = text(&soup, "h1.post-title")?
+            .replace(" Free Download", "")
+            .trim().to_owned();
+
+        // Hosts link protocol-relative, like `//megadb.net/...`.
+        let links = select(soup.root_element(), "a.shortc-button")
+            .filter_map(|item| item.value().attr("href"))
+            .filter_map(|href| {
+                let dot = href.find('.')?;
+                Some(Link { host: titlecase(href.get(2..dot)?), url: format!("https:{href}") })
+            })
+            .collect::<Vec<_>>();
+        if links.is_empty() {
+            return Err(ParseError::NoLinks);
+        }
+        Ok(Parsed::Links(FetchResult { title, groups: vec![LinkGroup { name: None, links }] }))
+    }
+
+    fn parse_search(&self, html: &str) -> Result<Vec<SearchHit>, ParseError> {
+        let soup = Html::parse_document(html);
+        let mut hits: Vec<SearchHit> = Vec::new();
+        for link in select(soup.root_element(), "h2.post-title a, h2.thumb-title a") {
+            let Some(slug) = link.attr("href").and_then(slug_of) else {
+                continue;
+            };
+            if !hits.iter().any(|hit| hit.slug == slug) {
+                let title = link.text().collect::<String>().replace(" Free Download", "");
+                hits.push(SearchHit { title: title.trim().to_owned(), slug });
+            }
+        }
+        Ok(hits)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn links(html: &str) -> Result<FetchResult, ParseError> {
+        match SteamRip.parse(html)? {
+            Parsed::Links(result) => Ok(result),
+            Parsed::Follow { .. } => panic!("SteamRIP pages have the links themselves")
+        }
+    }
+
+    #[test]
+    fn parses_the_game_page() {
+        let result = links(include_str!("fixtures/steamrip.html")).unwrap();
+        assert_eq!(result.title, "Hollow Knight");
+        let links: Vec<(&str, &str)> = result.groups[0].links.iter().map(|link| (link.host.as_str(), link.url.as_str())).collect();
+        assert_eq!(links, [
+            ("Megadb", "https://megadb.net/9a8b7c6d5e4f"),
+            ("Gofile", "https://gofile.io/d/Xy12Ab"),
+            ("Buzzheavier", "https://buzzheavier.com/f/GQ3k9")
+        ]);
+    }
+
+    #[test]
+    fn reads_what_it_can_from_malformed_pages() {
+        let result = links(include_str!("fixtures/steamrip_malformed.html")).unwrap();
+        assert_eq!(result.title, "Hollow Knight");
+        let hosts: Vec<&str> = result.groups[0].links.iter().map(|link| link.host.as_str()).collect();
+        assert_eq!(hosts, ["Megadb", "Gofile"]);
+    }
+
+    #[test]
+    fn reports_changed_layouts() {
+        assert_eq!(links(include_str!("fixtures/steamrip_changed.html")).err(), Some(ParseError::Missing("h1.post-title")));
+        assert_eq!(links(include_str!("fixtures/steamrip_no_links.html")).err(), Some(ParseError::NoLinks));
+        assert_eq!(links("").err(), Some(ParseError::Missing("h1.post-title")));
+    }
+
+    #[test]
+    fn parses_search_results() {
+        let hits = SteamRip.parse_search(include_str!("fixtures/steamrip_search.html")).unwrap();
+        let hits: Vec<(&str, &str)> = hits.iter().map(|hit| (hit.title.as_str(), hit.slug.as_str())).collect();
+        assert_eq!(hits, [
+            ("Hollow Knight", "hollow-knight-free-download"),
+            ("Hollow Knight: Silksong", "hollow-knight-silksong-free-download")
+        ]);
+    }
+}
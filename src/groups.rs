@@ -0,0 +1,151 @@
+use serde::{Serialize, Deserialize};
+
+use crate::Config;
+
+/// Several aliases or commands launched together with `plz run <group>`.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Group {
+    pub members: Vec<Member>
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Member {
+    /// An alias to launch...
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    /// ...or a command to run through the shell.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Seconds to wait before starting this member.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub delay: u64,
+    /// Don't start the next member until this port (`25565` or `host:25565`) accepts connections.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait_port: Option<String>,
+    /// Don't start the next member until this file exists.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait_file: Option<String>,
+    /// How long to wait for `wait_port` or `wait_file`, in seconds (60 by default).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait_timeout: Option<u64>,
+    /// The group stops once this member exits. Defaults to the first alias in the group.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub primary: bool,
+    /// Leave this member running when the group stops.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub keep_running: bool
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+
+impl Member {
+    pub fn label(&self) -> &str {
+        self.alias.as_deref().or(self.command.as_deref()).unwrap_or_default()
+    }
+}
+
+
+/// Problems that keep `name` from running, like members pointing to missing aliases.
+pub fn validate(config: &Config, name: &str) -> Vec<String> {
+    let group = &config.groups[name];
+    let mut problems = Vec::new();
+    if config.aliases.contains_key(name) {
+        problems.push(format!("Group `{name}` has the same name as an alias, `plz run` will run the alias"));
+    }
+    if group.members.is_empty() {
+        problems.push(format!("Group `{name}` has no members"));
+    }
+    for (idx, member) in group.members.iter().enumerate() {
+        match (&member.alias, &member.command) {
+            (Some(alias), None) if !config.aliases.contains_key(alias) => {
+                problems.push(format!("Member {} of group `{name}` is alias `{alias}` which doesn't exist", idx + 1));
+            }
+            (Some(_), None) | (None, Some(_)) => {}
+            _ => problems.push(format!("Member {} of group `{name}` needs either `alias` or `command`", idx + 1))
+        }
+    }
+    if group.members.iter().filter(|member| member.primary).count() > 1 {
+        problems.push(format!("Group `{name}` has more than one primary member"));
+    }
+    problems
+}
+
+
+/// Names of the groups that launch `alias`, sorted.
+pub fn groups_using(config: &Config, alias: &str) -> Vec<String> {
+    let mut names: Vec<String> = config.groups.iter()
+        .filter(|(_, group)| group.members.iter().any(|member| member.alias.as_deref() == Some(alias)))
+        .map(|(name, _)| name.clone())
+        .collect();
+    names.sort();
+    names
+}
+
+
+/// The member the group stops with.
+pub fn primary_index(group: &Group) -> usize {
+    group.members.iter().position(|member| member.primary)
+        .or_else(|| group.members.iter().position(|member| member.alias.is_some()))
+        .unwrap_or(0)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Alias;
+
+    fn member(alias: Option<&str>, command: Option<&str>) -> Member {
+        Member { alias: alias.map(str::to_owned), command: command.map(str::to_owned), ..Member::default() }
+    }
+
+    fn config(groups: &[(&str, Vec<Member>)]) -> Config {
+        let mut config: Config = toml::from_str(crate::DEFAULT_CONFIG).unwrap();
+        for alias in ["server", "client"] {
+            config.aliases.insert(alias.to_owned(), Alias::new(format!("/games/{alias}")));
+        }
+        for (name, members) in groups {
+            config.groups.insert(name.to_string(), Group { members: members.clone() });
+        }
+        config
+    }
+
+    #[test]
+    fn finds_what_keeps_a_group_from_running() {
+        let config = config(&[
+            ("lan", vec![member(Some("server"), None), member(None, Some("sleep 1")), member(Some("client"), None)]),
+            ("broken", vec![member(Some("missing"), None), member(None, None), member(Some("client"), Some("sleep 1"))]),
+            ("client", vec![member(Some("client"), None)]),
+            ("empty", Vec::new()),
+            ("primaries", vec![
+                Member { primary: true, ..member(Some("server"), None) },
+                Member { primary: true, ..member(Some("client"), None) }
+            ])
+        ]);
+        assert!(validate(&config, "lan").is_empty());
+        assert_eq!(validate(&config, "broken"), [
+            "Member 1 of group `broken` is alias `missing` which doesn't exist",
+            "Member 2 of group `broken` needs either `alias` or `command`",
+            "Member 3 of group `broken` needs either `alias` or `command`"
+        ]);
+        assert_eq!(validate(&config, "client"), ["Group `client` has the same name as an alias, `plz run` will run the alias"]);
+        assert_eq!(validate(&config, "empty"), ["Group `empty` has no members"]);
+        assert_eq!(validate(&config, "primaries"), ["Group `primaries` has more than one primary member"]);
+        assert_eq!(groups_using(&config, "client"), ["broken", "client", "lan", "primaries"]);
+        assert!(groups_using(&config, "sleep 1").is_empty());
+    }
+
+    #[test]
+    fn stops_with_the_primary_member() {
+        let server = member(Some("server"), None);
+        let command = member(None, Some("sleep 1"));
+        let primary = Member { primary: true, ..member(Some("client"), None) };
+        // Marked explicitly, then the first alias, then just the first member.
+        assert_eq!(primary_index(&Group { members: vec![command.clone(), server.clone(), primary] }), 2);
+        assert_eq!(primary_index(&Group { members: vec![command.clone(), server] }), 1);
+        assert_eq!(primary_index(&Group { members: vec![command] }), 0);
+    }
+}
@@ -0,0 +1,90 @@
+use std::time::{UNIX_EPOCH, SystemTime};
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+use crate::data_dir;
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct History {
+    #[serde(default)]
+    pub sessions: Vec<Session>
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Session {
+    pub alias: String,
+    /// Unix timestamp (seconds) of when the game was started.
+    pub started: u64,
+    /// How long the game ran for, in seconds.
+    pub duration: u64,
+    pub exit_code: Option<i32>
+}
+
+
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+
+pub fn read_history() -> io::Result<History> {
+    let contents = match fs::read_to_string(data_dir().join("history.toml")) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(History::default()),
+        Err(err) => return Err(err)
+    };
+    toml::from_str(&contents)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("Unable to load the history file. {err}")))
+}
+
+
+pub fn save_history(history: &History) -> io::Result<()> {
+    let contents = toml::to_string(history).unwrap();
+    fs::write(data_dir().join("history.toml"), contents)
+}
+
+
+pub fn record_session(session: Session) -> io::Result<()> {
+    let mut history = read_history()?;
+    history.sessions.push(session);
+    save_history(&history)
+}
+
+
+/// Moves the recorded sessions of `old` over to `new`.
+pub fn rename(old: &str, new: &str) -> io::Result<()> {
+    let mut history = read_history()?;
+    let mut changed = false;
+    for session in history.sessions.iter_mut().filter(|session| session.alias == old) {
+        session.alias = new.to_owned();
+        changed = true;
+    }
+    if changed {
+        save_history(&history)?;
+    }
+    Ok(())
+}
+
+
+/// Total playtime in seconds and the number of sessions for every alias.
+pub fn playtime(history: &History) -> HashMap<String, (u64, usize)> {
+    let mut totals: HashMap<String, (u64, usize)> = HashMap::new();
+    for session in &history.sessions {
+        let entry = totals.entry(session.alias.clone()).or_default();
+        entry.0 += session.duration;
+        entry.1 += 1;
+    }
+    totals
+}
+
+
+pub fn format_duration(secs: u64) -> String {
+    let hours = secs / 3600;
+    let minutes = secs % 3600 / 60;
+    if hours > 0 {
+        format!("{hours}h {minutes:02}m")
+    } else {
+        format!("{minutes}m {:02}s", secs % 60)
+    }
+}
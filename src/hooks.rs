@@ -0,0 +1,146 @@
+use std::time::{Duration, Instant};
+use serde::{Serialize, Deserialize};
+use std::process::Command;
+use std::thread;
+
+use crate::launch;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HookSettings {
+    #[serde(default)]
+    pub pre_launch: Vec<String>,
+    #[serde(default)]
+    pub post_exit: Vec<String>,
+    /// How many seconds a single hook may run before it gets killed.
+    #[serde(default = "default_timeout")]
+    pub timeout: u64
+}
+
+impl Default for HookSettings {
+    fn default() -> Self {
+        HookSettings { pre_launch: Vec::new(), post_exit: Vec::new(), timeout: default_timeout() }
+    }
+}
+
+fn default_timeout() -> u64 {
+    60
+}
+
+
+#[cfg(unix)]
+pub fn shell(hook: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(hook);
+    command
+}
+
+
+#[cfg(windows)]
+pub fn shell(hook: &str) -> Command {
+    let mut command = Command::new("cmd");
+    command.arg("/C").arg(hook);
+    command
+}
+
+
+/// Runs a single hook through the system shell. Returns an error message if it failed,
+/// couldn't be started or ran past the timeout.
+fn run_hook(hook: &str, env: &[(&str, String)], timeout: Duration) -> Result<(), String> {
+    let mut command = shell(hook);
+    command.envs(env.iter().map(|(key, value)| (key, value)));
+    // In a group of its own, so a timeout stops whatever the hook started and not just the shell.
+    launch::new_process_group(&mut command);
+    let mut child = command.spawn().map_err(|err| format!("failed to start: {err}"))?;
+
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return Ok(()),
+            Ok(Some(status)) => return Err(format!("exited with {status}")),
+            Ok(None) if started.elapsed() >= timeout => {
+                launch::kill_tree(child.id(), true).ok();
+                child.wait().ok();
+                return Err(format!("timed out after {}s", timeout.as_secs()));
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(50)),
+            Err(err) => return Err(err.to_string())
+        }
+    }
+}
+
+
+/// Runs every hook in order and stops at the first one that fails.
+pub fn run_pre_launch(hooks: &[String], env: &[(&str, String)], timeout: u64) -> Result<(), String> {
+    for hook in hooks {
+        run_hook(hook, env, Duration::from_secs(timeout))
+            .map_err(|err| format!("Pre-launch hook `{hook}` {err}"))?;
+    }
+    Ok(())
+}
+
+
+/// Runs every hook in order, returning why the ones that failed did. A failing hook
+/// doesn't stop the others, the game has already exited.
+pub fn run_post_exit(hooks: &[String], env: &[(&str, String)], timeout: u64) -> Vec<String> {
+    hooks.iter()
+        .filter_map(|hook| {
+            run_hook(hook, env, Duration::from_secs(timeout))
+                .err()
+                .map(|err| format!("Post-exit hook `{hook}` {err}"))
+        })
+        .collect()
+}
+
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use std::fs;
+
+    #[test]
+    fn passes_the_environment_on() {
+        let dir = TestDir::new();
+        let out = dir.path().join("out");
+        let hook = format!("printf '%s %s' \"$PLZ_ALIAS\" \"$PLZ_PATH\" > '{}'", out.display());
+        let env = [("PLZ_ALIAS", String::from("hk")), ("PLZ_PATH", String::from("/games/hk.exe"))];
+        run_pre_launch(&[hook], &env, 10).unwrap();
+        assert_eq!(fs::read_to_string(out).unwrap(), "hk /games/hk.exe");
+    }
+
+    #[test]
+    fn stops_at_the_first_failing_pre_launch_hook() {
+        let dir = TestDir::new();
+        let marker = dir.path().join("ran");
+        let hooks = [String::from("exit 3"), format!("touch '{}'", marker.display())];
+        let err = run_pre_launch(&hooks, &[], 10).unwrap_err();
+        assert!(err.starts_with("Pre-launch hook `exit 3` exited with"), "{err}");
+        assert!(!marker.exists());
+        // Post-exit hooks all run anyway.
+        let failed = run_post_exit(&hooks, &[], 10);
+        assert_eq!(failed.len(), 1);
+        assert!(marker.exists());
+    }
+
+    #[test]
+    fn kills_what_a_hook_started_when_it_times_out() {
+        let dir = TestDir::new();
+        let pid_file = dir.path().join("pid");
+        let hook = format!("sleep 100 & echo $! > '{}'; wait", pid_file.display());
+        let err = run_pre_launch(&[hook], &[], 1).unwrap_err();
+        assert!(err.ends_with("timed out after 1s"), "{err}");
+
+        let sleep = fs::read_to_string(pid_file).unwrap().trim().to_owned();
+        // Nobody might reap the orphaned sleep, a zombie is gone as far as this goes.
+        let gone = || {
+            let output = Command::new("ps").args(["-o", "stat=", "-p", &sleep]).output().unwrap();
+            let stat = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+            stat.is_empty() || stat.starts_with('Z')
+        };
+        let asked = Instant::now();
+        while !gone() && asked.elapsed().as_secs() < 5 {
+            thread::sleep(Duration::from_millis(50));
+        }
+        assert!(gone(), "the hook's sleep kept running");
+    }
+}
@@ -0,0 +1,217 @@
+use reqwest::{Certificate, Proxy, Response, StatusCode};
+use reqwest::header::HeaderMap;
+use serde::{Serialize, Deserialize};
+use std::time::Duration;
+use tokio::time::sleep;
+use std::fs;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HttpSettings {
+    /// How long a request may take as a whole, and how long to wait for a connection or for the
+    /// next bit of a response, in seconds. Downloads only have the latter two.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// How many times to try again when a request can't connect, times out or the server is having trouble.
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// How long to wait before the first retry, in milliseconds. Doubles for every retry after it.
+    #[serde(default = "default_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    /// Proxy for every request, like `http://proxy.example.com:8080`. Without it, the
+    /// `HTTPS_PROXY`, `HTTP_PROXY` and `ALL_PROXY` environment variables are used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// PEM file with certificates to trust besides the system ones, like a proxy's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<String>,
+    #[serde(default = "default_user_agent")]
+    pub user_agent: String
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        HttpSettings {
+            timeout_secs: default_timeout_secs(),
+            retries: default_retries(),
+            retry_backoff_ms: default_retry_backoff_ms(),
+            proxy: None,
+            ca_bundle: None,
+            user_agent: default_user_agent()
+        }
+    }
+}
+
+fn default_timeout_secs() -> u64 {
+    5
+}
+
+fn default_retries() -> u32 {
+    2
+}
+
+fn default_retry_backoff_ms() -> u64 {
+    500
+}
+
+fn default_user_agent() -> String {
+    String::from("plz")
+}
+
+/// How long a download may take as a whole, they're only expected not to stall.
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// A `reqwest::Client` that retries the way the `[http]` settings say. Cheap to clone,
+/// clones share their connections.
+#[derive(Clone)]
+pub struct Client {
+    inner: reqwest::Client,
+    retries: u32,
+    backoff: Duration
+}
+
+/// Builds the client every request of plz goes through.
+pub fn client(settings: &HttpSettings) -> Result<Client, String> {
+    let timeout = Duration::from_secs(settings.timeout_secs);
+    let mut builder = reqwest::Client::builder()
+        .user_agent(&settings.user_agent)
+        .timeout(timeout)
+        .connect_timeout(timeout)
+        .read_timeout(timeout);
+    if let Some(proxy) = &settings.proxy {
+        let proxy = Proxy::all(proxy).map_err(|err| format!("http.proxy `{proxy}` is not valid. {err}"))?;
+        builder = builder.proxy(proxy);
+    }
+    if let Some(path) = &settings.ca_bundle {
+        let pem = fs::read(path).map_err(|err| format!("Unable to read http.ca_bundle `{path}`. {err}"))?;
+        let certificates = Certificate::from_pem_bundle(&pem)
+            .map_err(|err| format!("http.ca_bundle `{path}` has no valid certificates. {err}"))?;
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    let inner = builder.build().map_err(|err| err.to_string())?;
+    Ok(Client { inner, retries: settings.retries, backoff: Duration::from_millis(settings.retry_backoff_ms) })
+}
+
+
+/// Whether trying the same request again later could work.
+fn is_transient(result: &reqwest::Result<Response>) -> bool {
+    match result {
+        Ok(res) => res.status().is_server_error() || res.status() == StatusCode::TOO_MANY_REQUESTS,
+        Err(err) => err.is_connect() || err.is_timeout()
+    }
+}
+
+
+impl Client {
+    /// Sends a GET request to `url`, retrying with backoff while it fails in a way that might pass.
+    /// Gives back the last response even if it's an error status.
+    pub async fn get(&self, url: &str) -> reqwest::Result<Response> {
+        self.get_with(url, HeaderMap::new()).await
+    }
+
+    /// `get` with extra request headers.
+    pub async fn get_with(&self, url: &str, headers: HeaderMap) -> reqwest::Result<Response> {
+        self.send(url, headers, None).await
+    }
+
+    /// `get` for a file that can take a while to arrive, only a stalled download times out.
+    pub async fn download(&self, url: &str) -> reqwest::Result<Response> {
+        self.send(url, HeaderMap::new(), Some(DOWNLOAD_TIMEOUT)).await
+    }
+
+    async fn send(&self, url: &str, headers: HeaderMap, timeout: Option<Duration>) -> reqwest::Result<Response> {
+        let request = || {
+            let request = self.inner.get(url).headers(headers.clone());
+            match timeout {
+                Some(timeout) => request.timeout(timeout),
+                None => request
+            }
+        };
+        let mut backoff = self.backoff;
+        for _ in 0..self.retries {
+            let result = request().send().await;
+            if !is_transient(&result) {
+                return result;
+            }
+            sleep(backoff).await;
+            backoff *= 2;
+        }
+        request().send().await
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server;
+
+    #[tokio::test]
+    async fn retries_only_transient_failures() {
+        let base = test_server::serve(vec![
+            ("/busy", 503, b"Busy".to_vec()),
+            ("/gone", 404, b"Gone".to_vec())
+        ]).await;
+        let settings = HttpSettings { retries: 2, retry_backoff_ms: 100, ..Default::default() };
+        let client = client(&settings).unwrap();
+
+        let started = std::time::Instant::now();
+        assert_eq!(client.get(&format!("{base}/busy")).await.unwrap().status(), 503);
+        assert!(started.elapsed() >= Duration::from_millis(300));
+
+        let started = std::time::Instant::now();
+        assert_eq!(client.get(&format!("{base}/gone")).await.unwrap().status(), 404);
+        assert!(started.elapsed() < Duration::from_millis(100));
+    }
+
+    /// Serves a 10 byte body one byte every 150ms, so only a timeout for the whole request fires.
+    async fn trickle() -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    if let Ok(0) | Err(_) = socket.read(&mut [0; 4096]).await {
+                        return;
+                    }
+                    socket.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n").await.ok();
+                    for _ in 0..10 {
+                        sleep(Duration::from_millis(150)).await;
+                        if socket.write_all(b"x").await.is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn limits_how_long_a_request_takes() {
+        let base = trickle().await;
+        let client = client(&HttpSettings { timeout_secs: 1, retries: 0, ..Default::default() }).unwrap();
+
+        let started = std::time::Instant::now();
+        let body = match client.get(&base).await {
+            Ok(res) => res.bytes().await,
+            Err(err) => Err(err)
+        };
+        assert!(body.err().unwrap().is_timeout());
+        assert!(started.elapsed() < Duration::from_secs(3));
+        // Downloads go on while bytes keep coming.
+        let body = client.download(&base).await.unwrap().bytes().await.unwrap();
+        assert_eq!(body.len(), 10);
+    }
+
+    #[test]
+    fn reports_bad_settings() {
+        let settings = HttpSettings { proxy: Some(String::from("not a url")), ..Default::default() };
+        assert!(client(&settings).err().unwrap().starts_with("http.proxy `not a url`"));
+        let settings = HttpSettings { ca_bundle: Some(String::from("/nonexistent/ca.pem")), ..Default::default() };
+        assert!(client(&settings).err().unwrap().starts_with("Unable to read http.ca_bundle"));
+    }
+}
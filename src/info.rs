@@ -0,0 +1,492 @@
+use reqwest::header::HeaderMap;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use std::path::Path;
+use reqwest::Url;
+
+use crate::http::Client;
+use crate::{cache, Config};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct InfoSettings {
+    /// The MediaWiki API of PCGamingWiki.
+    #[serde(default = "default_api_url")]
+    pub api_url: String,
+    /// How long looked up games are reused without asking PCGamingWiki again, in hours.
+    #[serde(default = "default_cache_ttl_hours")]
+    pub cache_ttl_hours: u64
+}
+
+impl Default for InfoSettings {
+    fn default() -> Self {
+        InfoSettings { api_url: default_api_url(), cache_ttl_hours: default_cache_ttl_hours() }
+    }
+}
+
+fn default_api_url() -> String {
+    String::from("https://www.pcgamingwiki.com/w/api.php")
+}
+
+fn default_cache_ttl_hours() -> u64 {
+    7 * 24
+}
+
+/// The cache namespace of looked up games.
+const CACHE: &str = "info";
+
+/// What PCGamingWiki knows about a game.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GameInfo {
+    /// The title of the game's page.
+    pub page: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub steam_appid: Option<u32>,
+    pub engines: Vec<String>,
+    pub saves: Vec<GamePath>,
+    pub config: Vec<GamePath>,
+    /// Descriptions of the fixes for issues the game has.
+    pub fixes: Vec<String>
+}
+
+/// A location from the game data section of a page.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GamePath {
+    /// Like `Windows`, `Linux` or `Steam Play (Linux)`.
+    pub platform: String,
+    /// As written on the wiki, with placeholders like `{{p|appdata}}`.
+    pub path: String
+}
+
+/// How the wiki writes its path placeholders out, and what they are as a save location when
+/// plz can tell. `None` entries can't be turned into one (registry keys, the Steam folder...).
+const PLACEHOLDERS: [(&str, &str, Option<&str>); 15] = [
+    ("game", "<path-to-game>", Some("${game_dir}")),
+    ("uid", "<user-id>", Some("*")),
+    ("userprofile", "%USERPROFILE%", Some("${USERPROFILE}")),
+    ("userprofile\\documents", "%USERPROFILE%\\Documents", Some("${USERPROFILE}/Documents")),
+    ("appdata", "%APPDATA%", Some("${USERPROFILE}/AppData/Roaming")),
+    ("localappdata", "%LOCALAPPDATA%", Some("${USERPROFILE}/AppData/Local")),
+    ("programdata", "%PROGRAMDATA%", None),
+    ("public", "%PUBLIC%", None),
+    ("steam", "<Steam-folder>", None),
+    ("hkcu", "HKEY_CURRENT_USER", None),
+    ("hklm", "HKEY_LOCAL_MACHINE", None),
+    ("linuxhome", "~", Some("${HOME}")),
+    ("xdgdatahome", "$XDG_DATA_HOME", Some("${HOME}/.local/share")),
+    ("xdgconfighome", "$XDG_CONFIG_HOME", Some("${HOME}/.config")),
+    ("osxhome", "~", Some("${HOME}"))
+];
+
+impl GamePath {
+    /// Replaces every `{{p|name}}` in the path with what `replace` gives for it, or returns `None`
+    /// as soon as it gives nothing.
+    fn replace_placeholders(&self, replace: impl Fn(&(&str, &str, Option<&str>)) -> Option<String>) -> Option<String> {
+        let mut result = String::new();
+        let mut rest = self.path.as_str();
+        while let Some(start) = [rest.find("{{p|"), rest.find("{{P|")].into_iter().flatten().min() {
+            let end = start + rest[start..].find("}}")?;
+            let name = rest[start + 4..end].trim().to_lowercase();
+            let placeholder = PLACEHOLDERS.iter().find(|(known, _, _)| *known == name)?;
+            result.push_str(&rest[..start]);
+            result.push_str(&replace(placeholder)?);
+            rest = &rest[end + 2..];
+        }
+        result.push_str(rest);
+        Some(result.trim().to_owned())
+    }
+
+    /// The path the way the wiki shows it.
+    pub fn display(&self) -> String {
+        self.replace_placeholders(|(_, shown, _)| Some(shown.to_string())).unwrap_or_else(|| self.path.clone())
+    }
+
+    /// The path as a save location for `saves add`. Windows paths of games running through
+    /// a runner like wine are looked for inside `${WINEPREFIX}`.
+    pub fn save_location(&self, in_wine: bool) -> Option<String> {
+        let location = self.replace_placeholders(|(_, _, location)| location.map(str::to_owned))?;
+        let location = match in_wine {
+            true => location.replace("${USERPROFILE}", "${WINEPREFIX}/drive_c/users/*"),
+            false => location.replace("${USERPROFILE}", "${HOME}")
+        };
+        let location = location.replace('\\', "/");
+        Some(location.trim_end_matches('/').to_owned())
+    }
+}
+
+
+impl GameInfo {
+    /// The save locations that fit how `alias` is run, as globs for its `saves`, or `None`
+    /// if there's no such alias.
+    pub fn save_locations(&self, config: &Config, alias: &str) -> Option<Vec<String>> {
+        let entry = config.aliases.get(alias)?;
+        let windows_game = cfg!(windows) || entry.runner.is_some() || entry.path.to_lowercase().ends_with(".exe");
+        let in_wine = windows_game && !cfg!(windows);
+        let platform = match windows_game {
+            true => "Windows",
+            false if cfg!(target_os = "macos") => "OS X",
+            false => "Linux"
+        };
+        let mut locations: Vec<String> = self.saves.iter()
+            .filter(|save| save.platform == platform)
+            .filter_map(|save| save.save_location(in_wine))
+            .collect();
+        locations.dedup();
+        Some(locations)
+    }
+}
+
+
+/// The name of the game `alias` points to: the folder it's in, right under games_dir or a
+/// library, or the folder of the executable otherwise.
+pub fn guess_title(config: &Config, alias: &str) -> String {
+    let path = config.alias_path(alias).unwrap_or_default();
+    let path = Path::new(&path);
+    let roots = config.path_vars().into_iter().filter(|(name, _)| name != "HOME").map(|(_, root)| root);
+    for root in roots.filter(|root| !root.is_empty()) {
+        let Ok(rest) = path.strip_prefix(&root) else {
+            continue;
+        };
+        // Only a folder counts, not the executable itself.
+        let mut components = rest.components();
+        if let (Some(folder), Some(_)) = (components.next(), components.next()) {
+            return folder.as_os_str().to_string_lossy().into_owned();
+        }
+    }
+    path.parent()
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| alias.to_owned())
+}
+
+
+/// `text` without HTML comments and `<ref>` footnotes.
+fn strip_markup(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    loop {
+        let next = [("<!--", "-->"), ("<ref", "</ref>")].iter()
+            .filter_map(|(open, close)| rest.find(open).map(|start| (start, *open, *close)))
+            .min_by_key(|(start, _, _)| *start);
+        let Some((start, open, close)) = next else {
+            break;
+        };
+        result.push_str(&rest[..start]);
+        let tag = &rest[start..];
+        // `<ref name="x" />` has no closing tag.
+        let self_closing = open == "<ref" && tag.find('>').is_some_and(|end| tag[..end].ends_with('/'));
+        let end = match self_closing {
+            true => tag.find('>').map(|end| end + 1),
+            false => tag.find(close).map(|end| end + close.len())
+        };
+        // An unclosed comment or footnote runs until the end.
+        let Some(end) = end else {
+            return result;
+        };
+        rest = &tag[end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+
+/// The arguments of every `{{name|...}}` template in `wikitext`, split on the `|` that aren't
+/// inside a nested template or link.
+fn templates(wikitext: &str, name: &str) -> Vec<Vec<String>> {
+    let mut found = Vec::new();
+    let open = format!("{{{{{name}|");
+    let mut rest = wikitext;
+    while let Some(start) = rest.find(&open) {
+        let body = &rest[start + open.len()..];
+        let mut args = vec![String::new()];
+        let mut depth = 0;
+        let mut chars = body.char_indices().peekable();
+        let mut end = body.len();
+        while let Some((idx, char)) = chars.next() {
+            let pair = chars.peek().map(|(_, next)| *next);
+            match (char, pair) {
+                ('{', Some('{')) | ('[', Some('[')) => {
+                    depth += 1;
+                    args.last_mut().unwrap().push_str(&body[idx..idx + 2]);
+                    chars.next();
+                }
+                ('}', Some('}')) if depth == 0 => {
+                    end = idx + 2;
+                    break;
+                }
+                ('}', Some('}')) | (']', Some(']')) => {
+                    depth -= 1;
+                    args.last_mut().unwrap().push_str(&body[idx..idx + 2]);
+                    chars.next();
+                }
+                ('|', _) if depth == 0 => args.push(String::new()),
+                _ => args.last_mut().unwrap().push(char)
+            }
+        }
+        found.push(args.into_iter().map(|arg| arg.trim().to_owned()).collect());
+        rest = &body[end.min(body.len())..];
+    }
+    found
+}
+
+
+/// `[[Page|text]]` and `[[text]]` as their text, without bold and italic quotes.
+fn plain_text(wikitext: &str) -> String {
+    let mut result = String::new();
+    let mut rest = wikitext;
+    while let Some(start) = rest.find("[[") {
+        let Some(end) = rest[start..].find("]]").map(|end| start + end) else {
+            break;
+        };
+        let link = &rest[start + 2..end];
+        result.push_str(&rest[..start]);
+        result.push_str(link.rsplit('|').next().unwrap_or(link));
+        rest = &rest[end + 2..];
+    }
+    result.push_str(rest);
+    result.replace("'''", "").replace("''", "").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+
+fn game_paths(wikitext: &str, template: &str) -> Vec<GamePath> {
+    templates(wikitext, template).into_iter()
+        .filter_map(|args| {
+            let (platform, paths) = args.split_first()?;
+            Some(paths.iter()
+                .filter(|path| !path.is_empty())
+                .map(|path| GamePath { platform: platform.clone(), path: path.clone() })
+                .collect::<Vec<_>>())
+        })
+        .flatten()
+        .collect()
+}
+
+
+/// The save and config locations and the fixes on a game's page.
+pub fn parse_page(wikitext: &str) -> (Vec<GamePath>, Vec<GamePath>, Vec<String>) {
+    let wikitext = strip_markup(wikitext);
+    let fixes = templates(&wikitext, "Fixbox").into_iter()
+        .filter_map(|args| {
+            args.iter()
+                .filter_map(|arg| arg.split_once('='))
+                .find(|(name, _)| name.trim() == "description")
+                .map(|(_, description)| plain_text(description))
+        })
+        .filter(|fix| !fix.is_empty())
+        .collect();
+    (game_paths(&wikitext, "Game data/saves"), game_paths(&wikitext, "Game data/config"), fixes)
+}
+
+
+async fn get_json(client: &Client, url: &str) -> Result<Value, String> {
+    let res = client.get(url).await.map_err(|err| format!("Error sending request: {err}"))?;
+    if !res.status().is_success() {
+        return Err(format!("PCGamingWiki answered with {}", res.status()));
+    }
+    res.json().await.map_err(|err| format!("PCGamingWiki sent something unexpected. {err}"))
+}
+
+
+fn api_url(settings: &InfoSettings, params: &[(&str, &str)]) -> Result<String, String> {
+    Url::parse_with_params(&settings.api_url, params)
+        .map(String::from)
+        .map_err(|err| format!("info.api_url `{}` is not valid. {err}", settings.api_url))
+}
+
+
+/// Asks PCGamingWiki about the game called `title`.
+pub async fn lookup(client: &Client, settings: &InfoSettings, title: &str) -> Result<GameInfo, String> {
+    let condition = format!("Infobox_game._pageName=\"{}\"", title.replace('"', "\\\""));
+    let url = api_url(settings, &[
+        ("action", "cargoquery"),
+        ("tables", "Infobox_game"),
+        ("fields", "Infobox_game._pageName=Page,Infobox_game.Steam_AppID=AppID,Infobox_game.Engines=Engines"),
+        ("where", &condition),
+        ("limit", "1"),
+        ("format", "json")
+    ])?;
+    let found = get_json(client, &url).await?;
+    let row = found["cargoquery"].get(0).map(|row| &row["title"])
+        .ok_or_else(|| format!("PCGamingWiki has no page for `{title}`"))?;
+    let page = row["Page"].as_str().unwrap_or(title).to_owned();
+    let steam_appid = row["AppID"].as_str()
+        .and_then(|ids| ids.split(',').next())
+        .and_then(|id| id.trim().parse().ok());
+    let engines = row["Engines"].as_str().unwrap_or_default()
+        .split(',')
+        .map(|engine| engine.trim().trim_start_matches("Engine:").to_owned())
+        .filter(|engine| !engine.is_empty())
+        .collect();
+
+    let url = api_url(settings, &[
+        ("action", "parse"),
+        ("page", &page),
+        ("prop", "wikitext"),
+        ("format", "json"),
+        ("formatversion", "2")
+    ])?;
+    let parsed = get_json(client, &url).await?;
+    let wikitext = parsed["parse"]["wikitext"].as_str()
+        .ok_or_else(|| format!("PCGamingWiki didn't send the page of `{page}`"))?;
+    let (saves, config, fixes) = parse_page(wikitext);
+    let url = format!("https://www.pcgamingwiki.com/wiki/{}", page.replace(' ', "_"));
+    Ok(GameInfo { page, url, steam_appid, engines, saves, config, fixes })
+}
+
+
+/// `lookup` through the cache, unless `refresh` is set.
+pub async fn lookup_cached(client: &Client, cache_dir: &Path, settings: &InfoSettings, title: &str, refresh: bool) -> Result<GameInfo, String> {
+    let key = title.to_lowercase();
+    if !refresh {
+        let cached: Option<cache::Entry<GameInfo>> = cache::read(cache_dir, CACHE, &key);
+        if let Some(entry) = cached.filter(|entry| entry.is_fresh(settings.cache_ttl_hours * 60 * 60)) {
+            return Ok(entry.value);
+        }
+    }
+    let entry = cache::Entry::new(&key, lookup(client, settings, title).await?, &HeaderMap::new());
+    cache::write(cache_dir, CACHE, &entry).ok();
+    Ok(entry.value)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{http, test_server};
+    use crate::test_dir::TestDir;
+
+    const WIKITEXT: &str = r#"{{Infobox game
+|steam appid  = 367520
+}}
+'''Hollow Knight''' is a 2D action game.<ref>{{Refurl|url=https://example.com|title=Review}}</ref>
+
+===Save game data location===
+{{Game data|
+{{Game data/saves|Windows|{{p|userprofile}}\AppData\LocalLow\Team Cherry\Hollow Knight\<ref name="saves"/>}}
+{{Game data/saves|OS X|{{p|osxhome}}/Library/Application Support/unity.Team Cherry.Hollow Knight/}}
+{{Game data/saves|Linux|{{p|xdgconfighome}}/unity3d/Team Cherry/Hollow Knight/}}
+{{Game data/saves|Steam Play (Linux)|{{p|uid}}|}}
+}}
+
+===Configuration file(s) location===
+{{Game data|
+{{Game data/config|Windows|{{P|hkcu}}\Software\Team Cherry\Hollow Knight|{{p|game}}\hollow_knight_Data\boot.config}}
+<!-- {{Game data/config|Linux|commented out}} -->
+}}
+
+===Stuttering===
+{{Fixbox|description=Use [[Glossary:Vertical sync (Vsync)|vertical sync]] ''instead''|ref={{cn}}|fix=
+# Open the options.
+}}
+{{Fixbox|description=Set a [[frame rate]] cap|ref=<ref>{{Refcheck|user=Someone|date=2024}}</ref>|fix=}}
+"#;
+
+    #[test]
+    fn parses_the_game_data_and_fixes() {
+        let (saves, config, fixes) = parse_page(WIKITEXT);
+        let saves: Vec<(&str, String)> = saves.iter().map(|save| (save.platform.as_str(), save.display())).collect();
+        assert_eq!(saves, [
+            ("Windows", String::from("%USERPROFILE%\\AppData\\LocalLow\\Team Cherry\\Hollow Knight\\")),
+            ("OS X", String::from("~/Library/Application Support/unity.Team Cherry.Hollow Knight/")),
+            ("Linux", String::from("$XDG_CONFIG_HOME/unity3d/Team Cherry/Hollow Knight/")),
+            ("Steam Play (Linux)", String::from("<user-id>"))
+        ]);
+        let config: Vec<String> = config.iter().map(GamePath::display).collect();
+        assert_eq!(config, ["HKEY_CURRENT_USER\\Software\\Team Cherry\\Hollow Knight", "<path-to-game>\\hollow_knight_Data\\boot.config"]);
+        assert_eq!(fixes, ["Use vertical sync instead", "Set a frame rate cap"]);
+    }
+
+    #[test]
+    fn turns_paths_into_save_locations() {
+        let path = |path: &str| GamePath { platform: String::from("Windows"), path: path.to_owned() };
+        let saves = path("{{p|appdata}}\\Team Cherry\\{{p|uid}}\\");
+        assert_eq!(saves.save_location(true).unwrap(), "${WINEPREFIX}/drive_c/users/*/AppData/Roaming/Team Cherry/*");
+        assert_eq!(saves.save_location(false).unwrap(), "${HOME}/AppData/Roaming/Team Cherry/*");
+        assert_eq!(path("{{p|game}}\\Saves").save_location(true).unwrap(), "${game_dir}/Saves");
+        assert!(path("{{p|hkcu}}\\Software\\Team Cherry").save_location(true).is_none());
+        assert!(path("{{p|somewhere}}\\Saves").save_location(true).is_none());
+    }
+
+    #[tokio::test]
+    async fn looks_games_up_on_the_api() {
+        let query = |params: &[(&str, &str)]| {
+            let url = Url::parse_with_params("http://localhost/w/api.php", params).unwrap();
+            format!("{}?{}", url.path(), url.query().unwrap())
+        };
+        let cargo = query(&[
+            ("action", "cargoquery"),
+            ("tables", "Infobox_game"),
+            ("fields", "Infobox_game._pageName=Page,Infobox_game.Steam_AppID=AppID,Infobox_game.Engines=Engines"),
+            ("where", "Infobox_game._pageName=\"hollow knight\""),
+            ("limit", "1"),
+            ("format", "json")
+        ]);
+        let parse = query(&[
+            ("action", "parse"),
+            ("page", "Hollow Knight"),
+            ("prop", "wikitext"),
+            ("format", "json"),
+            ("formatversion", "2")
+        ]);
+        let found = r#"{"cargoquery":[{"title":{"Page":"Hollow Knight","AppID":"367520,1234","Engines":"Engine:Unity"}}]}"#;
+        let page = serde_json::json!({ "parse": { "title": "Hollow Knight", "wikitext": WIKITEXT } }).to_string();
+        let base = test_server::serve(vec![
+            (&cargo, 200, found.as_bytes().to_vec()),
+            (&parse, 200, page.into_bytes()),
+            ("/w/api.php", 200, br#"{"cargoquery":[]}"#.to_vec())
+        ]).await;
+        let settings = InfoSettings { api_url: format!("{base}/w/api.php"), ..Default::default() };
+        let client = http::client(&http::HttpSettings::default()).unwrap();
+
+        let info = lookup(&client, &settings, "hollow knight").await.unwrap();
+        assert_eq!(info.page, "Hollow Knight");
+        assert_eq!(info.url, "https://www.pcgamingwiki.com/wiki/Hollow_Knight");
+        assert_eq!(info.steam_appid, Some(367520));
+        assert_eq!(info.engines, ["Unity"]);
+        assert_eq!(info.saves.len(), 4);
+        assert_eq!(info.fixes.len(), 2);
+
+        let err = lookup(&client, &settings, "Hollow Night").await.err().unwrap();
+        assert_eq!(err, "PCGamingWiki has no page for `Hollow Night`");
+    }
+
+    #[tokio::test]
+    async fn reuses_looked_up_games() {
+        let found = r#"{"cargoquery":[{"title":{"Page":"Hollow Knight","AppID":"367520","Engines":""}}]}"#;
+        let page = serde_json::json!({ "parse": { "title": "Hollow Knight", "wikitext": WIKITEXT } }).to_string();
+        let url = Url::parse_with_params("http://localhost/w/api.php", &[
+            ("action", "cargoquery"),
+            ("tables", "Infobox_game"),
+            ("fields", "Infobox_game._pageName=Page,Infobox_game.Steam_AppID=AppID,Infobox_game.Engines=Engines"),
+            ("where", "Infobox_game._pageName=\"Hollow Knight\""),
+            ("limit", "1"),
+            ("format", "json")
+        ]).unwrap();
+        let cargo = format!("{}?{}", url.path(), url.query().unwrap());
+        let base = test_server::serve(vec![
+            (&cargo, 200, found.as_bytes().to_vec()),
+            ("/w/api.php", 200, page.into_bytes())
+        ]).await;
+        let settings = InfoSettings { api_url: format!("{base}/w/api.php"), ..Default::default() };
+        let client = http::client(&http::HttpSettings::default()).unwrap();
+        let dir = TestDir::new();
+
+        let info = lookup_cached(&client, dir.path(), &settings, "Hollow Knight", false).await.unwrap();
+        assert_eq!(info.steam_appid, Some(367520));
+
+        // Changing the cached game shows whether it's asked for again.
+        let mut entry: cache::Entry<GameInfo> = cache::read(dir.path(), CACHE, "hollow knight").unwrap();
+        entry.value.steam_appid = Some(1);
+        cache::write(dir.path(), CACHE, &entry).unwrap();
+        let info = lookup_cached(&client, dir.path(), &settings, "hollow knight", false).await.unwrap();
+        assert_eq!(info.steam_appid, Some(1));
+
+        let info = lookup_cached(&client, dir.path(), &settings, "Hollow Knight", true).await.unwrap();
+        assert_eq!(info.steam_appid, Some(367520));
+
+        entry.stored = 0;
+        cache::write(dir.path(), CACHE, &entry).unwrap();
+        let info = lookup_cached(&client, dir.path(), &settings, "Hollow Knight", false).await.unwrap();
+        assert_eq!(info.steam_appid, Some(367520));
+    }
+}
@@ -0,0 +1,173 @@
+use serde::{Serialize, Deserialize};
+use std::time::{Duration, Instant};
+use std::process::Child;
+use std::path::PathBuf;
+use std::fs::{self, File};
+use std::thread;
+use std::io;
+
+use crate::history::now;
+use crate::{data_dir, launch};
+
+/// A game started by plz that hasn't exited yet, kept in `running.toml`.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Instance {
+    pub alias: String,
+    pub pid: u32,
+    /// Unix timestamp (seconds) of when the game was started.
+    pub started: u64,
+    /// What `launch::process_start` said about the game's process when it was registered,
+    /// so a pid the system gave to another process since isn't mistaken for the game.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process_start: Option<String>
+}
+
+impl Instance {
+    /// Whether the game's process is still there, and still the one that was registered.
+    pub fn is_running(&self) -> bool {
+        match &self.process_start {
+            Some(start) => launch::process_start(self.pid).as_ref() == Some(start),
+            // Nothing to compare with if the start couldn't be read back then.
+            None => launch::is_alive(self.pid)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Instances {
+    #[serde(default)]
+    instances: Vec<Instance>
+}
+
+
+fn instances_path() -> PathBuf {
+    data_dir().join("running.toml")
+}
+
+
+fn read_instances() -> Vec<Instance> {
+    fs::read_to_string(instances_path())
+        .ok()
+        .and_then(|contents| toml::from_str::<Instances>(&contents).ok())
+        .map(|instances| instances.instances)
+        .unwrap_or_default()
+}
+
+
+fn save_instances(instances: Vec<Instance>) {
+    let contents = toml::to_string(&Instances { instances }).unwrap();
+    fs::write(instances_path(), contents).ok();
+}
+
+
+/// Runs `change` on the instances and saves them if they changed, holding `running.lock`
+/// meanwhile so plz processes starting and stopping games at the same time don't drop
+/// each other's entries.
+fn update<T>(change: impl FnOnce(&mut Vec<Instance>) -> T) -> T {
+    // Without the lock file the data folder can't be written to, so nothing gets saved anyway.
+    let lock = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(data_dir().join("running.lock"))
+        .and_then(|file| file.lock().map(|_| file));
+    let mut instances = read_instances();
+    let before = instances.clone();
+    let result = change(&mut instances);
+    if instances != before {
+        save_instances(instances);
+    }
+    drop(lock);
+    result
+}
+
+
+/// Starts a game with `spawn` and registers it as `alias`. Unless `allow_multiple` is set,
+/// fails without calling `spawn` if `alias` is already running. The check and the
+/// registration happen under one lock, so two launches at once can't both get through.
+pub fn start(alias: &str, allow_multiple: bool, spawn: impl FnOnce() -> io::Result<Child>) -> io::Result<Child> {
+    update(|instances| {
+        instances.retain(Instance::is_running);
+        if let Some(running) = instances.iter().find(|instance| instance.alias == alias).filter(|_| !allow_multiple) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("`{alias}` is already running (pid {})", running.pid)
+            ));
+        }
+        let child = spawn()?;
+        let pid = child.id();
+        instances.retain(|instance| instance.pid != pid);
+        instances.push(Instance { alias: alias.to_owned(), pid, started: now(), process_start: launch::process_start(pid) });
+        Ok(child)
+    })
+}
+
+
+pub fn unregister(pid: u32) {
+    update(|instances| instances.retain(|instance| instance.pid != pid));
+}
+
+
+/// Every instance that's still alive, oldest first. Entries left behind by a plz
+/// that got killed before its game exited, or whose pid now belongs to another process,
+/// are dropped here.
+pub fn running() -> Vec<Instance> {
+    let mut alive = update(|instances| {
+        instances.retain(Instance::is_running);
+        instances.clone()
+    });
+    alive.sort_by_key(|instance| instance.started);
+    alive
+}
+
+
+pub fn running_alias(alias: &str) -> Vec<Instance> {
+    running().into_iter().filter(|instance| instance.alias == alias).collect()
+}
+
+
+/// How long an instance gets to exit after being asked to, before it's killed.
+const KILL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Stops every running instance of `alias` with its whole process tree, returning how many there were.
+pub fn kill(alias: &str, force: bool) -> io::Result<usize> {
+    let instances = running_alias(alias);
+    for instance in &instances {
+        launch::kill_tree(instance.pid, force)?;
+    }
+    let asked = Instant::now();
+    while instances.iter().any(Instance::is_running) && asked.elapsed() < KILL_TIMEOUT {
+        thread::sleep(Duration::from_millis(100));
+    }
+    for instance in instances.iter().filter(|instance| instance.is_running()) {
+        launch::kill_tree(instance.pid, true)?;
+    }
+    // The plz waiting on the game unregisters it, this only matters if that one is gone too.
+    for instance in &instances {
+        if !instance.is_running() {
+            unregister(instance.pid);
+        }
+    }
+    Ok(instances.len())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_reused_pids_apart() {
+        let pid = std::process::id();
+        let start = launch::process_start(pid);
+        assert!(start.is_some());
+        assert_eq!(launch::process_start(pid), start);
+
+        let instance = Instance { alias: String::from("game"), pid, started: now(), process_start: start };
+        assert!(instance.is_running());
+        let reused = Instance { process_start: Some(String::from("some other process")), ..instance.clone() };
+        assert!(!reused.is_running());
+        let gone = Instance { pid: 999_999_999, process_start: None, ..instance };
+        assert!(!gone.is_running());
+    }
+}
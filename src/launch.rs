@@ -0,0 +1,404 @@
+use std::process::{Child, Command, Stdio};
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Instant;
+use std::path::Path;
+use std::fs::File;
+use std::thread;
+
+use crate::history::{now, Session};
+use crate::{hooks, instances, saves, Config};
+
+/// A game started with `start`, registered as running until it's waited for.
+pub struct Game {
+    pub alias: String,
+    child: Child,
+    started: u64,
+    perf: Instant,
+    tees: Option<(JoinHandle<()>, JoinHandle<()>)>
+}
+
+/// What `post_exit` did, for the caller to report.
+pub struct AfterExit {
+    /// The automatic save backup, if the alias takes one.
+    pub backup: Option<io::Result<saves::Backup>>,
+    /// Why the post-exit hooks that failed did.
+    pub failed_hooks: Vec<String>
+}
+
+
+/// Copies everything from `reader` both to the terminal and to the shared log file.
+fn tee<R, W>(mut reader: R, mut terminal: W, log: Arc<Mutex<File>>) -> thread::JoinHandle<()>
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static
+{
+    thread::spawn(move || {
+        let mut buf = [0; 8192];
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n
+            };
+            terminal.write_all(&buf[..n]).ok();
+            terminal.flush().ok();
+            log.lock().unwrap().write_all(&buf[..n]).ok();
+        }
+    })
+}
+
+
+/// Splits a runner into the program and its arguments at whitespace, except inside single
+/// or double quotes, so paths with spaces like Proton's can be quoted. Backslashes are kept
+/// as they are for Windows paths.
+pub fn split_runner(runner: &str) -> Result<Vec<String>, String> {
+    let mut parts = Vec::new();
+    let mut part: Option<String> = None;
+    let mut quote = None;
+    for c in runner.chars() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), c) => part.get_or_insert_with(String::new).push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                part.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => parts.extend(part.take()),
+            (None, c) => part.get_or_insert_with(String::new).push(c)
+        }
+    }
+    if let Some(open) = quote {
+        return Err(format!("runner `{runner}` has an unclosed {open}"));
+    }
+    parts.extend(part);
+    Ok(parts)
+}
+
+
+/// The command that starts `alias` from its game folder, going through its runner if it has one.
+pub fn game_command(config: &Config, alias: &str, path: &Path) -> io::Result<Command> {
+    let entry = &config.aliases[alias];
+    let runner = entry.runner.as_deref().map(split_runner).transpose()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?
+        .unwrap_or_default();
+    let runner: Vec<String> = runner.iter().map(|part| config.expand_path(part)).collect();
+    let mut command = match runner.split_first() {
+        Some((program, runner_args)) => {
+            let mut command = Command::new(program);
+            command.args(runner_args).arg(path);
+            command
+        }
+        None => Command::new(path)
+    };
+    command.args(entry.args.iter().map(|arg| config.expand_path(arg)));
+    command.envs(entry.env.iter().map(|(name, value)| (name, config.expand_path(value))));
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        command.current_dir(dir);
+    }
+    Ok(command)
+}
+
+
+/// Starts `alias` in a process group of its own and registers it as running. Fails if it's
+/// already running, unless `allow_multiple` is set. With `log`, the game's output is copied
+/// into it as well as to the terminal.
+pub fn start(config: &Config, alias: &str, log: Option<File>, allow_multiple: bool) -> io::Result<Game> {
+    let path = config.resolve(alias)?;
+    let mut command = game_command(config, alias, &path)?;
+    if log.is_some() {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+    new_process_group(&mut command);
+    let mut child = instances::start(alias, allow_multiple, || command.spawn())?;
+    let tees = log.map(|log| {
+        let log = Arc::new(Mutex::new(log));
+        let stdout = tee(child.stdout.take().unwrap(), io::stdout(), log.clone());
+        let stderr = tee(child.stderr.take().unwrap(), io::stderr(), log);
+        (stdout, stderr)
+    });
+    Ok(Game { alias: alias.to_owned(), child, started: now(), perf: Instant::now(), tees })
+}
+
+
+impl Game {
+    pub fn pid(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Waits for the game to exit. The session isn't recorded yet, that's up to
+    /// the caller with `history::record_session`.
+    pub fn wait(mut self) -> io::Result<Session> {
+        let status = self.child.wait();
+        instances::unregister(self.child.id());
+        if let Some((stdout, stderr)) = self.tees {
+            stdout.join().ok();
+            stderr.join().ok();
+        }
+        Ok(Session {
+            alias: self.alias,
+            started: self.started,
+            duration: self.perf.elapsed().as_secs(),
+            exit_code: status?.code()
+        })
+    }
+}
+
+
+fn hook_env(alias: &str, path: &Path, session: Option<&Session>) -> Vec<(&'static str, String)> {
+    let mut env = vec![
+        ("PLZ_ALIAS", alias.to_owned()),
+        ("PLZ_PATH", path.display().to_string())
+    ];
+    if let Some(session) = session {
+        let code = session.exit_code.map(|code| code.to_string()).unwrap_or_default();
+        env.push(("PLZ_EXIT_CODE", code));
+        env.push(("PLZ_DURATION", session.duration.to_string()));
+    }
+    env
+}
+
+
+/// Runs the global and then the alias' own pre-launch hooks, stopping at the first that fails.
+pub fn pre_launch(config: &Config, alias: &str, path: &Path) -> Result<(), String> {
+    let entry = &config.aliases[alias];
+    let hooks: Vec<String> = config.hooks.pre_launch.iter().chain(&entry.pre_launch).cloned().collect();
+    hooks::run_pre_launch(&hooks, &hook_env(alias, path, None), config.hooks.timeout)
+}
+
+
+/// Backs up the saves if the alias wants that, then runs its own and the global post-exit hooks.
+pub fn post_exit(config: &Config, alias: &str, path: &Path, session: Option<&Session>) -> AfterExit {
+    let entry = &config.aliases[alias];
+    let backup = (entry.auto_backup.unwrap_or(config.saves.auto_backup) && !entry.saves.is_empty())
+        .then(|| saves::backup(config, alias));
+    let hooks: Vec<String> = entry.post_exit.iter().chain(&config.hooks.post_exit).cloned().collect();
+    let failed_hooks = hooks::run_post_exit(&hooks, &hook_env(alias, path, session), config.hooks.timeout);
+    AfterExit { backup, failed_hooks }
+}
+
+
+/// Fails if `alias` is already running, so it isn't started twice by accident.
+pub fn ensure_not_running(alias: &str) -> io::Result<()> {
+    match instances::running_alias(alias).first() {
+        Some(instance) => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("`{alias}` is already running (pid {})", instance.pid)
+        )),
+        None => Ok(())
+    }
+}
+
+
+/// Keeps the child running after the terminal it was started from goes away.
+#[cfg(unix)]
+pub fn detach_from_terminal(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    command.process_group(0);
+}
+
+
+#[cfg(windows)]
+pub fn detach_from_terminal(command: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    const DETACHED_PROCESS: u32 = 0x00000008;
+    const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
+    command.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+}
+
+
+/// Starts the child in a process group of its own, so `kill_tree` can reach
+/// everything it launches. The terminal's Ctrl+C doesn't reach the group anymore,
+/// it's up to the caller to pass it on.
+#[cfg(unix)]
+pub fn new_process_group(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    command.process_group(0);
+}
+
+
+#[cfg(windows)]
+pub fn new_process_group(command: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
+    command.creation_flags(CREATE_NEW_PROCESS_GROUP);
+}
+
+
+/// `pid` and every process started by it, found through `ps`.
+#[cfg(unix)]
+fn process_tree(pid: u32) -> io::Result<Vec<u32>> {
+    let output = Command::new("ps").args(["-A", "-o", "pid=", "-o", "ppid="]).output()?;
+    let parents: Vec<(u32, u32)> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace().map(|field| field.parse::<u32>().ok());
+            Some((fields.next()??, fields.next()??))
+        })
+        .collect();
+    let mut tree = vec![pid];
+    let mut idx = 0;
+    while idx < tree.len() {
+        let parent = tree[idx];
+        tree.extend(parents.iter().filter(|(_, ppid)| *ppid == parent).map(|(child, _)| *child));
+        idx += 1;
+    }
+    Ok(tree)
+}
+
+
+/// Asks `pid`, its process group and everything it started to exit, or kills them with `force`.
+/// Games are started as the leader of their own group (see `new_process_group`), so this
+/// also reaches processes that left the tree, like the ones Wine hands over to init.
+#[cfg(unix)]
+pub fn kill_tree(pid: u32, force: bool) -> io::Result<()> {
+    let signal = if force { "-KILL" } else { "-TERM" };
+    let tree = process_tree(pid)?;
+    let group = Command::new("kill")
+        .arg(signal)
+        .arg("--")
+        .arg(format!("-{pid}"))
+        .stderr(Stdio::null())
+        .status()?;
+    let processes = Command::new("kill")
+        .arg(signal)
+        .args(tree.iter().map(u32::to_string))
+        .stderr(Stdio::null())
+        .status()?;
+    if !group.success() && !processes.success() {
+        return Err(io::Error::other(format!("No process {pid} to signal")));
+    }
+    Ok(())
+}
+
+
+#[cfg(unix)]
+pub fn is_alive(pid: u32) -> bool {
+    Command::new("kill")
+        .arg("-0")
+        .arg(pid.to_string())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+
+/// When `pid` was started, in a form only meant to be compared with another call. Tells a
+/// process apart from a later one that got the same pid, `None` if there's no such process.
+/// On Linux it's the boot and the start time in clock ticks since it, from `/proc`.
+#[cfg(target_os = "linux")]
+pub fn process_start(pid: u32) -> Option<String> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // The name in parentheses can have spaces and parentheses of its own, the start time
+    // is the 20th field after it.
+    let start = stat[stat.rfind(')')? + 1..].split_whitespace().nth(19)?.to_owned();
+    let boot = std::fs::read_to_string("/proc/sys/kernel/random/boot_id").unwrap_or_default();
+    Some(format!("{}:{}", boot.trim(), start))
+}
+
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn process_start(pid: u32) -> Option<String> {
+    let output = Command::new("ps").args(["-o", "lstart=", "-p"]).arg(pid.to_string()).output().ok()?;
+    let start = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    (output.status.success() && !start.is_empty()).then_some(start)
+}
+
+
+#[cfg(windows)]
+pub fn kill_tree(pid: u32, force: bool) -> io::Result<()> {
+    let mut command = Command::new("taskkill");
+    command.arg("/T").arg("/PID").arg(pid.to_string());
+    if force {
+        command.arg("/F");
+    }
+    let status = command.stdout(Stdio::null()).stderr(Stdio::null()).status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("taskkill couldn't stop process {pid}")));
+    }
+    Ok(())
+}
+
+
+#[cfg(windows)]
+pub fn is_alive(pid: u32) -> bool {
+    Command::new("tasklist")
+        .args(["/NH", "/FO", "CSV", "/FI"])
+        .arg(format!("PID eq {pid}"))
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains(&format!("\"{pid}\"")))
+}
+
+
+#[cfg(windows)]
+pub fn process_start(pid: u32) -> Option<String> {
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-Command"])
+        .arg(format!("(Get-Process -Id {pid}).StartTime.Ticks"))
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let start = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    (output.status.success() && !start.is_empty()).then_some(start)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_runners_with_quoted_paths() {
+        let runner = "gamemoderun \"/home/me/.steam/steamapps/common/Proton 9.0/proton\" run";
+        assert_eq!(split_runner(runner).unwrap(), ["gamemoderun", "/home/me/.steam/steamapps/common/Proton 9.0/proton", "run"]);
+        assert_eq!(split_runner("  wine  ").unwrap(), ["wine"]);
+        assert_eq!(split_runner(r"'C:\Games\My Tools\run.bat' ''").unwrap(), [r"C:\Games\My Tools\run.bat", ""]);
+        assert_eq!(split_runner("--opt=\"a b\"c").unwrap(), ["--opt=a bc"]);
+        assert!(split_runner("\"/opt/Proton 9.0/proton").is_err());
+    }
+
+    #[test]
+    fn runs_through_runners_with_spaces() {
+        let mut config: Config = toml::from_str(crate::DEFAULT_CONFIG).unwrap();
+        let entry = crate::Alias {
+            runner: Some(String::from("'/opt/Proton 9.0/proton' run")),
+            ..crate::Alias::new(String::from("/games/hk/hollow_knight.exe"))
+        };
+        config.aliases.insert(String::from("hk"), entry);
+        let command = game_command(&config, "hk", Path::new("/games/hk/hollow_knight.exe")).unwrap();
+        assert_eq!(command.get_program(), "/opt/Proton 9.0/proton");
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, ["run", "/games/hk/hollow_knight.exe"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn kills_what_the_game_started() {
+        use std::io::{BufRead, BufReader};
+
+        // The second one leaves the sleep to init, like Wine does with its processes.
+        for script in ["sleep 100 & echo $!; wait", "sleep 100 & echo $!"] {
+            let mut command = Command::new("sh");
+            command.args(["-c", script]).stdout(Stdio::piped());
+            new_process_group(&mut command);
+            let mut child = command.spawn().unwrap();
+            let mut line = String::new();
+            BufReader::new(child.stdout.take().unwrap()).read_line(&mut line).unwrap();
+            let grandchild: u32 = line.trim().parse().unwrap();
+
+            kill_tree(child.id(), false).unwrap();
+            child.wait().unwrap();
+            // Nobody might reap the orphaned sleep, a zombie is gone as far as this goes.
+            let gone = || {
+                let output = Command::new("ps").args(["-o", "stat=", "-p"]).arg(grandchild.to_string()).output().unwrap();
+                let stat = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+                stat.is_empty() || stat.starts_with('Z')
+            };
+            let asked = Instant::now();
+            while !gone() && asked.elapsed().as_secs() < 5 {
+                thread::sleep(std::time::Duration::from_millis(50));
+            }
+            assert!(gone(), "`{script}` left its sleep running");
+        }
+    }
+}
@@ -0,0 +1,42 @@
+//! The alias and launch model behind the `plz` command line tool.
+//!
+//! Everything here returns values and errors instead of printing, so it can be embedded
+//! in other tools. The config and data are read from next to the running executable,
+//! call [`set_root_dir`] first to use the ones of a plz install somewhere else.
+//!
+//! ```no_run
+//! let config = plz::try_read_config().unwrap().unwrap();
+//! let game = plz::launch::start(&config, "terraria", None, false).unwrap();
+//! let session = game.wait().unwrap();
+//! println!("Played for {}s", session.duration);
+//! ```
+
+pub mod aliases;
+pub mod bundle;
+pub mod cache;
+pub mod compat;
+pub mod config;
+pub mod doctor;
+pub mod fetch;
+pub mod groups;
+pub mod history;
+pub mod http;
+pub mod hooks;
+pub mod info;
+pub mod instances;
+pub mod launch;
+pub mod logs;
+pub mod mods;
+pub mod repair;
+pub mod saves;
+pub mod scan;
+pub mod selfupdate;
+pub mod sync;
+#[cfg(test)]
+mod test_dir;
+#[cfg(test)]
+mod test_server;
+pub mod updates;
+
+pub use config::{data_dir, exe_dir, save_config, set_root_dir, try_read_config, Alias, Config, DEFAULT_CONFIG};
+pub(crate) use config::{deserialize_aliases, serialize_aliases};
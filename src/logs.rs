@@ -0,0 +1,141 @@
+use serde::{Serialize, Deserialize};
+use std::path::{Path, PathBuf};
+use std::fs::File;
+use std::fs;
+use std::io;
+
+use crate::data_dir;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LogSettings {
+    /// How many logs to keep per alias.
+    pub max_files: usize,
+    /// How many megabytes of logs to keep per alias.
+    pub max_size_mb: u64
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        LogSettings { max_files: 10, max_size_mb: 50 }
+    }
+}
+
+
+pub fn alias_log_dir(alias: &str) -> PathBuf {
+    data_dir().join("logs").join(alias)
+}
+
+
+/// Creates `logs/<alias>/<timestamp>.log` and prunes old logs of that alias.
+pub fn new_log_file(alias: &str, settings: &LogSettings) -> io::Result<(PathBuf, File)> {
+    let dir = alias_log_dir(alias);
+    fs::create_dir_all(&dir)?;
+    let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S");
+    let mut path = dir.join(format!("{timestamp}.log"));
+    let mut n = 1;
+    while path.exists() {
+        path = dir.join(format!("{timestamp}_{n}.log"));
+        n += 1;
+    }
+    let file = File::create(&path)?;
+    rotate(&dir, &path, settings)?;
+    Ok((path, file))
+}
+
+
+/// Removes the oldest logs until both `max_files` and `max_size_mb` are satisfied.
+/// The log that is currently being written to is never removed.
+fn rotate(dir: &Path, current: &Path, settings: &LogSettings) -> io::Result<()> {
+    let mut logs = list_logs(dir)?;
+    let max_size = settings.max_size_mb * 1024 * 1024;
+    let mut total: u64 = logs.iter().map(|(_, size)| size).sum();
+
+    while !logs.is_empty() && (logs.len() > settings.max_files.max(1) || total > max_size) {
+        let (oldest, size) = logs.remove(0);
+        if oldest == current {
+            continue;
+        }
+        fs::remove_file(&oldest)?;
+        total -= size;
+    }
+    Ok(())
+}
+
+
+/// What logs are ordered by: the timestamp, then the number logs started within the same
+/// second get, compared as a number so `_10` comes after `_9`.
+fn log_order(path: &Path) -> (String, u64) {
+    let name = path.file_stem().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    match name.rsplit_once('_').map(|(timestamp, n)| (timestamp, n.parse())) {
+        Some((timestamp, Ok(n))) => (timestamp.to_owned(), n),
+        _ => (name, 0)
+    }
+}
+
+
+/// Logs in `dir` from the oldest to the newest, with their sizes.
+fn list_logs(dir: &Path) -> io::Result<Vec<(PathBuf, u64)>> {
+    let mut logs = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "log") {
+            logs.push((path, entry.metadata()?.len()));
+        }
+    }
+    logs.sort_by_cached_key(|(path, _)| log_order(path));
+    Ok(logs)
+}
+
+
+/// Logs of `alias` from the oldest to the newest, with their sizes.
+pub fn list_alias_logs(alias: &str) -> io::Result<Vec<(PathBuf, u64)>> {
+    match list_logs(&alias_log_dir(alias)) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        logs => logs
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    fn names(dir: &Path) -> Vec<String> {
+        list_logs(dir).unwrap().into_iter()
+            .map(|(path, _)| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn keeps_the_newest_logs() {
+        let dir = TestDir::new();
+        let logs = ["2026-01-01_09-59-59".to_owned(), "2026-01-01_10-00-00".to_owned()].into_iter()
+            .chain((1..=10).map(|n| format!("2026-01-01_10-00-00_{n}")));
+        for name in logs {
+            fs::write(dir.path().join(format!("{name}.log")), "line\n").unwrap();
+        }
+        fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+        let current = dir.path().join("2026-01-01_10-00-00_10.log");
+        rotate(dir.path(), &current, &LogSettings { max_files: 3, max_size_mb: 50 }).unwrap();
+        assert_eq!(names(dir.path()), ["2026-01-01_10-00-00_8.log", "2026-01-01_10-00-00_9.log", "2026-01-01_10-00-00_10.log"]);
+        assert!(dir.path().join("notes.txt").exists());
+    }
+
+    #[test]
+    fn keeps_the_current_log_past_the_size_limit() {
+        let dir = TestDir::new();
+        fs::write(dir.path().join("2026-01-01_10-00-00.log"), vec![b'x'; 1024 * 1024]).unwrap();
+        fs::write(dir.path().join("2026-01-01_11-00-00.log"), vec![b'x'; 1024 * 1024]).unwrap();
+        let current = dir.path().join("2026-01-01_11-00-00.log");
+        rotate(dir.path(), &current, &LogSettings { max_files: 10, max_size_mb: 1 }).unwrap();
+        assert_eq!(names(dir.path()), ["2026-01-01_11-00-00.log"]);
+
+        // Even if it's the only one, and it's too big.
+        fs::write(&current, vec![b'x'; 2 * 1024 * 1024]).unwrap();
+        rotate(dir.path(), &current, &LogSettings { max_files: 10, max_size_mb: 1 }).unwrap();
+        assert_eq!(names(dir.path()), ["2026-01-01_11-00-00.log"]);
+    }
+}
@@ -1,84 +1,21 @@
-use std::time::{UNIX_EPOCH, SystemTime, Duration};
-use std::path::{MAIN_SEPARATOR, Path};
-use serde::{Serialize, Deserialize};
-use scraper::{Html, Selector};
-use std::collections::HashMap;
-use clap::{Arg, Command};
-use std::time::Instant;
+use std::time::{Duration, UNIX_EPOCH, SystemTime};
+use std::path::{Path, PathBuf};
+use clap::{Arg, ArgAction, ArgGroup, Command};
 use std::process::exit;
 use anstyle::AnsiColor;
-use reqwest::Client;
 use anstyle::Style;
 use std::io::Write;
 use std::fs;
 use std::io;
 
-#[derive(Serialize, Deserialize)]
-struct Config {
-    games_dir: String,
-    check_for_updates: bool,
-    default_fetch_provider: String,
-    autoadd_ignore: Vec<String>,
-    aliases: HashMap<String, String>
-}
-
-#[derive(Deserialize)]
-struct Release {
-    tag_name: String
-}
-
-
-fn read_config(default_content: &str) -> Config {
-    let red = AnsiColor::BrightRed.on_default().bold();
-    let error = format!("{red}error:{red:#} ");
-    let exe = match std::env::current_exe() {
-        Ok(exe) => exe,
-        Err(err) => {
-            eprintln!("{error}Failed to get executable file. {err}");
-            exit(1);
-        }
-    };
-    let path = exe.parent().unwrap();
-    let contents = match fs::read_to_string(path.join("config.toml")) {
-        Ok(contents) => contents,
-        Err(_) => {
-            eprintln!("{error}Could find the config file, creating new one");
-            let data: Config = toml::from_str(default_content).unwrap();
-            save_config(&data);
-            default_content.to_owned()
-        }
-    };
-    match toml::from_str(&contents) {
-        Ok(x) => x,
-        Err(err) => {
-            eprintln!("{error}Unable to load the config file. {}", err);
-            exit(1);
-        }
-    }
-}
-
-
-fn save_config(data: &Config) {
-    let red = AnsiColor::BrightRed.on_default().bold();
-    let error = format!("{red}error:{red:#} ");
-    let contents = toml::to_string(data).unwrap();
-    let exe = match std::env::current_exe() {
-        Ok(exe) => exe,
-        Err(err) => {
-            eprintln!("{error}Failed to get executable file. {err}");
-            exit(1);
-        }
-    };
-    let path = exe.parent().unwrap();
-    match fs::write(path.join("config.toml"), contents) {
-        Ok(_) => {},
-        Err(err) => eprintln!("{error}Failed to save the config file. {}", err)
-    }
-}
+use plz::{aliases, bundle, cache, doctor, fetch, groups, history, instances, mods, repair, saves, sync, updates, Alias, Config, DEFAULT_CONFIG};
+use cli::{read_config, save_config, sort_by_key_length, user_input};
 
+mod cli;
 
 fn get_matches() -> Result<clap::ArgMatches, clap::Error> {
-    Command::new("plz")
+    let provider_names: Vec<&str> = fetch::providers().iter().map(|provider| provider.name()).collect();
+    let mut command = Command::new("plz")
         .about("plz is an alias manager to help you manage your games.")
         .version(env!("CARGO_PKG_VERSION"))
         .subcommand_required(true)
@@ -91,10 +28,130 @@ fn get_matches() -> Result<clap::ArgMatches, clap::Error> {
                         .help("The alias to run")
                         .required(true)
                 )
+                .arg(
+                    Arg::new("detach")
+                        .help("Return immediately and let the game run in the background")
+                        .long("detach")
+                        .short('d')
+                        .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("log")
+                        .help("Save the game's output into a log file")
+                        .long("log")
+                        .short('l')
+                        .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("profile")
+                        .help("Deploy this mod profile before launching (same as `alias:profile`)")
+                        .long("profile")
+                        .short('p')
+                )
+                .arg(
+                    Arg::new("revert")
+                        .help("Switch back to the previously enabled mods once the game exits")
+                        .long("revert")
+                        .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("allow_multiple")
+                        .help("Start the game even if it's already running")
+                        .long("allow-multiple")
+                        .action(ArgAction::SetTrue)
+                )
         )
         .subcommand(
             Command::new("random")
                 .about("Run a random alias")
+                .arg(
+                    Arg::new("detach")
+                        .help("Return immediately and let the game run in the background")
+                        .long("detach")
+                        .short('d')
+                        .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("log")
+                        .help("Save the game's output into a log file")
+                        .long("log")
+                        .short('l')
+                        .action(ArgAction::SetTrue)
+                )
+        )
+        .subcommand(
+            Command::new("supervise")
+                .about("Run an alias and record the session once it exits (used by `run --detach`)")
+                .hide(true)
+                .arg(Arg::new("alias").required(true))
+                .arg(
+                    Arg::new("revert_mods")
+                        .long("revert-mods")
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("allow_multiple")
+                        .long("allow-multiple")
+                        .action(ArgAction::SetTrue)
+                )
+        )
+        .subcommand(
+            Command::new("logs")
+                .about("View the logs saved for an alias")
+                .arg(
+                    Arg::new("alias")
+                        .help("The alias to view the logs of")
+                        .required(true)
+                )
+                .arg(
+                    Arg::new("last")
+                        .help("Print the newest log (default)")
+                        .long("last")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with_all(["list", "follow"])
+                )
+                .arg(
+                    Arg::new("list")
+                        .help("List all saved logs")
+                        .long("list")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("follow")
+                )
+                .arg(
+                    Arg::new("follow")
+                        .help("Print the newest log and keep printing what gets added to it")
+                        .long("follow")
+                        .short('f')
+                        .action(ArgAction::SetTrue)
+                )
+        )
+        .subcommand(
+            Command::new("ps")
+                .about("List the games started by plz that are still running")
+        )
+        .subcommand(
+            Command::new("kill")
+                .about("Stop a running alias together with every process it started")
+                .arg(
+                    Arg::new("alias")
+                        .help("The alias to stop")
+                        .required(true)
+                )
+                .arg(
+                    Arg::new("force")
+                        .help("Kill it right away instead of asking it to exit first")
+                        .long("force")
+                        .short('f')
+                        .action(ArgAction::SetTrue)
+                )
+        )
+        .subcommand(
+            Command::new("playtime")
+                .about("Show how long you've played your aliases")
+                .arg(
+                    Arg::new("alias")
+                        .help("Only show playtime of this alias")
+                )
         )
         .subcommand(
             Command::new("config")
@@ -120,7 +177,27 @@ fn get_matches() -> Result<clap::ArgMatches, clap::Error> {
                         .about("Change or view default_fetch_provider in your config")
                         .arg(
                             Arg::new("value")
-                                .help("Value to change it to (SteamRIP/Game3rb/GOG Games)")
+                                .help(format!("Value to change it to ({})", provider_names.join("/")))
+                        )
+                )
+                .subcommand(
+                    Command::new("library")
+                        .about("Add, change, remove or view the game libraries in your config")
+                        .arg(
+                            Arg::new("name")
+                                .help("Name of the library, used as `${library:name}` in alias paths")
+                        )
+                        .arg(
+                            Arg::new("path")
+                                .help("Folder to point the library to")
+                        )
+                        .arg(
+                            Arg::new("remove")
+                                .help("Remove the library")
+                                .long("remove")
+                                .action(ArgAction::SetTrue)
+                                .requires("name")
+                                .conflicts_with("path")
                         )
                 )
         )
@@ -151,6 +228,52 @@ fn get_matches() -> Result<clap::ArgMatches, clap::Error> {
                                 .required(true)
                         )
                 )
+                .subcommand(
+                    Command::new("rename")
+                        .about("Rename an alias, keeping its history, logs, save backups and mods")
+                        .arg(
+                            Arg::new("old")
+                                .help("The alias to rename")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("new")
+                                .help("The new name")
+                                .required(true)
+                        )
+                )
+                .subcommand(
+                    Command::new("copy")
+                        .about("Copy an alias, e.g. to make a variant with different args")
+                        .arg(
+                            Arg::new("source")
+                                .help("The alias to copy")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("destination")
+                                .help("Name of the copy")
+                                .required(true)
+                        )
+                )
+                .subcommand(
+                    Command::new("show")
+                        .about("Show every setting of an alias")
+                        .arg(
+                            Arg::new("alias")
+                                .help("The alias to show")
+                                .required(true)
+                        )
+                )
+                .subcommand(
+                    Command::new("edit")
+                        .about("Edit an alias as TOML in $EDITOR")
+                        .arg(
+                            Arg::new("alias")
+                                .help("The alias to edit")
+                                .required(true)
+                        )
+                )
                 .subcommand(
                     Command::new("list")
                         .about("List all aliases")
@@ -159,511 +282,635 @@ fn get_matches() -> Result<clap::ArgMatches, clap::Error> {
                     Command::new("autoadd")
                         .about("Automatically add aliases from games_dir")
                 )
+                .subcommand(
+                    Command::new("repair")
+                        .about("Find executables of broken aliases that got moved or renamed")
+                        .arg(
+                            Arg::new("alias")
+                                .help("Only repair this alias")
+                        )
+                        .arg(
+                            Arg::new("root")
+                                .help("Also search this folder (games_dir and libraries are always searched)")
+                                .long("root")
+                                .action(ArgAction::Append)
+                        )
+                        .arg(
+                            Arg::new("yes")
+                                .help("Pick the best candidate without asking when its size or contents match")
+                                .long("yes")
+                                .short('y')
+                                .action(ArgAction::SetTrue)
+                        )
+                )
+                .subcommand(
+                    Command::new("relocate")
+                        .about("Move every alias under one folder to another, e.g. `relocate D:/Games /mnt/games`")
+                        .arg(
+                            Arg::new("from")
+                                .help("The old folder")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("to")
+                                .help("The new folder")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("dry_run")
+                                .help("Only show what would change")
+                                .long("dry-run")
+                                .action(ArgAction::SetTrue)
+                        )
+                )
+                .subcommand(
+                    Command::new("export")
+                        .about("Print aliases as a bundle to share, e.g. `alias export > bundle.toml`")
+                        .arg(
+                            Arg::new("tag")
+                                .help("Only export aliases with this tag")
+                                .long("tag")
+                        )
+                )
+                .subcommand(
+                    Command::new("import")
+                        .about("Add the aliases from a bundle")
+                        .arg(
+                            Arg::new("file")
+                                .help("The bundle to import")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("skip")
+                                .help("Keep your alias when the bundle has one with the same name (default)")
+                                .long("skip")
+                                .action(ArgAction::SetTrue)
+                        )
+                        .arg(
+                            Arg::new("overwrite")
+                                .help("Replace your alias when the bundle has one with the same name")
+                                .long("overwrite")
+                                .action(ArgAction::SetTrue)
+                        )
+                        .arg(
+                            Arg::new("rename")
+                                .help("Import the bundle's alias under a new name when the name is taken")
+                                .long("rename")
+                                .action(ArgAction::SetTrue)
+                        )
+                        .group(ArgGroup::new("on_conflict").args(["skip", "overwrite", "rename"]))
+                )
         )
         .subcommand(
-            Command::new("fetch")
-                .about("Fetch links from your default fetch provider")
+            Command::new("saves")
+                .about("Back up and restore save games")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("add")
+                        .about("Add a save location to an alias")
+                        .arg(
+                            Arg::new("alias")
+                                .help("The alias to add the save location to")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("location")
+                                .help("Glob path of the saves, can use ${HOME}, ${WINEPREFIX} and ${game_dir}")
+                                .required(true)
+                        )
+                )
+                .subcommand(
+                    Command::new("backup")
+                        .about("Back up the saves of an alias")
+                        .arg(
+                            Arg::new("alias")
+                                .help("The alias to back up")
+                                .required(true)
+                        )
+                )
+                .subcommand(
+                    Command::new("restore")
+                        .about("Restore the saves of an alias from a backup")
+                        .arg(
+                            Arg::new("alias")
+                                .help("The alias to restore")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("at")
+                                .help("Timestamp of the backup to restore (defaults to the newest)")
+                                .long("at")
+                        )
+                        .arg(
+                            Arg::new("yes")
+                                .help("Don't ask before overwriting the current saves")
+                                .long("yes")
+                                .short('y')
+                                .action(ArgAction::SetTrue)
+                        )
+                )
+                .subcommand(
+                    Command::new("sync")
+                        .about("Compare the local saves with the ones in sync_dir")
+                        .arg(
+                            Arg::new("alias")
+                                .help("The alias to compare")
+                                .required(true)
+                        )
+                )
+                .subcommand(
+                    Command::new("pull")
+                        .about("Restore the newest saves from sync_dir")
+                        .arg(
+                            Arg::new("alias")
+                                .help("The alias to restore")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("yes")
+                                .help("Don't ask before overwriting the current saves")
+                                .long("yes")
+                                .short('y')
+                                .action(ArgAction::SetTrue)
+                        )
+                )
+                .subcommand(
+                    Command::new("list")
+                        .about("List save backups")
+                        .arg(
+                            Arg::new("alias")
+                                .help("Only list backups of this alias")
+                        )
+                )
+        )
+        .subcommand(
+            Command::new("mods")
+                .about("Manage the mods of an alias")
+                .subcommand_required(true)
                 .arg(
-                    Arg::new("game")
-                        .help("The game to fetch links for")
+                    Arg::new("alias")
+                        .help("The alias to manage the mods of")
                         .required(true)
                 )
+                .subcommand(
+                    Command::new("add")
+                        .about("Add a mod from a folder or a .zip/.tar/.tar.gz archive")
+                        .arg(
+                            Arg::new("source")
+                                .help("The folder or archive to add")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("name")
+                                .help("Name of the mod (defaults to the file name)")
+                                .long("name")
+                        )
+                        .arg(
+                            Arg::new("target")
+                                .help("Folder inside the game folder to deploy the mod into, e.g. BepInEx/plugins")
+                                .long("target")
+                                .default_value("")
+                        )
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .about("Remove a disabled mod")
+                        .arg(
+                            Arg::new("mod")
+                                .help("The mod to remove")
+                                .required(true)
+                        )
+                )
+                .subcommand(
+                    Command::new("enable")
+                        .about("Deploy a mod into the game folder")
+                        .arg(
+                            Arg::new("mod")
+                                .help("The mod to enable")
+                                .required(true)
+                        )
+                )
+                .subcommand(
+                    Command::new("disable")
+                        .about("Remove a mod from the game folder and restore the files it replaced")
+                        .arg(
+                            Arg::new("mod")
+                                .help("The mod to disable")
+                                .required(true)
+                        )
+                )
+                .subcommand(
+                    Command::new("list")
+                        .about("List the mods of an alias and their conflicts")
+                )
+                .subcommand(
+                    Command::new("profile")
+                        .about("Manage named sets of mods")
+                        .subcommand_required(true)
+                        .subcommand(
+                            Command::new("save")
+                                .about("Save the currently enabled mods as a profile")
+                                .arg(
+                                    Arg::new("name")
+                                        .help("Name of the profile")
+                                        .required(true)
+                                )
+                        )
+                        .subcommand(
+                            Command::new("apply")
+                                .about("Enable exactly the mods of a profile")
+                                .arg(
+                                    Arg::new("name")
+                                        .help("Name of the profile")
+                                        .required(true)
+                                )
+                        )
+                        .subcommand(
+                            Command::new("remove")
+                                .about("Remove a profile")
+                                .arg(
+                                    Arg::new("name")
+                                        .help("Name of the profile")
+                                        .required(true)
+                                )
+                        )
+                        .subcommand(
+                            Command::new("list")
+                                .about("List profiles")
+                        )
+                        .subcommand(
+                            Command::new("export")
+                                .about("Print a profile as a manifest others can import")
+                                .arg(
+                                    Arg::new("name")
+                                        .help("Name of the profile")
+                                        .required(true)
+                                )
+                        )
+                        .subcommand(
+                            Command::new("import")
+                                .about("Import a profile manifest")
+                                .arg(
+                                    Arg::new("file")
+                                        .help("The manifest to import")
+                                        .required(true)
+                                )
+                                .arg(
+                                    Arg::new("name")
+                                        .help("Name to save the profile as (defaults to the one in the manifest)")
+                                        .long("name")
+                                )
+                        )
+                )
         )
         .subcommand(
-            Command::new("fetchrip")
-                .about("Fetch links from SteamRIP")
+            Command::new("doctor")
+                .about("Check your config and setup for problems")
                 .arg(
-                    Arg::new("game")
-                        .help("The game to fetch links for")
-                        .required(true)
+                    Arg::new("fix")
+                        .help("Apply the fixes that are safe to apply")
+                        .long("fix")
+                        .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("json")
+                        .help("Print the results as JSON")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                )
+        )
+        .subcommand(
+            Command::new("self-update")
+                .about("Update plz to the newest release")
+                .arg(
+                    Arg::new("check")
+                        .help("Only show whether there's a newer release")
+                        .long("check")
+                        .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("rollback")
+                        .help("Go back to the version before the last update")
+                        .long("rollback")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("check")
                 )
         )
         .subcommand(
-            Command::new("fetchrb")
-                .about("Fetch links from Game3rb")
+            Command::new("fetch")
+                .about("Fetch links from your default fetch provider")
                 .arg(
                     Arg::new("game")
                         .help("The game to fetch links for")
+                        .required_unless_present("search")
+                )
+                .arg(
+                    Arg::new("search")
+                        .help("List the games the providers find for a query instead, then pick one to fetch")
+                        .long("search")
+                        .value_name("QUERY")
+                        .conflicts_with_all(["game", "all"])
+                )
+                .arg(
+                    Arg::new("pick")
+                        .help("Fetch this search result instead of asking")
+                        .long("pick")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .requires("search")
+                        .conflicts_with("game")
+                )
+                .arg(
+                    Arg::new("all")
+                        .help("Ask every provider at the same time and show all their links")
+                        .long("all")
+                        .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("deadline")
+                        .help("Give up on the providers that haven't answered after this many seconds")
+                        .long("deadline")
+                        .value_name("SECONDS")
+                        .value_parser(clap::value_parser!(u64))
+                )
+                .arg(
+                    Arg::new("refresh")
+                        .help("Ask the providers again instead of using cached links")
+                        .long("refresh")
+                        .action(ArgAction::SetTrue)
+                )
+        )
+        .subcommand(
+            Command::new("info")
+                .about("Show what PCGamingWiki knows about the game of an alias")
+                .arg(
+                    Arg::new("alias")
+                        .help("The alias to look up")
                         .required(true)
                 )
+                .arg(
+                    Arg::new("title")
+                        .help("The game's name on PCGamingWiki, if it isn't the name of its folder")
+                        .long("title")
+                )
+                .arg(
+                    Arg::new("refresh")
+                        .help("Ask PCGamingWiki again instead of using the cached answer")
+                        .long("refresh")
+                        .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("add_saves")
+                        .help("Add the save game locations to the alias, so `saves backup` picks them up")
+                        .long("add-saves")
+                        .action(ArgAction::SetTrue)
+                )
         )
         .subcommand(
-            Command::new("fetchgog")
-                .about("Fetch links from GOG Games")
+            Command::new("compat")
+                .about("Show how the game of an alias runs through Proton according to ProtonDB")
                 .arg(
-                    Arg::new("game")
-                        .help("The game to fetch links for")
+                    Arg::new("alias")
+                        .help("The alias to look up")
                         .required(true)
                 )
+                .arg(
+                    Arg::new("appid")
+                        .help("The game's Steam app ID, kept on the alias (found on PCGamingWiki otherwise)")
+                        .long("appid")
+                        .value_parser(clap::value_parser!(u32))
+                )
+                .arg(
+                    Arg::new("title")
+                        .help("The game's name on PCGamingWiki, to find its Steam app ID")
+                        .long("title")
+                        .conflicts_with("appid")
+                )
+                .arg(
+                    Arg::new("refresh")
+                        .help("Ask ProtonDB again instead of using the cached answer")
+                        .long("refresh")
+                        .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("apply")
+                        .help("Set the suggested runner and environment variables on the alias")
+                        .long("apply")
+                        .action(ArgAction::SetTrue)
+                )
         )
-    .try_get_matches()
-}
-
-
-async fn fetch_game3rb(name: &str) -> bool {
-    let v = AnsiColor::BrightYellow.on_default();
-    let green = AnsiColor::BrightGreen.on_default().bold();
-    let red = AnsiColor::BrightRed.on_default().bold();
-    let error = format!("{red}error:{red:#} ");
-    let success = format!("{green}success:{green:#} ");
-    let bold = Style::new().bold();
-    let perf = Instant::now();
-    let client = reqwest::Client::builder().user_agent("plz").timeout(Duration::from_secs(5)).build().unwrap();
-
-    let url = format!("https://game3rb.com/{}", name);
-    let res = match client.get(&url).send().await {
-        Ok(res) => res,
-        Err(err) => {
-            eprintln!("{error}Error sending request: {err}");
-            return false;
-        },
-    };
-
-    if res.status().as_u16() == 404 {
-        eprintln!("{error}Failed to fetch Game3rb");
-        return false;
-    }
-
-    let soup = Html::parse_document(&res.text().await.unwrap());
-    let title = soup
-        .select(&Selector::parse("h1.post-title").unwrap())
-        .next().unwrap()
-        .text().collect::<String>()
-        .replace("Download ", "")
-        .replace(" + OnLine", " + Online")
-        .trim().to_owned();
-
-    println!("{bold}{title}\nGame3rb Download links:{bold:#}");
-    let item = soup.select(&Selector::parse("a#download-link.direct").unwrap()).next().unwrap();
-    let href = item.value().attr("href").unwrap();
-    let res = match client.get(href).send().await {
-        Ok(res) => res,
-        Err(_) => {
-            eprintln!("{error}Failed to get to the links website");
-            return false;
-        },
-    };
-
-    let selector = &Selector::parse("ol li a").unwrap();
-    let soup = Html::parse_document(&res.text().await.unwrap());
-    for link in soup.select(selector) {
-        let host = link.attr("href").unwrap();
-        let mut idx = host.find("://").unwrap() + 3;
-        if host[idx..].starts_with("www.") {
-            idx += 4;
-        }
-        let dot = host[idx..].find('.').unwrap() + idx;
-        let name = titlecase(&host[idx..dot]);
-        println!(" {bold}{name}:{bold:#} {host}");
-    }
-
-    println!("{success}Fetched Game3rb for `{v}{}{v:#}` in {v}{:.2}{v:#}s\n", name, perf.elapsed().as_secs_f64());
-    true
-}
-
-
-async fn fetch_steamrip(name: &str) -> bool {
-    let v = AnsiColor::BrightYellow.on_default();
-    let green = AnsiColor::BrightGreen.on_default().bold();
-    let red = AnsiColor::BrightRed.on_default().bold();
-    let error = format!("{red}error:{red:#} ");
-    let success = format!("{green}success:{green:#} ");
-    let bold = Style::new().bold();
-    let perf = Instant::now();
-    let client = reqwest::Client::builder().user_agent("plz").timeout(Duration::from_secs(5)).build().unwrap();
-
-    let url = format!("https://steamrip.com/{}", name);
-    let res = match client.get(&url).send().await {
-        Ok(res) => res,
-        Err(err) => {
-            eprintln!("{error}Error sending request: {err}");
-            return false;
-        },
-    };
-
-    if res.status().as_u16() == 404 {
-        eprintln!("{error}Failed to fetch SteamRIP");
-        return false;
-    }
-
-    let soup = Html::parse_document(&res.text().await.unwrap());
-    let title = soup
-        .select(&Selector::parse("h1.post-title").unwrap())
-        .next().unwrap()
-        .text().collect::<String>()
-        .replace(" Free Download", "")
-        .trim().to_owned();
-
-    println!("{bold}{title}{bold:#}");
-    let selector = &Selector::parse("a.shortc-button").unwrap();
-    for item in soup.select(selector) {
-        let href = item.value().attr("href").unwrap();
-        let dot = href.find('.').unwrap();
-        let name = titlecase(&href[2..dot]);
-        println!(" {bold}{name}:{bold:#} https:{href}");
-    }
-
-    println!("{success}Fetched SteamRIP for `{v}{}{v:#}` in {v}{:.2}{v:#}s\n", name, perf.elapsed().as_secs_f64());
-    true
-}
-
-
-async fn fetch_gog_games(name: &str) -> bool {
-    let v = AnsiColor::BrightYellow.on_default();
-    let green = AnsiColor::BrightGreen.on_default().bold();
-    let red = AnsiColor::BrightRed.on_default().bold();
-    let error = format!("{red}error:{red:#} ");
-    let success = format!("{green}success:{green:#} ");
-    let bold = Style::new().bold();
-    let perf = Instant::now();
-    let client = reqwest::Client::builder().user_agent("plz").timeout(Duration::from_secs(5)).build().unwrap();
-
-    let url = format!("https://gog-games.to/game/{}", name.replace('-', "_"));
-    let res = match client.get(&url).send().await {
-        Ok(res) => res,
-        Err(err) => {
-            eprintln!("{error}Error sending request: {err}");
-            return false;
-        },
-    };
-
-    if res.status().as_u16() == 404 {
-        eprintln!("{error}Failed to fetch GOG Games");
-        return false;
-    }
-
-    let soup = Html::parse_document(&res.text().await.unwrap());
-    let title = soup.select(&Selector::parse("div.index h1").unwrap())
-        .next().unwrap().text().collect::<String>();
-    
-    println!("{bold}{title}{bold:#}");
-    let selector = &Selector::parse("div.items-links-block div").unwrap();
-    for group in soup.select(selector) {
-        let selector = &Selector::parse("div.title").unwrap();
-        let mut title = group.select(selector);
-        let title = title.next().unwrap().text();
-        println!("{bold}{}:{bold:#}", title.collect::<String>());
-
-        let selector = &Selector::parse("div.item-expand.wrap").unwrap();
-        let links = group.select(selector);
-        for link in links {
-            let name = link.select(&Selector::parse("label").unwrap())
-                .next().unwrap().attr("title").unwrap();
-            let href = link.select(&Selector::parse("div.items-group a").unwrap())
-                .next().unwrap().attr("href").unwrap();
-            println!(" {bold}{name}:{bold:#} {href}");
-        }
-    }
-
-    println!("{success}Fetched GOG Games for `{v}{}{v:#}` in {v}{:.2}{v:#}s\n", name, perf.elapsed().as_secs_f64());
-    true
-}
-
-
-fn titlecase(string: &str) -> String {
-    let mut chars = string.chars();
-    let first = chars.next().unwrap().to_uppercase().collect::<String>();
-    first + chars.as_str()
-}
-
-
-fn recursive_search(path: &str, folder_path: &str, config: &mut Config) -> io::Result<()> {
-    const EXECUTABLE_BLACKLIST: [&str; 3] = ["unins000.exe", "UnityCrashHandler64.exe", "UnityCrashHandler32.exe"];
-    let v = AnsiColor::BrightYellow.on_default();
-    let mut executables = Vec::new();
-    let mut folders = Vec::new();
-
-    for entry in fs::read_dir(folder_path)? {
-        let entry = entry?;
-        let file_path = entry.path();
-        let file_name = entry.file_name().into_string().unwrap_or_default();
-
-        if file_path.is_file()
-            && file_name.ends_with(".exe")
-            && !EXECUTABLE_BLACKLIST.contains(&file_name.as_str())
-        {
-            executables.push(file_path);
-        } else if file_path.is_dir() {
-            folders.push(file_path);
-        }
-    }
-
-    if !executables.is_empty() {
-        for executable_file in executables {
-            let file_path = executable_file.display().to_string();
-            let filename = executable_file.file_name().unwrap().to_string_lossy();
-            if !config.autoadd_ignore.contains(&file_path) && !config.aliases.values().any(|val| val == &file_path) {
-                print!("Alias name for `{v}{}{v:#}` (enter to skip): ", filename);
-                io::stdout().flush().unwrap();
-                let mut input = String::new();
-                io::stdin().read_line(&mut input)?;
-                let name = input.trim();
-                
-                if !name.is_empty() {
-                    if config.aliases.contains_key(name) {
-                        if user_input(format!("Overwrite alias `{v}{}{v:#}`? (y/n) ", name)) {
-                            config.aliases.insert(name.to_string(), file_path);
-                        }
-                    } else {
-                        config.aliases.insert(name.to_string(), file_path);
-                    }
-                } else {
-                    config.autoadd_ignore.push(file_path);
-                }
-            }
-        }
-    } else if !folders.is_empty() {
-        println!("No executables found in `{v}{}{v:#}`, going into subfolders", path);
-        for folder in folders {
-            let folder_path = folder.display().to_string();
-            recursive_search(&folder_path, &folder_path, config)?;
-        }
-    }
-
-    Ok(())
-}
-
-
-fn autoadd(config: &mut Config) -> io::Result<()> {
-    let v = AnsiColor::BrightYellow.on_default();
-    if config.games_dir.is_empty() {
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            "games_dir is empty, please set it first.",
-        ));
-    }
-    
-    for entry in fs::read_dir(&config.games_dir)? {
-        let entry = entry?;
-        let file_path = entry.path();
-        let file_name = entry.file_name().into_string().unwrap_or_default();
-
-        if file_path.is_dir() {
-            let folder_path = file_path.display().to_string();
-            recursive_search(&file_name, &folder_path, config)?;
-        } else if file_path.is_file() && file_name.ends_with(".exe") &&
-                  !config.autoadd_ignore.contains(&file_path.display().to_string()) &&
-                  !config.aliases.values().any(|val| val == &file_path.display().to_string()) {
-                
-                print!("Alias name for `{v}{}{v:#}` (enter to skip): ", file_name);
-                io::stdout().flush().unwrap();
-                let mut input = String::new();
-                io::stdin().read_line(&mut input)?;
-                let name = input.trim();
-                
-                if !name.is_empty() {
-                    if config.aliases.contains_key(name) {
-                        if user_input(format!("Overwrite alias `{v}{}{v:#}`? (y/n) ", name)) {
-                            config.aliases.insert(name.to_string(), file_path.display().to_string());
-                        }
-                    } else {
-                        config.aliases.insert(name.to_string(), file_path.display().to_string());
-                    }
-                } else {
-                    config.autoadd_ignore.push(file_path.display().to_string());
-                }
-        }
-    }
-
-    save_config(config);
-    Ok(())
-}
-
-
-async fn fetch(name: &str, provider: &str) {
-    let red = AnsiColor::BrightRed.on_default().bold();
-    let error = format!("{red}error:{red:#} ");
-    let v = AnsiColor::BrightYellow.on_default();
-
-    let name = &name.replace(' ', "-");
-    let primary = match provider {
-        "GOG Games" => fetch_gog_games(name).await,
-        "Game3rb" => fetch_game3rb(name).await,
-        "SteamRIP" => fetch_steamrip(name).await,
-        _ => {
-            eprintln!("{error}Fetch provider is not valid `{v}{provider}{v:#}`");
-            eprintln!("{error}Avaliable: [{v}SteamRIP{v:#}, {v}Game3rb{v:#}, {v}GOG Games{v:#}]");
-            exit(1);
-        }
-    };
-
-    if !primary {
-        let secondary = match provider {
-            "GOG Games" => fetch_steamrip(name).await,
-            "Game3rb" => fetch_steamrip(name).await,
-            "SteamRIP" => fetch_game3rb(name).await,
-            _ => unreachable!()
-        };
-        if !secondary {
-            match provider {
-                "GOG Games" => fetch_game3rb(name).await,
-                "Game3rb" => fetch_gog_games(name).await,
-                "SteamRIP" => fetch_gog_games(name).await,
-                _ => unreachable!()
-            };
-        }
-    }
-}
-
-fn check_config(config: &mut Config) {
-    let bold_yellow = AnsiColor::BrightYellow.on_default().bold();
-    let v = AnsiColor::BrightYellow.on_default();
-    let warning = format!("\n{bold_yellow}warning:{bold_yellow:#} ");
-    let path = Path::new(&config.games_dir);
-    
-    if !path.exists() {
-        eprint!("{warning}games_dir `{v}{}{v:#}` does not exist.", config.games_dir);
-    } else if !path.is_dir() {
-        eprint!("{warning}games_dir `{v}{}{v:#}` is not a directory.", config.games_dir);
-    } else if !config.games_dir.contains(std::path::MAIN_SEPARATOR) {
-        eprint!("{warning}games_dir `{v}{}{v:#}` doesn't use system's main separator ({}).", config.games_dir, MAIN_SEPARATOR);
-    }
-
-    for path in config.aliases.clone() {
-        if !Path::new(&path.1).exists() {
-            eprint!("{warning}Alias `{v}{}{v:#}` points to `{v}{}{v:#}` which does not exist.", path.0, path.1);
-        } else if !Path::new(&path.1).is_file() {
-            eprint!("{warning}Alias `{v}{}{v:#}` points to `{v}{}{v:#}` which is not a file.", path.0, path.1);
-        }
-    }
-
-    let mut idx = 0;
-    for path in config.autoadd_ignore.clone() {
-        if !Path::new(&path).exists() {
-            config.autoadd_ignore.remove(idx);
-        } else {
-            idx += 1;
-        }
+        .subcommand(
+            Command::new("cache")
+                .about("Look at or clear the cached answers of fetch providers, PCGamingWiki and ProtonDB")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("stats")
+                        .about("Show how much is cached")
+                )
+                .subcommand(
+                    Command::new("clear")
+                        .about("Remove everything from the cache")
+                )
+        );
+    for provider in fetch::providers() {
+        command = command.subcommand(
+            Command::new(provider.command())
+                .about(format!("Fetch links from {}", provider.name()))
+                .arg(
+                    Arg::new("game")
+                        .help("The game to fetch links for")
+                        .required_unless_present("search")
+                )
+                .arg(
+                    Arg::new("search")
+                        .help("List the games the provider finds for a query instead, then pick one to fetch")
+                        .long("search")
+                        .value_name("QUERY")
+                        .conflicts_with("game")
+                )
+                .arg(
+                    Arg::new("pick")
+                        .help("Fetch this search result instead of asking")
+                        .long("pick")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .requires("search")
+                        .conflicts_with("game")
+                )
+                .arg(
+                    Arg::new("deadline")
+                        .help("Give up on the providers that haven't answered after this many seconds")
+                        .long("deadline")
+                        .value_name("SECONDS")
+                        .value_parser(clap::value_parser!(u64))
+                )
+                .arg(
+                    Arg::new("refresh")
+                        .help("Ask the provider again instead of using cached links")
+                        .long("refresh")
+                        .action(ArgAction::SetTrue)
+                )
+        );
     }
-    save_config(config);
-}
-
-
-fn sort_by_key_length(mut hash_map: HashMap<String, String>) -> Vec<(String, String)> {
-    let mut vec: Vec<(String, String)> = hash_map.drain().collect();
-    vec.sort_by(|(key1, _), (key2, _)| key2.len().cmp(&key1.len()));
-    vec.into_iter().collect()
+    command.try_get_matches()
 }
 
 
-fn user_input(message: String) -> bool {
-    print!("{}", message);
-    io::stdout().flush().unwrap();
-    let mut buf = String::new();
-    match io::stdin().read_line(&mut buf) {
-        Ok(_) => {},
-        Err(err) => {
-            eprintln!("{}", err);
-            exit(1);
-        }
-    }
-
-    let input = buf.trim().to_lowercase();
-    if input == "y" || input == "yes" {
-        return true;
+#[tokio::main]
+async fn main() {
+    let matches = get_matches();
+    // --help, --version and usage errors need neither the config nor an update check,
+    // self-update runs `--version` on the new binary before installing it.
+    if let Err(err) = &matches {
+        err.exit();
     }
-    false
-}
-
-
-async fn check_for_updates() -> String {
-    let client = Client::builder().user_agent("plz").timeout(Duration::from_secs(5)).build().unwrap();
-    let res = client.get("https://api.github.com/repos/nieboczek/plz/releases/latest").send().await;
-    if let Ok(res) = res {
-        let release: Release = res.json().await.unwrap();
-        if release.tag_name != String::from("v") + env!("CARGO_PKG_VERSION") {
-            let green = AnsiColor::BrightGreen.on_default().bold();
-            let v = AnsiColor::BrightYellow.on_default().bold();
-            return format!("\n{green}New version of plz available:{green:#}\n Current: {v}v{}{v:#}\n New version: {green}{}{green:#}", env!("CARGO_PKG_VERSION"), release.tag_name);
+    // doctor loads the config itself, so it can report a broken one instead of exiting.
+    if let Ok(matches) = &matches {
+        if let Some(("doctor", matches)) = matches.subcommand() {
+            let checks = doctor::run_checks(matches.get_flag("fix"));
+            if matches.get_flag("json") {
+                println!("{}", serde_json::to_string_pretty(&checks).unwrap());
+            } else {
+                cli::doctor::print_checks(&checks);
+            }
+            if checks.iter().any(|check| check.status == doctor::Status::Error) {
+                exit(1);
+            }
+            return;
         }
     }
-    String::new()
-}
-
 
-#[tokio::main]
-async fn main() {
-    let mut config: Config = read_config("games_dir = \"\"\ndefault_fetch_provider = \"SteamRIP\"\ncheck_for_updates = true\nautoadd_ignore = []\n[aliases]");
-    let update_message = match config.check_for_updates {
-        true => Some(check_for_updates()),
-        false => None,
+    let mut config: Config = read_config(DEFAULT_CONFIG);
+    // The supervisor only writes to a log file and self-update looks for a new version itself.
+    let update_check = match matches.as_ref().ok().and_then(|matches| matches.subcommand_name()) {
+        Some("supervise" | "self-update") => None,
+        _ => updates::start(&config, &updates::cache_path())
     };
 
-    match get_matches() {
+    match matches {
         Ok(matches) => {
             let red = AnsiColor::BrightRed.on_default().bold();
             let error = format!("{red}error:{red:#} ");
             let green = AnsiColor::BrightGreen.on_default().bold();
             let success = format!("{green}success:{green:#} ");
             let v = AnsiColor::BrightYellow.on_default();
+            let bold_yellow = AnsiColor::BrightYellow.on_default().bold();
             let bold = Style::new().bold();
             match matches.subcommand() {
                 Some(("run", matches)) => {
-                    let alias: &String = matches.get_one("alias").unwrap();
-                    match config.aliases.get(alias) {
-                        Some(path) => {
-                            let path: &Path = Path::new(path);
-                            let dir = match path.parent() {
-                                Some(path) => path,
-                                None => {
-                                    eprintln!("{error}Path: {}. Failed to get the parent of path", path.display());
-                                    exit(1);
-                                }
-                            };
-                            match std::env::set_current_dir(dir) {
-                                Ok(_) => {}
-                                Err(err) => {
-                                    eprintln!("{error}Path: {}. {}", path.display(), err);
-                                    exit(1);
-                                }
-                            }
-                            println!("{bold}Running:{bold:#} `{v}{}{v:#}`", path.display());
-                            if let Err(err) = std::process::Command::new(path).status() {
-                                eprintln!("{error}Failed to run alias `{v}{}{v:#}`: {}", alias, err);
+                    let mut alias: &str = matches.get_one::<String>("alias").unwrap();
+                    let mut profile = matches.get_one::<String>("profile").cloned();
+                    if !config.aliases.contains_key(alias) && config.groups.contains_key(alias) {
+                        if ["detach", "log", "revert"].iter().any(|flag| matches.get_flag(flag)) || profile.is_some() {
+                            eprintln!("{error}`{v}{}{v:#}` is a group, groups can't be run with --detach, --log, --profile or --revert", alias);
+                            exit(1);
+                        }
+                        cli::groups::run(&config, alias, matches.get_flag("allow_multiple"));
+                    } else {
+                        if !config.aliases.contains_key(alias) {
+                            if let Some((name, name_profile)) = alias.split_once(':') {
+                                alias = name;
+                                profile = Some(name_profile.to_owned());
                             }
-                        },
-                        None => eprintln!("{error}Alias `{v}{}{v:#}` not found", alias)
+                        }
+                        let options = cli::run::RunOptions {
+                            detach: matches.get_flag("detach"),
+                            log: matches.get_flag("log"),
+                            profile,
+                            revert: matches.get_flag("revert"),
+                            allow_multiple: matches.get_flag("allow_multiple")
+                        };
+                        cli::run::run_alias(&config, alias, &options);
                     }
                 }
-                Some(("random", _)) => {
+                Some(("random", matches)) => {
                     if config.aliases.is_empty() {
                         eprintln!("{error}No aliases found");
                         exit(1);
                     }
                     let current_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
                     let index = current_time % config.aliases.len() as u128;
-                    if let Some((alias, value)) = config.aliases.iter().nth(index as usize) {
-                        let path = Path::new(value);
-                        let dir = match path.parent() {
-                            Some(path) => path,
-                            None => {
-                                eprintln!("{error}Path: {}. Failed to get the parent of path", path.display());
-                                exit(1);
-                            }
+                    if let Some(alias) = config.aliases.keys().nth(index as usize) {
+                        let options = cli::run::RunOptions {
+                            detach: matches.get_flag("detach"),
+                            log: matches.get_flag("log"),
+                            ..Default::default()
                         };
-                        match std::env::set_current_dir(dir) {
-                            Ok(_) => {}
-                            Err(err) => {
-                                eprintln!("{error}Path: `{v}{}{v:#}`. {}", path.display(), err);
-                                exit(1);
-                            }
+                        cli::run::run_alias(&config, alias, &options);
+                    }
+                }
+                Some(("supervise", matches)) => {
+                    let alias: &String = matches.get_one("alias").unwrap();
+                    let revert_mods: Option<Vec<String>> = matches.get_many::<String>("revert_mods")
+                        .map(|mods| mods.filter(|name| !name.is_empty()).cloned().collect());
+                    cli::run::supervise(&config, alias, revert_mods, matches.get_flag("allow_multiple"));
+                }
+                Some(("logs", matches)) => {
+                    let alias: &String = matches.get_one("alias").unwrap();
+                    if matches.get_flag("list") {
+                        cli::logs::print_list(alias);
+                    } else if matches.get_flag("follow") {
+                        cli::logs::follow(alias);
+                    } else {
+                        cli::logs::print_last(alias);
+                    }
+                }
+                Some(("ps", _)) => {
+                    let gray = AnsiColor::BrightBlack.on_default();
+                    let running = instances::running();
+                    if running.is_empty() {
+                        println!("No games are running");
+                    } else {
+                        println!("{bold}Running:{bold:#}");
+                        for instance in running {
+                            let uptime = history::now().saturating_sub(instance.started);
+                            println!(" {bold}{}{bold:#} {gray}->{gray:#} pid {v}{}{v:#}, up for {v}{}{v:#}", instance.alias, instance.pid, history::format_duration(uptime));
+                        }
+                    }
+                }
+                Some(("kill", matches)) => {
+                    let alias: &String = matches.get_one("alias").unwrap();
+                    match instances::kill(alias, matches.get_flag("force")) {
+                        Ok(0) => eprintln!("{error}`{v}{}{v:#}` is not running", alias),
+                        Ok(count) => println!("{success}Stopped {v}{}{v:#} instance(s) of `{v}{}{v:#}`", count, alias),
+                        Err(err) => eprintln!("{error}Failed to stop `{v}{}{v:#}`. {}", alias, err)
+                    }
+                }
+                Some(("playtime", matches)) => {
+                    let filter: Option<&String> = matches.get_one("alias");
+                    let history = match history::read_history() {
+                        Ok(history) => history,
+                        Err(err) => {
+                            eprintln!("{error}{}", err);
+                            exit(1);
+                        }
+                    };
+                    let totals = history::playtime(&history);
+                    let mut totals: Vec<_> = totals.into_iter()
+                        .filter(|(alias, _)| filter.is_none_or(|filter| filter == alias))
+                        .collect();
+                    totals.sort_by_key(|(_, (secs, _))| std::cmp::Reverse(*secs));
+                    let gray = AnsiColor::BrightBlack.on_default();
+
+                    if totals.is_empty() {
+                        println!("No recorded sessions yet");
+                    } else {
+                        println!("{bold}Playtime:{bold:#}");
+                        for (alias, (secs, sessions)) in totals {
+                            println!(" {bold}{}{bold:#} {gray}->{gray:#} {v}{}{v:#} over {} session(s)", alias, history::format_duration(secs), sessions);
                         }
-                        println!("{bold}Running:{bold:#} `{v}{}{v:#}`", path.display());
-                        if let Err(err) = std::process::Command::new(value).status() {
-                            eprintln!("{error}Failed to run alias `{v}{}{v:#}`: {}", alias, err);
-                        } 
                     }
                 }
                 Some(("config", matches)) => {
                     match matches.subcommand() {
                         Some(("check_for_updates", matches)) => {
                             let value: Option<&String> = matches.get_one("value");
-                            if value.is_some() {
-                                if value.unwrap() == "true" {
+                            if let Some(value) = value {
+                                if value == "true" {
                                     config.check_for_updates = true;
                                     save_config(&config);
                                     println!("{success}Set value of check_for_updates to `{v}true{v:#}`");
-                                } else if value.unwrap() == "false" {
+                                } else if value == "false" {
                                     config.check_for_updates = false;
                                     save_config(&config);
                                     println!("{success}Set value of check_for_updates to `{v}false{v:#}`");
@@ -676,21 +923,14 @@ async fn main() {
                         }
                         Some(("default_fetch_provider", matches)) => {
                             let value: Option<&String> = matches.get_one("value");
-                            if value.is_some() {
-                                if value.unwrap() == "Game3rb" {
-                                    config.default_fetch_provider = String::from("Game3rb");
+                            if let Some(value) = value {
+                                if let Some(provider) = fetch::provider(value) {
+                                    config.default_fetch_provider = String::from(provider.name());
                                     save_config(&config);
-                                    println!("{success}Set value of default_fetch_provider to `{v}Game3rb{v:#}`");
-                                } else if value.unwrap() == "GOG Games" {
-                                    config.default_fetch_provider = String::from("GOG Games");
-                                    save_config(&config);
-                                    println!("{success}Set value of default_fetch_provider to `{v}GOG Games{v:#}`");
-                                } else if value.unwrap() == "SteamRIP" {
-                                    config.default_fetch_provider = String::from("SteamRIP");
-                                    save_config(&config);
-                                    println!("{success}Set value of default_fetch_provider to `{v}SteamRIP{v:#}`");
+                                    println!("{success}Set value of default_fetch_provider to `{v}{}{v:#}`", provider.name());
                                 } else {
-                                    eprintln!("{error}Value needs to be either `{v}SteamRIP{v:#}`, `{v}Game3rb{v:#}` or `{v}GOG Games{v:#}`");
+                                    let names: Vec<String> = fetch::providers().iter().map(|provider| format!("`{v}{}{v:#}`", provider.name())).collect();
+                                    eprintln!("{error}Value needs to be one of {}", names.join(", "));
                                 }
                             } else {
                                 println!("Current value of default_fetch_provider is `{v}{}{v:#}`", config.default_fetch_provider);
@@ -698,19 +938,56 @@ async fn main() {
                         }
                         Some(("games_dir", matches)) => {
                             let value: Option<&String> = matches.get_one("value");
-                            if value.is_some() {
-                                config.games_dir = value.unwrap().clone();
+                            if let Some(value) = value {
+                                config.games_dir = value.clone();
                                 save_config(&config);
-                                println!("{success}Set value of games_dir to `{v}{}{v:#}`", value.unwrap());
+                                println!("{success}Set value of games_dir to `{v}{}{v:#}`", value);
                             } else {
                                 println!("Current value of games_dir is `{v}{}{v:#}`", config.games_dir);
                             }
                         }
+                        Some(("library", matches)) => {
+                            let name: Option<&String> = matches.get_one("name");
+                            let path: Option<&String> = matches.get_one("path");
+                            match (name, path) {
+                                (Some(name), _) if matches.get_flag("remove") => {
+                                    if config.libraries.remove(name).is_some() {
+                                        save_config(&config);
+                                        println!("{success}Removed library `{v}{}{v:#}`", name);
+                                    } else {
+                                        eprintln!("{error}Library `{v}{}{v:#}` doesn't exist", name);
+                                    }
+                                }
+                                (Some(name), Some(path)) => {
+                                    config.libraries.insert(name.clone(), path.clone());
+                                    save_config(&config);
+                                    println!("{success}Set library `{v}{}{v:#}` to `{v}{}{v:#}`", name, path);
+                                }
+                                (Some(name), None) => match config.libraries.get(name) {
+                                    Some(path) => println!("Library `{v}{}{v:#}` is `{v}{}{v:#}`", name, path),
+                                    None => eprintln!("{error}Library `{v}{}{v:#}` doesn't exist", name)
+                                },
+                                (None, _) => {
+                                    let gray = AnsiColor::BrightBlack.on_default();
+                                    let mut libraries: Vec<_> = config.libraries.iter().collect();
+                                    libraries.sort();
+                                    println!("{bold}Libraries:{bold:#}");
+                                    for (name, path) in libraries {
+                                        println!(" {bold}{}{bold:#} {gray}->{gray:#} {}", name, path);
+                                    }
+                                }
+                            }
+                        }
                         _ => {
                             println!("{bold}Current config values:{bold:#}");
                             println!(" {bold}games_dir:{bold:#} `{v}{}{v:#}`", config.games_dir);
                             println!(" {bold}default_fetch_provider:{bold:#} `{v}{}{v:#}`", config.default_fetch_provider);
                             println!(" {bold}check_for_updates:{bold:#} `{v}{}{v:#}`", config.check_for_updates);
+                            let mut libraries: Vec<_> = config.libraries.iter().collect();
+                            libraries.sort();
+                            for (name, path) in libraries {
+                                println!(" {bold}library {}:{bold:#} `{v}{}{v:#}`", name, path);
+                            }
                         }
                     }
                 }
@@ -719,28 +996,88 @@ async fn main() {
                         Some(("add", matches)) => {
                             let alias: &String = matches.get_one("alias").unwrap();
                             let path: &String = matches.get_one("path").unwrap();
+                            if let Err(err) = aliases::validate_name(alias) {
+                                eprintln!("{error}{}", err);
+                                exit(1);
+                            }
+                            let stored = config.portable_alias_path(path);
     
                             if config.aliases.contains_key(alias) {
                                 if user_input(format!("Overwrite alias `{v}{}{v:#}`? (y/n) ", alias)) {
-                                    config.aliases.insert(alias.to_string(), path.to_string());
+                                    config.aliases.insert(alias.to_string(), Alias::new(stored));
                                     save_config(&config);
                                     println!("{success}Overwrote alias `{v}{}{v:#}`", alias);
                                 }
                             } else {
-                                config.aliases.insert(alias.to_string(), path.to_string());
+                                config.aliases.insert(alias.to_string(), Alias::new(stored));
                                 save_config(&config);
                                 println!("{success}Added alias `{v}{}{v:#}`", alias);
                             }
-    
+                            repair::record_fingerprint(&config.expand_path(path)).ok();
                         }
                         Some(("remove", matches)) => {
+                            let alias: &String = matches.get_one("alias").unwrap();
+                            match aliases::remove(&mut config, alias) {
+                                Ok(_) => {
+                                    save_config(&config);
+                                    println!("{success}Removed alias `{v}{}{v:#}`", alias);
+                                }
+                                Err(err) => eprintln!("{error}{}", err)
+                            }
+                        }
+                        Some(("rename", matches)) => {
+                            let old: &String = matches.get_one("old").unwrap();
+                            let new: &String = matches.get_one("new").unwrap();
+                            match aliases::rename(&mut config, old, new) {
+                                Ok(_) => {
+                                    save_config(&config);
+                                    println!("{success}Renamed alias `{v}{}{v:#}` to `{v}{}{v:#}`", old, new);
+                                }
+                                Err(err) => eprintln!("{error}Failed to rename alias `{v}{}{v:#}`. {}", old, err)
+                            }
+                        }
+                        Some(("copy", matches)) => {
+                            let source: &String = matches.get_one("source").unwrap();
+                            let destination: &String = matches.get_one("destination").unwrap();
+                            if let Err(err) = aliases::validate_name(destination) {
+                                eprintln!("{error}{}", err);
+                                exit(1);
+                            }
+                            if let Some(entry) = config.aliases.get(source).cloned() {
+                                if !config.aliases.contains_key(destination) || user_input(format!("Overwrite alias `{v}{}{v:#}`? (y/n) ", destination)) {
+                                    config.aliases.insert(destination.clone(), entry);
+                                    save_config(&config);
+                                    println!("{success}Copied alias `{v}{}{v:#}` to `{v}{}{v:#}`", source, destination);
+                                }
+                            } else {
+                                eprintln!("{error}Alias `{v}{}{v:#}` doesn't exist", source);
+                            }
+                        }
+                        Some(("show", matches)) => {
                             let alias: &String = matches.get_one("alias").unwrap();
                             if config.aliases.contains_key(alias) {
-                                config.aliases.remove(alias);
-                                save_config(&config);
-                                println!("{success}Removed alias `{v}{}{v:#}`", alias);
+                                cli::aliases::show(&config, alias);
+                            } else {
+                                eprintln!("{error}Alias `{v}{}{v:#}` doesn't exist", alias);
+                            }
+                        }
+                        Some(("edit", matches)) => {
+                            let alias: &String = matches.get_one("alias").unwrap();
+                            if !config.aliases.contains_key(alias) {
+                                eprintln!("{error}Alias `{v}{}{v:#}` doesn't exist", alias);
                             } else {
-                                println!("{error}Alias `{v}{}{v:#}` doesn't exist", alias);
+                                match cli::aliases::edit(&config, alias) {
+                                    Ok(Some(entry)) => {
+                                        config.aliases.insert(alias.clone(), entry);
+                                        save_config(&config);
+                                        println!("{success}Saved alias `{v}{}{v:#}`", alias);
+                                        if let Some(problem) = bundle::unresolved(&config, alias) {
+                                            eprintln!("{bold_yellow}warning:{bold_yellow:#} {}", problem);
+                                        }
+                                    }
+                                    Ok(None) => println!("Alias `{v}{}{v:#}` was not changed", alias),
+                                    Err(err) => eprintln!("{error}Failed to edit alias `{v}{}{v:#}`. {}", alias, err)
+                                }
                             }
                         }
                         Some(("list", _)) => {
@@ -748,44 +1085,529 @@ async fn main() {
                             let gray = AnsiColor::BrightBlack.on_default();
                             
                             println!("{bold}Aliases:");
-                            for (alias, path) in sorted.iter() {
-                                println!(" {bold}{}{bold:#} {gray}->{gray:#} {}", alias, path);
+                            for (alias, entry) in sorted.iter() {
+                                println!(" {bold}{}{bold:#} {gray}->{gray:#} {}", alias, entry.path);
+                            }
+                            if !config.groups.is_empty() {
+                                println!("{bold}Groups:");
+                                for (name, group) in sort_by_key_length(config.groups.clone()) {
+                                    let members: Vec<&str> = group.members.iter().map(groups::Member::label).collect();
+                                    println!(" {bold}{}{bold:#} {gray}->{gray:#} {}", name, members.join(", "));
+                                }
                             }
                         }
                         Some(("autoadd", _)) => {
-                            match autoadd(&mut config) {
+                            match cli::aliases::autoadd(&mut config) {
                                 Ok(_) => {
                                     save_config(&config);
+                                    for entry in config.aliases.values() {
+                                        repair::record_fingerprint(&config.expand_path(&entry.path)).ok();
+                                    }
                                 },
                                 Err(err) => eprintln!("{error}{}", err)
                             }
                         }
+                        Some(("repair", matches)) => {
+                            let only: Option<&String> = matches.get_one("alias");
+                            let mut roots = repair::library_roots(&config);
+                            if let Some(extra) = matches.get_many::<String>("root") {
+                                roots.extend(extra.map(PathBuf::from));
+                            }
+                            let mut broken: Vec<(String, String)> = config.aliases.iter()
+                                .filter(|(alias, _)| only.is_none_or(|only| only == *alias))
+                                .map(|(alias, entry)| (alias.clone(), config.expand_path(&entry.path)))
+                                .filter(|(_, path)| !Path::new(path).is_file())
+                                .collect();
+                            broken.sort();
+                            if broken.is_empty() {
+                                println!("{success}No broken aliases");
+                            }
+
+                            let gray = AnsiColor::BrightBlack.on_default();
+                            for (alias, old_path) in broken {
+                                let candidates = repair::find_candidates(&old_path, &roots);
+                                if candidates.is_empty() {
+                                    eprintln!("{error}No executable named like `{v}{}{v:#}` found for `{v}{}{v:#}`", old_path, alias);
+                                    continue;
+                                }
+                                println!("{bold}{}{bold:#} {gray}(was {}){gray:#}", alias, old_path);
+                                for (idx, candidate) in candidates.iter().enumerate() {
+                                    let quality = match candidate.quality {
+                                        repair::Match::Hash => "same contents",
+                                        repair::Match::Size => "same size",
+                                        repair::Match::Name => "same name"
+                                    };
+                                    println!(" {bold}{}.{bold:#} {} {gray}({}){gray:#}", idx + 1, candidate.path.display(), quality);
+                                }
+
+                                let best = &candidates[0];
+                                let unique = candidates.get(1).is_none_or(|next| next.quality < best.quality);
+                                let choice = if matches.get_flag("yes") && unique && best.quality > repair::Match::Name {
+                                    Some(0)
+                                } else {
+                                    print!("Pick a number (enter to skip): ");
+                                    io::stdout().flush().unwrap();
+                                    let mut input = String::new();
+                                    io::stdin().read_line(&mut input).ok();
+                                    input.trim().parse::<usize>().ok()
+                                        .filter(|choice| (1..=candidates.len()).contains(choice))
+                                        .map(|choice| choice - 1)
+                                };
+                                if let Some(choice) = choice {
+                                    let new_path = candidates[choice].path.display().to_string();
+                                    config.aliases.get_mut(&alias).unwrap().path = config.portable_alias_path(&new_path);
+                                    save_config(&config);
+                                    println!("{success}Alias `{v}{}{v:#}` now points to `{v}{}{v:#}`", alias, new_path);
+                                }
+                            }
+                        }
+                        Some(("relocate", matches)) => {
+                            let from: &String = matches.get_one("from").unwrap();
+                            let to: &String = matches.get_one("to").unwrap();
+                            let gray = AnsiColor::BrightBlack.on_default();
+                            let changes = repair::relocate(&mut config, from, to);
+                            for (alias, old, new) in &changes {
+                                println!(" {bold}{}{bold:#} {} {gray}->{gray:#} {}", alias, old, new);
+                            }
+                            if changes.is_empty() {
+                                println!("No aliases point into `{v}{}{v:#}`", from);
+                            } else if matches.get_flag("dry_run") {
+                                println!("Would relocate {v}{}{v:#} alias(es)", changes.len());
+                            } else {
+                                save_config(&config);
+                                println!("{success}Relocated {v}{}{v:#} alias(es)", changes.len());
+                            }
+                        }
+                        Some(("export", matches)) => {
+                            let tag = matches.get_one::<String>("tag").map(String::as_str);
+                            let bundle = bundle::export(&config, tag);
+                            if bundle.aliases.is_empty() {
+                                eprintln!("{error}No aliases to export");
+                                exit(1);
+                            }
+                            print!("{}", toml::to_string(&bundle).unwrap());
+                        }
+                        Some(("import", matches)) => {
+                            let file: &String = matches.get_one("file").unwrap();
+                            let imported: bundle::Bundle = match fs::read_to_string(file).map(|contents| toml::from_str(&contents)) {
+                                Ok(Ok(imported)) => imported,
+                                Ok(Err(err)) => {
+                                    eprintln!("{error}`{v}{}{v:#}` is not a valid alias bundle. {}", file, err);
+                                    exit(1);
+                                }
+                                Err(err) => {
+                                    eprintln!("{error}Failed to read `{v}{}{v:#}`. {}", file, err);
+                                    exit(1);
+                                }
+                            };
+                            let on_conflict = if matches.get_flag("overwrite") {
+                                bundle::OnConflict::Overwrite
+                            } else if matches.get_flag("rename") {
+                                bundle::OnConflict::Rename
+                            } else {
+                                bundle::OnConflict::Skip
+                            };
+                            let gray = AnsiColor::BrightBlack.on_default();
+                            let report = bundle::import(&mut config, imported, on_conflict);
+                            save_config(&config);
+
+                            let mut added = 0;
+                            for (alias, imported) in report {
+                                let name = match imported {
+                                    bundle::Imported::Added => alias,
+                                    bundle::Imported::Overwritten => {
+                                        println!(" {bold}{}{bold:#} {gray}(overwritten){gray:#}", alias);
+                                        alias
+                                    }
+                                    bundle::Imported::Renamed(name) => {
+                                        println!(" {bold}{}{bold:#} {gray}(imported as{gray:#} {bold}{}{bold:#}{gray}){gray:#}", alias, name);
+                                        name
+                                    }
+                                    bundle::Imported::Skipped => {
+                                        println!(" {bold}{}{bold:#} {gray}(skipped, already exists){gray:#}", alias);
+                                        continue;
+                                    }
+                                    bundle::Imported::Invalid(err) => {
+                                        eprintln!("{bold_yellow}warning:{bold_yellow:#} Skipped {}", err);
+                                        continue;
+                                    }
+                                };
+                                added += 1;
+                                if let Some(problem) = bundle::unresolved(&config, &name) {
+                                    eprintln!("{bold_yellow}warning:{bold_yellow:#} Alias `{v}{}{v:#}` can't be resolved here, {}", name, problem);
+                                }
+                            }
+                            println!("{success}Imported {v}{}{v:#} alias(es)", added);
+                        }
                         _ => unreachable!(),
                     }
                 }
+                Some(("saves", matches)) => {
+                    match matches.subcommand() {
+                        Some(("add", matches)) => {
+                            let alias: &String = matches.get_one("alias").unwrap();
+                            let location: &String = matches.get_one("location").unwrap();
+                            match config.aliases.get_mut(alias) {
+                                Some(entry) => {
+                                    entry.saves.push(location.clone());
+                                    save_config(&config);
+                                    println!("{success}Added save location `{v}{}{v:#}` to `{v}{}{v:#}`", location, alias);
+                                }
+                                None => eprintln!("{error}Alias `{v}{}{v:#}` not found", alias)
+                            }
+                        }
+                        Some(("backup", matches)) => {
+                            let alias: &String = matches.get_one("alias").unwrap();
+                            if !config.aliases.contains_key(alias) {
+                                eprintln!("{error}Alias `{v}{}{v:#}` not found", alias);
+                                exit(1);
+                            }
+                            match saves::backup(&config, alias) {
+                                Ok(backup) => println!(
+                                    "{success}Backed up {v}{}{v:#} file(s) of `{v}{}{v:#}` to `{v}{}{v:#}`",
+                                    backup.manifest.files.len(), alias, backup.archive.display()
+                                ),
+                                Err(err) => eprintln!("{error}{}", err)
+                            }
+                        }
+                        Some(("restore", matches)) => {
+                            let alias: &String = matches.get_one("alias").unwrap();
+                            let at: Option<&String> = matches.get_one("at");
+                            let backup = match saves::find_backup(alias, at.map(|at| at.as_str())) {
+                                Ok(backup) => backup,
+                                Err(err) => {
+                                    eprintln!("{error}{}", err);
+                                    exit(1);
+                                }
+                            };
+                            println!("{bold}Backup {}:{bold:#}", backup.timestamp);
+                            for file in &backup.manifest.files {
+                                println!(" {}", file.source);
+                            }
+                            if matches.get_flag("yes") || user_input(String::from("Overwrite these files? (y/n) ")) {
+                                match saves::restore(&config, alias, &backup) {
+                                    Ok(_) => println!("{success}Restored `{v}{}{v:#}` from `{v}{}{v:#}`", alias, backup.timestamp),
+                                    Err(err) => eprintln!("{error}Failed to restore `{v}{}{v:#}`. {}", alias, err)
+                                }
+                            }
+                        }
+                        Some(("sync", matches)) => {
+                            let alias: &String = matches.get_one("alias").unwrap();
+                            if !config.aliases.contains_key(alias) {
+                                eprintln!("{error}Alias `{v}{}{v:#}` not found", alias);
+                                exit(1);
+                            }
+                            match sync::status(&config, alias) {
+                                Ok(None) => eprintln!("{error}Set saves.sync_dir and add a save location to `{v}{}{v:#}` first", alias),
+                                Ok(Some((status, synced))) => {
+                                    let synced = synced.map(|synced| synced.timestamp).unwrap_or_default();
+                                    match status {
+                                        sync::SyncStatus::InSync => println!("{success}Saves of `{v}{}{v:#}` match the synced ones from `{v}{}{v:#}`", alias, synced),
+                                        sync::SyncStatus::NotSynced => println!("Saves of `{v}{}{v:#}` haven't been synced yet, run `{v}plz saves backup {}{v:#}`", alias, alias),
+                                        sync::SyncStatus::LocalNewer => println!("Local saves of `{v}{}{v:#}` are newer than the synced ones, run `{v}plz saves backup {}{v:#}`", alias, alias),
+                                        sync::SyncStatus::SyncNewer => println!("Synced saves of `{v}{}{v:#}` from `{v}{}{v:#}` are newer, run `{v}plz saves pull {}{v:#}`", alias, synced, alias),
+                                        sync::SyncStatus::Diverged => println!("Local and synced saves of `{v}{}{v:#}` have diverged, check them before running `{v}plz saves pull {}{v:#}`", alias, alias)
+                                    }
+                                }
+                                Err(err) => eprintln!("{error}Failed to compare saves of `{v}{}{v:#}`. {}", alias, err)
+                            }
+                        }
+                        Some(("pull", matches)) => {
+                            let alias: &String = matches.get_one("alias").unwrap();
+                            if !config.aliases.contains_key(alias) {
+                                eprintln!("{error}Alias `{v}{}{v:#}` not found", alias);
+                                exit(1);
+                            }
+                            let Some(sync_dir) = &config.saves.sync_dir else {
+                                eprintln!("{error}saves.sync_dir is not set");
+                                exit(1);
+                            };
+                            let synced = match sync::latest_synced(Path::new(sync_dir), alias) {
+                                Ok(Some(synced)) => synced,
+                                Ok(None) => {
+                                    eprintln!("{error}`{v}{}{v:#}` has no synced saves", alias);
+                                    exit(1);
+                                }
+                                Err(err) => {
+                                    eprintln!("{error}{}", err);
+                                    exit(1);
+                                }
+                            };
+                            if matches.get_flag("yes") || user_input(format!("Overwrite the saves of `{v}{}{v:#}` with `{v}{}{v:#}`? (y/n) ", alias, synced.timestamp)) {
+                                match sync::pull(&config, alias, &synced) {
+                                    Ok(_) => println!("{success}Restored `{v}{}{v:#}` from `{v}{}{v:#}`", alias, synced.timestamp),
+                                    Err(err) => eprintln!("{error}Failed to restore `{v}{}{v:#}`. {}", alias, err)
+                                }
+                            }
+                        }
+                        Some(("list", matches)) => {
+                            let filter: Option<&String> = matches.get_one("alias");
+                            let gray = AnsiColor::BrightBlack.on_default();
+                            let mut aliases: Vec<&String> = config.aliases.keys()
+                                .filter(|alias| filter.is_none_or(|filter| filter == *alias))
+                                .collect();
+                            aliases.sort();
+
+                            for alias in aliases {
+                                let backups = match saves::list_backups(alias) {
+                                    Ok(backups) => backups,
+                                    Err(err) => {
+                                        eprintln!("{error}Failed to list backups of `{v}{}{v:#}`. {}", alias, err);
+                                        continue;
+                                    }
+                                };
+                                if backups.is_empty() {
+                                    continue;
+                                }
+                                println!("{bold}{}:{bold:#}", alias);
+                                for backup in backups.iter().rev() {
+                                    let size: u64 = backup.manifest.files.iter().map(|file| file.size).sum();
+                                    println!(
+                                        " {} {gray}({} file(s), {:.1} KiB){gray:#}",
+                                        backup.timestamp, backup.manifest.files.len(), size as f64 / 1024.0
+                                    );
+                                }
+                            }
+                        }
+                        _ => unreachable!()
+                    }
+                }
+                Some(("mods", matches)) => {
+                    let alias: &String = matches.get_one("alias").unwrap();
+                    if !config.aliases.contains_key(alias) {
+                        eprintln!("{error}Alias `{v}{}{v:#}` not found", alias);
+                        exit(1);
+                    }
+                    let mods_dir = mods::mods_dir();
+                    match matches.subcommand() {
+                        Some(("add", matches)) => {
+                            let source = Path::new(matches.get_one::<String>("source").unwrap());
+                            let name = match matches.get_one::<String>("name") {
+                                Some(name) => name.clone(),
+                                None => mods::mod_name(source)
+                            };
+                            let target: &String = matches.get_one("target").unwrap();
+                            match mods::add(&mods_dir, alias, source, &name, target) {
+                                Ok(_) => println!("{success}Added mod `{v}{}{v:#}` to `{v}{}{v:#}`", name, alias),
+                                Err(err) => eprintln!("{error}Failed to add mod `{v}{}{v:#}`. {}", name, err)
+                            }
+                        }
+                        Some(("remove", matches)) => {
+                            let name: &String = matches.get_one("mod").unwrap();
+                            match mods::remove(&mods_dir, alias, name) {
+                                Ok(_) => println!("{success}Removed mod `{v}{}{v:#}`", name),
+                                Err(err) => eprintln!("{error}{}", err)
+                            }
+                        }
+                        Some(("enable", matches)) => {
+                            let name: &String = matches.get_one("mod").unwrap();
+                            if let Ok(state) = mods::read_state(&mods_dir, alias) {
+                                for conflict in mods::enable_conflicts(&mods_dir, alias, &state, name).unwrap_or_default() {
+                                    let others: Vec<&str> = conflict.mods.iter().map(String::as_str).filter(|other| other != name).collect();
+                                    eprintln!("{error}`{v}{}{v:#}` is also in {}", conflict.path, others.join(", "));
+                                }
+                            }
+                            match mods::enable(&config, &mods_dir, alias, name) {
+                                Ok(files) => println!("{success}Enabled mod `{v}{}{v:#}` ({} file(s))", name, files),
+                                Err(err) => eprintln!("{error}{}", err)
+                            }
+                        }
+                        Some(("disable", matches)) => {
+                            let name: &String = matches.get_one("mod").unwrap();
+                            match mods::disable(&config, &mods_dir, alias, name) {
+                                Ok(files) => println!("{success}Disabled mod `{v}{}{v:#}` ({} file(s))", name, files),
+                                Err(err) => eprintln!("{error}{}", err)
+                            }
+                        }
+                        Some(("list", _)) => {
+                            let state = match mods::read_state(&mods_dir, alias) {
+                                Ok(state) => state,
+                                Err(err) => {
+                                    eprintln!("{error}Failed to read the mods of `{v}{}{v:#}`. {}", alias, err);
+                                    exit(1);
+                                }
+                            };
+                            let gray = AnsiColor::BrightBlack.on_default();
+                            let green = AnsiColor::BrightGreen.on_default();
+                            if state.mods.is_empty() {
+                                println!("`{v}{}{v:#}` has no mods", alias);
+                            } else {
+                                println!("{bold}Mods of `{}`:{bold:#}", alias);
+                                for info in &state.mods {
+                                    let status = if info.enabled { format!("{green}enabled{green:#}") } else { format!("{gray}disabled{gray:#}") };
+                                    let target = if info.target.is_empty() { String::new() } else { format!(" {gray}-> {}{gray:#}", info.target) };
+                                    println!(" {bold}{}{bold:#} {}{}", info.name, status, target);
+                                }
+                            }
+                            for conflict in mods::conflicts(&mods_dir, alias, &state).unwrap_or_default() {
+                                eprintln!("{bold_yellow}conflict:{bold_yellow:#} `{v}{}{v:#}` is in {}", conflict.path, conflict.mods.join(", "));
+                            }
+                        }
+                        Some(("profile", matches)) => {
+                            match matches.subcommand() {
+                                Some(("save", matches)) => {
+                                    let name: &String = matches.get_one("name").unwrap();
+                                    match mods::save_profile(&mods_dir, alias, name) {
+                                        Ok(profile) => println!("{success}Saved profile `{v}{}:{}{v:#}` with {} mod(s)", alias, name, profile.mods.len()),
+                                        Err(err) => eprintln!("{error}{}", err)
+                                    }
+                                }
+                                Some(("apply", matches)) => {
+                                    let name: &String = matches.get_one("name").unwrap();
+                                    match mods::apply_profile(&config, &mods_dir, alias, name) {
+                                        Ok(_) => println!("{success}Applied profile `{v}{}:{}{v:#}`", alias, name),
+                                        Err(err) => eprintln!("{error}Failed to apply profile `{v}{}:{}{v:#}`. {}", alias, name, err)
+                                    }
+                                }
+                                Some(("remove", matches)) => {
+                                    let name: &String = matches.get_one("name").unwrap();
+                                    match mods::remove_profile(&mods_dir, alias, name) {
+                                        Ok(_) => println!("{success}Removed profile `{v}{}:{}{v:#}`", alias, name),
+                                        Err(err) => eprintln!("{error}{}", err)
+                                    }
+                                }
+                                Some(("list", _)) => {
+                                    let state = match mods::read_state(&mods_dir, alias) {
+                                        Ok(state) => state,
+                                        Err(err) => {
+                                            eprintln!("{error}Failed to read the mods of `{v}{}{v:#}`. {}", alias, err);
+                                            exit(1);
+                                        }
+                                    };
+                                    let gray = AnsiColor::BrightBlack.on_default();
+                                    if state.profiles.is_empty() {
+                                        println!("`{v}{}{v:#}` has no profiles", alias);
+                                    }
+                                    for profile in &state.profiles {
+                                        println!(" {bold}{}:{}{bold:#} {gray}->{gray:#} {}", alias, profile.name, profile.mods.join(", "));
+                                    }
+                                }
+                                Some(("export", matches)) => {
+                                    let name: &String = matches.get_one("name").unwrap();
+                                    match mods::export_profile(&mods_dir, alias, name) {
+                                        Ok(export) => print!("{}", toml::to_string(&export).unwrap()),
+                                        Err(err) => eprintln!("{error}{}", err)
+                                    }
+                                }
+                                Some(("import", matches)) => {
+                                    let file: &String = matches.get_one("file").unwrap();
+                                    let export: mods::ProfileExport = match fs::read_to_string(file).map(|contents| toml::from_str(&contents)) {
+                                        Ok(Ok(export)) => export,
+                                        Ok(Err(err)) => {
+                                            eprintln!("{error}`{v}{}{v:#}` is not a valid profile manifest. {}", file, err);
+                                            exit(1);
+                                        }
+                                        Err(err) => {
+                                            eprintln!("{error}Failed to read `{v}{}{v:#}`. {}", file, err);
+                                            exit(1);
+                                        }
+                                    };
+                                    let name = matches.get_one::<String>("name").unwrap_or(&export.profile).clone();
+                                    match mods::import_profile(&mods_dir, alias, &export, &name) {
+                                        Ok(report) => {
+                                            for (mod_name, status) in report {
+                                                match status {
+                                                    mods::ImportStatus::Identical => {},
+                                                    mods::ImportStatus::Missing => eprintln!("{bold_yellow}warning:{bold_yellow:#} Mod `{v}{}{v:#}` isn't added yet", mod_name),
+                                                    mods::ImportStatus::Different => eprintln!("{bold_yellow}warning:{bold_yellow:#} Mod `{v}{}{v:#}` differs from the exported one", mod_name)
+                                                }
+                                            }
+                                            println!("{success}Imported profile `{v}{}:{}{v:#}`", alias, name);
+                                        }
+                                        Err(err) => eprintln!("{error}{}", err)
+                                    }
+                                }
+                                _ => unreachable!()
+                            }
+                        }
+                        _ => unreachable!()
+                    }
+                }
+                Some(("self-update", matches)) => {
+                    if matches.get_flag("rollback") {
+                        let exe = std::env::current_exe().unwrap();
+                        match plz::selfupdate::rollback(&exe) {
+                            Ok(_) => println!("{success}Rolled back, `{v}{}{v:#}` is the previous version again", exe.display()),
+                            Err(err) => {
+                                eprintln!("{error}Failed to roll back. {}", err);
+                                exit(1);
+                            }
+                        }
+                    } else if let Err(err) = cli::selfupdate::run(&config.updates, &config.http, matches.get_flag("check")).await {
+                        eprintln!("{error}{}", err);
+                        exit(1);
+                    }
+                }
                 Some(("fetch", matches)) => {
-                    let game: &String = matches.get_one("game").unwrap();
-                    fetch(game, &config.default_fetch_provider).await;
+                    let options = cli::fetch::FetchOptions {
+                        deadline: matches.get_one::<u64>("deadline").map(|secs| Duration::from_secs(*secs)),
+                        refresh: matches.get_flag("refresh"),
+                        pick: matches.get_one::<usize>("pick").copied()
+                    };
+                    let game = matches.get_one::<String>("game");
+                    if let Some(query) = matches.get_one::<String>("search") {
+                        let providers = cli::fetch::provider_order(&config.default_fetch_provider, &config);
+                        cli::fetch::search(query, providers, &config, &options).await;
+                    } else if matches.get_flag("all") {
+                        cli::fetch::fetch_all(game.unwrap(), &config, &options).await;
+                    } else {
+                        cli::fetch::fetch(game.unwrap(), &config.default_fetch_provider, &config, &options).await;
+                    }
+                }
+                Some(("info", matches)) => {
+                    let alias: &String = matches.get_one("alias").unwrap();
+                    let title = matches.get_one::<String>("title").map(String::as_str);
+                    cli::info::info(&mut config, alias, title, matches.get_flag("refresh"), matches.get_flag("add_saves")).await;
                 }
-                Some(("fetchrip", matches)) => {
-                    let game: &String = matches.get_one("game").unwrap();
-                    fetch(game, "SteamRIP").await;
+                Some(("compat", matches)) => {
+                    let alias: &String = matches.get_one("alias").unwrap();
+                    let appid = matches.get_one::<u32>("appid").copied();
+                    let title = matches.get_one::<String>("title").map(String::as_str);
+                    cli::compat::compat(&mut config, alias, appid, title, matches.get_flag("refresh"), matches.get_flag("apply")).await;
                 }
-                Some(("fetchrb", matches)) => {
-                    let game: &String = matches.get_one("game").unwrap();
-                    fetch(game, "Game3rb").await;
+                Some(("cache", matches)) => {
+                    match matches.subcommand() {
+                        Some(("stats", _)) => cli::cache::print_stats(),
+                        Some(("clear", _)) => match cache::clear() {
+                            Ok(entries) => println!("{success}Removed {v}{}{v:#} cached answer(s)", entries),
+                            Err(err) => {
+                                eprintln!("{error}Failed to clear the cache. {}", err);
+                                exit(1);
+                            }
+                        },
+                        _ => unreachable!()
+                    }
                 }
-                Some(("fetchgog", matches)) => {
-                    let game: &String = matches.get_one("game").unwrap();
-                    fetch(game, "GOG Games").await;
+                Some((command, matches)) => {
+                    let Some(provider) = fetch::provider_for_command(command) else {
+                        unreachable!()
+                    };
+                    let options = cli::fetch::FetchOptions {
+                        deadline: matches.get_one::<u64>("deadline").map(|secs| Duration::from_secs(*secs)),
+                        refresh: matches.get_flag("refresh"),
+                        pick: matches.get_one::<usize>("pick").copied()
+                    };
+                    if let Some(query) = matches.get_one::<String>("search") {
+                        cli::fetch::search(query, vec![provider], &config, &options).await;
+                    } else {
+                        let game: &String = matches.get_one("game").unwrap();
+                        cli::fetch::fetch(game, provider.name(), &config, &options).await;
+                    }
                 }
                 _ => unreachable!()
             }
         }
-        Err(err) => err.print().unwrap()
+        Err(_) => unreachable!()
     }
-    check_config(&mut config);
-    if let Some(future) = update_message {
-        println!("{}", future.await);
+    cli::doctor::hint(&config);
+    if let Some(check) = update_check {
+        if let Some(latest) = updates::finish(check, &config.updates, &updates::cache_path()).await {
+            let green = AnsiColor::BrightGreen.on_default().bold();
+            let v = AnsiColor::BrightYellow.on_default().bold();
+            println!(
+                "\n{green}New version of plz available:{green:#}\n Current: {v}v{}{v:#}\n New version: {green}{}{green:#}",
+                env!("CARGO_PKG_VERSION"), latest
+            );
+        }
     }
 }
@@ -0,0 +1,763 @@
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use flate2::read::GzDecoder;
+use sha2::Digest;
+use std::fs::File;
+use std::fs;
+use std::io;
+
+use crate::{data_dir, Config};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ModSettings {
+    /// How mods get deployed into the game folder: `symlink`, `hardlink` or `copy`.
+    #[serde(default = "default_method")]
+    pub method: String
+}
+
+impl Default for ModSettings {
+    fn default() -> Self {
+        ModSettings { method: default_method() }
+    }
+}
+
+fn default_method() -> String {
+    String::from("symlink")
+}
+
+pub const METHODS: [&str; 3] = ["symlink", "hardlink", "copy"];
+
+/// Everything plz knows about the mods of one alias, kept in `mods/<alias>/deployment.toml`.
+#[derive(Serialize, Deserialize, Default)]
+pub struct ModState {
+    #[serde(default)]
+    pub mods: Vec<ModInfo>,
+    /// Every file currently deployed into the game folder.
+    #[serde(default)]
+    pub deployed: Vec<Deployed>,
+    /// Folders that were created while deploying and get removed again once empty.
+    /// `mod` is the mod that created them, they're removed on whichever disable empties them.
+    #[serde(default)]
+    pub created_dirs: Vec<CreatedDir>,
+    #[serde(default)]
+    pub profiles: Vec<Profile>
+}
+
+/// A named set of mods, enabled in this order when the profile gets applied.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Profile {
+    pub name: String,
+    pub mods: Vec<String>
+}
+
+/// A profile written to a standalone file, so others can build the exact same modpack.
+#[derive(Serialize, Deserialize)]
+pub struct ProfileExport {
+    pub alias: String,
+    pub profile: String,
+    #[serde(default)]
+    pub mods: Vec<ExportedMod>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExportedMod {
+    pub name: String,
+    #[serde(default)]
+    pub target: String,
+    /// Hash over every staged file of the mod, see `content_hash`.
+    pub sha256: String
+}
+
+/// How an imported mod compares to what is staged locally.
+pub enum ImportStatus {
+    Identical,
+    Missing,
+    Different
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ModInfo {
+    pub name: String,
+    /// Folder inside the game folder the mod gets deployed into, empty for the game folder itself.
+    #[serde(default)]
+    pub target: String,
+    #[serde(default)]
+    pub enabled: bool
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Deployed {
+    /// Path relative to the game folder, always with `/` separators.
+    pub path: String,
+    #[serde(rename = "mod")]
+    pub mod_name: String,
+    pub method: String,
+    /// The game had its own file here, which was moved to `originals/` and gets put back on disable.
+    #[serde(default)]
+    pub replaced_original: bool
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CreatedDir {
+    pub path: String,
+    #[serde(rename = "mod")]
+    pub mod_name: String
+}
+
+/// A file that two mods both want to deploy.
+pub struct Conflict {
+    pub path: String,
+    pub mods: Vec<String>
+}
+
+
+/// Where the mods of every alias are kept, each in a folder named after it.
+pub fn mods_dir() -> PathBuf {
+    data_dir().join("mods")
+}
+
+
+fn staging_dir(dir: &Path, alias: &str, name: &str) -> PathBuf {
+    dir.join(alias).join("staging").join(name)
+}
+
+
+fn originals_dir(dir: &Path, alias: &str, name: &str) -> PathBuf {
+    dir.join(alias).join("originals").join(name)
+}
+
+
+pub fn game_dir(config: &Config, alias: &str) -> io::Result<PathBuf> {
+    let Some(path) = config.alias_path(alias) else {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("Alias `{alias}` not found")));
+    };
+    Ok(Path::new(&path).parent().map(Path::to_path_buf).unwrap_or_default())
+}
+
+
+/// The mod state of `alias`, from the mods folder at `dir` (normally `mods_dir()`).
+pub fn read_state(dir: &Path, alias: &str) -> io::Result<ModState> {
+    match fs::read_to_string(dir.join(alias).join("deployment.toml")) {
+        Ok(contents) => toml::from_str(&contents).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(ModState::default()),
+        Err(err) => Err(err)
+    }
+}
+
+
+pub fn save_state(dir: &Path, alias: &str, state: &ModState) -> io::Result<()> {
+    fs::create_dir_all(dir.join(alias))?;
+    fs::write(dir.join(alias).join("deployment.toml"), toml::to_string(state).unwrap())
+}
+
+
+fn not_found(alias: &str, name: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("`{alias}` has no mod named `{name}`"))
+}
+
+
+/// Files of a staged mod relative to its staging folder, with `/` separators.
+fn staged_files(dir: &Path) -> io::Result<Vec<String>> {
+    fn walk(root: &Path, dir: &Path, files: &mut Vec<String>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                walk(root, &path, files)?;
+            } else {
+                let relative = path.strip_prefix(root).unwrap();
+                files.push(relative.components()
+                    .map(|part| part.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/"));
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    walk(dir, dir, &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+
+/// `target` with `/` separators, or an error if it's absolute or leaves the game folder.
+fn target_dir(target: &str) -> io::Result<String> {
+    let parts: Vec<&str> = target.split(['/', '\\']).filter(|part| !part.is_empty() && *part != ".").collect();
+    if target.starts_with(['/', '\\']) || target.contains(':') || parts.contains(&"..") {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+            "`{target}` is not a folder inside the game folder"
+        )));
+    }
+    Ok(parts.join("/"))
+}
+
+
+/// Where each file of `info` ends up, relative to the game folder.
+fn mod_files(dir: &Path, alias: &str, info: &ModInfo) -> io::Result<Vec<(String, PathBuf)>> {
+    let staging = staging_dir(dir, alias, &info.name);
+    let target = target_dir(&info.target)?;
+    Ok(staged_files(&staging)?
+        .into_iter()
+        .map(|file| {
+            let source = staging.join(&file);
+            if target.is_empty() {
+                (file, source)
+            } else {
+                (format!("{target}/{file}"), source)
+            }
+        })
+        .collect())
+}
+
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let path = entry?.path();
+        let dest = to.join(path.file_name().unwrap());
+        if path.is_dir() {
+            copy_dir(&path, &dest)?;
+        } else {
+            fs::copy(&path, &dest)?;
+        }
+    }
+    Ok(())
+}
+
+
+fn unpack(source: &Path, dest: &Path) -> io::Result<()> {
+    let name = source.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+    if source.is_dir() {
+        copy_dir(source, dest)
+    } else if name.ends_with(".zip") {
+        let mut archive = zip::ZipArchive::new(File::open(source)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        archive.extract(dest).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        tar::Archive::new(GzDecoder::new(File::open(source)?)).unpack(dest)
+    } else if name.ends_with(".tar") {
+        tar::Archive::new(File::open(source)?).unpack(dest)
+    } else {
+        Err(io::Error::new(io::ErrorKind::Unsupported, format!(
+            "`{}` is not a folder or a .zip/.tar/.tar.gz archive", source.display()
+        )))
+    }
+}
+
+
+/// The default name of a mod added from `source`, its file name without the archive extension.
+pub fn mod_name(source: &Path) -> String {
+    let name = source.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    for ext in [".tar.gz", ".tgz", ".tar", ".zip"] {
+        if name.to_lowercase().ends_with(ext) {
+            return name[..name.len() - ext.len()].to_owned();
+        }
+    }
+    name
+}
+
+
+/// Copies or extracts `source` into the staging area. The mod starts out disabled.
+pub fn add(dir: &Path, alias: &str, source: &Path, name: &str, target: &str) -> io::Result<()> {
+    if name.is_empty() || name.contains(['/', '\\', ':']) || name == "." || name == ".." {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("`{name}` is not a valid mod name")));
+    }
+    let target = target_dir(target)?;
+    let mut state = read_state(dir, alias)?;
+    if state.mods.iter().any(|info| info.name == name) {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("`{alias}` already has a mod named `{name}`")));
+    }
+
+    let staging = staging_dir(dir, alias, name);
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    if let Err(err) = unpack(source, &staging) {
+        fs::remove_dir_all(&staging).ok();
+        return Err(err);
+    }
+
+    state.mods.push(ModInfo { name: name.to_owned(), target, enabled: false });
+    save_state(dir, alias, &state)
+}
+
+
+/// Deletes a disabled mod from the staging area.
+pub fn remove(dir: &Path, alias: &str, name: &str) -> io::Result<()> {
+    let mut state = read_state(dir, alias)?;
+    let Some(idx) = state.mods.iter().position(|info| info.name == name) else {
+        return Err(not_found(alias, name));
+    };
+    if state.mods[idx].enabled {
+        return Err(io::Error::other(format!("Disable `{name}` before removing it")));
+    }
+    let staging = staging_dir(dir, alias, name);
+    if staging.exists() {
+        fs::remove_dir_all(staging)?;
+    }
+    state.mods.remove(idx);
+    for profile in &mut state.profiles {
+        profile.mods.retain(|mod_name| mod_name != name);
+    }
+    save_state(dir, alias, &state)
+}
+
+
+/// Files that more than one staged mod would deploy.
+pub fn conflicts(dir: &Path, alias: &str, state: &ModState) -> io::Result<Vec<Conflict>> {
+    // Keyed case-insensitively, Windows and Wine don't care about the case either.
+    let mut owners: HashMap<String, Conflict> = HashMap::new();
+    for info in &state.mods {
+        for (path, _) in mod_files(dir, alias, info)? {
+            owners.entry(path.to_lowercase())
+                .or_insert_with(|| Conflict { path, mods: Vec::new() })
+                .mods.push(info.name.clone());
+        }
+    }
+    let mut conflicts: Vec<Conflict> = owners.into_values()
+        .filter(|conflict| conflict.mods.len() > 1)
+        .collect();
+    conflicts.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(conflicts)
+}
+
+
+/// Conflicts between `name` and the mods that are enabled right now.
+pub fn enable_conflicts(dir: &Path, alias: &str, state: &ModState, name: &str) -> io::Result<Vec<Conflict>> {
+    Ok(conflicts(dir, alias, state)?
+        .into_iter()
+        .filter(|conflict| conflict.mods.iter().any(|mod_name| mod_name == name))
+        .filter(|conflict| conflict.mods.iter().any(|mod_name| {
+            mod_name != name && state.mods.iter().any(|info| &info.name == mod_name && info.enabled)
+        }))
+        .collect())
+}
+
+
+fn deploy_file(source: &Path, dest: &Path, method: &str) -> io::Result<()> {
+    match method {
+        "symlink" => symlink_file(source, dest),
+        "hardlink" => fs::hard_link(source, dest),
+        "copy" => fs::copy(source, dest).map(|_| ()),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+            "Unknown deploy method `{method}`, use one of {}", METHODS.join(", ")
+        )))
+    }
+}
+
+
+#[cfg(unix)]
+fn symlink_file(source: &Path, dest: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(source, dest)
+}
+
+
+#[cfg(windows)]
+fn symlink_file(source: &Path, dest: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(source, dest)
+}
+
+
+/// Deploys every file of `name` into the game folder. Game files in the way are moved to
+/// `originals/` first, so `disable` can put them back exactly as they were.
+pub fn enable(config: &Config, dir: &Path, alias: &str, name: &str) -> io::Result<usize> {
+    let mut state = read_state(dir, alias)?;
+    let Some(idx) = state.mods.iter().position(|info| info.name == name) else {
+        return Err(not_found(alias, name));
+    };
+    if state.mods[idx].enabled {
+        return Ok(0);
+    }
+    let conflicts = enable_conflicts(dir, alias, &state, name)?;
+    if !conflicts.is_empty() {
+        return Err(io::Error::other(format!(
+            "`{name}` conflicts with enabled mods on {} file(s)", conflicts.len()
+        )));
+    }
+
+    let game_dir = game_dir(config, alias)?;
+    let method = config.mods.method.clone();
+    let files = mod_files(dir, alias, &state.mods[idx])?;
+    let deployed = files.iter().try_for_each(|(path, source)| {
+        let dest = game_dir.join(path);
+        let parent = dest.parent().unwrap();
+        if !parent.exists() {
+            // Remember every folder we create, from the outermost one in.
+            let mut missing = Vec::new();
+            let mut folder = parent;
+            while !folder.exists() {
+                missing.push(folder);
+                folder = folder.parent().unwrap();
+            }
+            for folder in missing.into_iter().rev() {
+                fs::create_dir(folder)?;
+                let relative = folder.strip_prefix(&game_dir).unwrap().to_string_lossy().replace('\\', "/");
+                state.created_dirs.push(CreatedDir { path: relative, mod_name: name.to_owned() });
+            }
+        }
+
+        let replaced_original = dest.symlink_metadata().is_ok();
+        if replaced_original {
+            let original = originals_dir(dir, alias, name).join(path);
+            fs::create_dir_all(original.parent().unwrap())?;
+            fs::rename(&dest, &original)?;
+        }
+        // Save the state after every file, so a failure halfway can still be undone.
+        state.deployed.push(Deployed { path: path.clone(), mod_name: name.to_owned(), method: method.clone(), replaced_original });
+        save_state(dir, alias, &state)?;
+        deploy_file(source, &dest, &method)
+    });
+    if let Err(err) = deployed {
+        // A mod is either enabled with all of its files or not at all.
+        save_state(dir, alias, &state)
+            .and_then(|_| disable(config, dir, alias, name))
+            .map_err(|undo| io::Error::new(err.kind(), format!("{err}. Undoing the files deployed so far failed too. {undo}")))?;
+        return Err(err);
+    }
+
+    state.mods[idx].enabled = true;
+    save_state(dir, alias, &state)?;
+    Ok(files.len())
+}
+
+
+/// Whether `dest` is still what `deploy_file` put there from `source`, and not something
+/// the game or the user replaced it with since.
+fn is_deployed(source: &Path, dest: &Path, method: &str) -> bool {
+    match method {
+        "symlink" => fs::read_link(dest).is_ok_and(|target| target == source),
+        _ => {
+            let is_file = dest.symlink_metadata().is_ok_and(|metadata| metadata.is_file());
+            is_file && matches!(
+                (crate::saves::sha256_file(source), crate::saves::sha256_file(dest)),
+                (Ok(source), Ok(dest)) if source == dest
+            )
+        }
+    }
+}
+
+
+/// Removes every deployed file of `name` and puts back the game files it replaced.
+/// Files that changed since they were deployed are left alone, and so are their originals.
+pub fn disable(config: &Config, dir: &Path, alias: &str, name: &str) -> io::Result<usize> {
+    let mut state = read_state(dir, alias)?;
+    let Some(idx) = state.mods.iter().position(|info| info.name == name) else {
+        return Err(not_found(alias, name));
+    };
+
+    let game_dir = game_dir(config, alias)?;
+    let sources: HashMap<String, PathBuf> = mod_files(dir, alias, &state.mods[idx])?.into_iter().collect();
+    let deployed: Vec<Deployed> = state.deployed.iter().filter(|deployed| deployed.mod_name == name).cloned().collect();
+    let mut kept_originals = false;
+    for file in &deployed {
+        let dest = game_dir.join(&file.path);
+        let original = originals_dir(dir, alias, name).join(&file.path);
+        let ours = sources.get(&file.path).is_some_and(|source| is_deployed(source, &dest, &file.method));
+        if ours {
+            fs::remove_file(&dest)?;
+        }
+        // Gone already if a disable failed halfway before, then only the original is left to restore.
+        if file.replaced_original && original.exists() {
+            if ours || dest.symlink_metadata().is_err() {
+                fs::rename(&original, &dest)?;
+            } else {
+                kept_originals = true;
+            }
+        }
+        // Save the state after every file, so a retry doesn't handle it again.
+        state.deployed.retain(|deployed| !(deployed.mod_name == name && deployed.path == file.path));
+        save_state(dir, alias, &state)?;
+    }
+
+    // Only empty folders go, the game or another mod might still have files in there.
+    for dir in state.created_dirs.iter().rev() {
+        fs::remove_dir(game_dir.join(&dir.path)).ok();
+    }
+    state.created_dirs.retain(|dir| game_dir.join(&dir.path).exists());
+    let originals = originals_dir(dir, alias, name);
+    if originals.exists() && !kept_originals {
+        fs::remove_dir_all(originals)?;
+    }
+
+    state.mods[idx].enabled = false;
+    save_state(dir, alias, &state)?;
+    Ok(deployed.len())
+}
+
+
+/// Names of the mods that are enabled right now.
+pub fn enabled_mods(state: &ModState) -> Vec<String> {
+    state.mods.iter().filter(|info| info.enabled).map(|info| info.name.clone()).collect()
+}
+
+
+/// Saves the currently enabled mods as a profile, replacing one with the same name.
+pub fn save_profile(dir: &Path, alias: &str, name: &str) -> io::Result<Profile> {
+    let mut state = read_state(dir, alias)?;
+    let profile = Profile { name: name.to_owned(), mods: enabled_mods(&state) };
+    state.profiles.retain(|profile| profile.name != name);
+    state.profiles.push(profile.clone());
+    save_state(dir, alias, &state)?;
+    Ok(profile)
+}
+
+
+pub fn remove_profile(dir: &Path, alias: &str, name: &str) -> io::Result<()> {
+    let mut state = read_state(dir, alias)?;
+    let count = state.profiles.len();
+    state.profiles.retain(|profile| profile.name != name);
+    if state.profiles.len() == count {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("`{alias}` has no profile named `{name}`")));
+    }
+    save_state(dir, alias, &state)
+}
+
+
+/// Makes exactly `mods` enabled, disabling everything else first. Returns the mods that
+/// were enabled before, so the caller can switch back.
+pub fn deploy_set(config: &Config, dir: &Path, alias: &str, mods: &[String]) -> io::Result<Vec<String>> {
+    let state = read_state(dir, alias)?;
+    let previous = enabled_mods(&state);
+    if let Some(missing) = mods.iter().find(|name| !state.mods.iter().any(|info| &info.name == *name)) {
+        return Err(not_found(alias, missing));
+    }
+    for name in previous.iter().filter(|name| !mods.contains(name)) {
+        disable(config, dir, alias, name)?;
+    }
+    for name in mods {
+        enable(config, dir, alias, name)?;
+    }
+    Ok(previous)
+}
+
+
+/// Deploys the mods of profile `name`. Returns the mods that were enabled before.
+pub fn apply_profile(config: &Config, dir: &Path, alias: &str, name: &str) -> io::Result<Vec<String>> {
+    let state = read_state(dir, alias)?;
+    let Some(profile) = state.profiles.iter().find(|profile| profile.name == name) else {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("`{alias}` has no profile named `{name}`")));
+    };
+    deploy_set(config, dir, alias, &profile.mods)
+}
+
+
+/// Hash of a staged mod: SHA-256 over the sorted list of its files and their own hashes,
+/// so it doesn't depend on timestamps or on how the mod was added.
+pub fn content_hash(dir: &Path, alias: &str, name: &str) -> io::Result<String> {
+    let staging = staging_dir(dir, alias, name);
+    let mut listing = String::new();
+    for file in staged_files(&staging)? {
+        let hash = crate::saves::sha256_file(&staging.join(&file))?;
+        listing.push_str(&format!("{hash}  {file}\n"));
+    }
+    let digest = sha2::Sha256::digest(listing.as_bytes());
+    Ok(digest.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+
+pub fn export_profile(dir: &Path, alias: &str, name: &str) -> io::Result<ProfileExport> {
+    let state = read_state(dir, alias)?;
+    let Some(profile) = state.profiles.iter().find(|profile| profile.name == name) else {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("`{alias}` has no profile named `{name}`")));
+    };
+    let mut export = ProfileExport { alias: alias.to_owned(), profile: name.to_owned(), mods: Vec::new() };
+    for mod_name in &profile.mods {
+        let info = state.mods.iter().find(|info| &info.name == mod_name).ok_or_else(|| not_found(alias, mod_name))?;
+        export.mods.push(ExportedMod {
+            name: mod_name.clone(),
+            target: info.target.clone(),
+            sha256: content_hash(dir, alias, mod_name)?
+        });
+    }
+    Ok(export)
+}
+
+
+/// Adds the profile from `export` under `name` and reports how every mod in it compares
+/// to the locally staged one. Missing mods are kept in the profile, applying it fails
+/// until they get added.
+pub fn import_profile(dir: &Path, alias: &str, export: &ProfileExport, name: &str) -> io::Result<Vec<(String, ImportStatus)>> {
+    let mut state = read_state(dir, alias)?;
+    let mut report = Vec::new();
+    for exported in &export.mods {
+        let status = match state.mods.iter().find(|info| info.name == exported.name) {
+            None => ImportStatus::Missing,
+            Some(_) if content_hash(dir, alias, &exported.name)? == exported.sha256 => ImportStatus::Identical,
+            Some(_) => ImportStatus::Different
+        };
+        report.push((exported.name.clone(), status));
+    }
+
+    state.profiles.retain(|profile| profile.name != name);
+    state.profiles.push(Profile {
+        name: name.to_owned(),
+        mods: export.mods.iter().map(|exported| exported.name.clone()).collect()
+    });
+    save_state(dir, alias, &state)?;
+    Ok(report)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use crate::Alias;
+
+    /// A config with the alias `game` for `<dir>/game/game.exe`, whose folder has a
+    /// `data/config.ini` of its own.
+    fn game(dir: &Path, method: &str) -> Config {
+        fs::create_dir_all(dir.join("game/data")).unwrap();
+        fs::write(dir.join("game/game.exe"), "game").unwrap();
+        fs::write(dir.join("game/data/config.ini"), "original").unwrap();
+        let mut config: Config = toml::from_str(crate::DEFAULT_CONFIG).unwrap();
+        config.mods.method = method.to_owned();
+        let path = dir.join("game/game.exe").to_string_lossy().into_owned();
+        config.aliases.insert(String::from("game"), Alias::new(path));
+        config
+    }
+
+    /// Adds a mod made of `files` (path and contents) to `game`.
+    fn add_mod(dir: &Path, name: &str, files: &[(&str, &str)]) {
+        let source = dir.join("sources").join(name);
+        for (path, contents) in files {
+            fs::create_dir_all(source.join(path).parent().unwrap()).unwrap();
+            fs::write(source.join(path), contents).unwrap();
+        }
+        add(&dir.join("mods"), "game", &source, name, "").unwrap();
+    }
+
+    fn read(path: PathBuf) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn puts_the_game_back_exactly_on_disable() {
+        for method in METHODS {
+            let dir = TestDir::new();
+            let config = game(dir.path(), method);
+            let mods = dir.path().join("mods");
+            add_mod(dir.path(), "hd", &[("data/config.ini", "modded"), ("textures/hd/rock.png", "rock")]);
+
+            assert_eq!(enable(&config, &mods, "game", "hd").unwrap(), 2);
+            assert_eq!(read(dir.path().join("game/data/config.ini")), "modded");
+            assert_eq!(read(dir.path().join("game/textures/hd/rock.png")), "rock");
+            assert!(read_state(&mods, "game").unwrap().mods[0].enabled);
+
+            assert_eq!(disable(&config, &mods, "game", "hd").unwrap(), 2);
+            assert_eq!(read(dir.path().join("game/data/config.ini")), "original");
+            assert!(!dir.path().join("game/textures").exists());
+            assert!(!originals_dir(&mods, "game", "hd").exists());
+            let state = read_state(&mods, "game").unwrap();
+            assert!(!state.mods[0].enabled);
+            assert!(state.deployed.is_empty() && state.created_dirs.is_empty());
+        }
+    }
+
+    #[test]
+    fn finds_files_two_mods_deploy() {
+        let dir = TestDir::new();
+        let config = game(dir.path(), "copy");
+        let mods = dir.path().join("mods");
+        add_mod(dir.path(), "hd", &[("data/config.ini", "hd"), ("textures/rock.png", "rock")]);
+        add_mod(dir.path(), "ui", &[("Data/Config.ini", "ui"), ("ui/font.ttf", "font")]);
+
+        let state = read_state(&mods, "game").unwrap();
+        let conflicts = conflicts(&mods, "game", &state).unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].mods, ["hd", "ui"]);
+        // Only enabled mods are in the way.
+        assert!(enable_conflicts(&mods, "game", &state, "ui").unwrap().is_empty());
+
+        enable(&config, &mods, "game", "hd").unwrap();
+        let state = read_state(&mods, "game").unwrap();
+        assert_eq!(enable_conflicts(&mods, "game", &state, "ui").unwrap().len(), 1);
+        assert!(enable(&config, &mods, "game", "ui").is_err());
+        assert!(!dir.path().join("game/ui").exists());
+        assert_eq!(read(dir.path().join("game/data/config.ini")), "hd");
+    }
+
+    #[test]
+    fn leaves_files_that_changed_since_deployment() {
+        let dir = TestDir::new();
+        let config = game(dir.path(), "copy");
+        let mods = dir.path().join("mods");
+        add_mod(dir.path(), "hd", &[("data/config.ini", "modded"), ("data/rock.png", "rock")]);
+        enable(&config, &mods, "game", "hd").unwrap();
+        fs::write(dir.path().join("game/data/config.ini"), "changed by the game").unwrap();
+
+        disable(&config, &mods, "game", "hd").unwrap();
+        assert_eq!(read(dir.path().join("game/data/config.ini")), "changed by the game");
+        assert!(!dir.path().join("game/data/rock.png").exists());
+        // The original is kept, it's not lost just because it couldn't be put back.
+        assert_eq!(read(originals_dir(&mods, "game", "hd").join("data/config.ini")), "original");
+        assert!(read_state(&mods, "game").unwrap().deployed.is_empty());
+    }
+
+    #[test]
+    fn undoes_half_deployed_mods() {
+        let dir = TestDir::new();
+        let config = game(dir.path(), "copy");
+        let mods = dir.path().join("mods");
+        // `textures` is a file in the game folder, so the second file can't be deployed.
+        fs::write(dir.path().join("game/textures"), "").unwrap();
+        add_mod(dir.path(), "hd", &[("data/config.ini", "modded"), ("textures/rock.png", "rock")]);
+
+        assert!(enable(&config, &mods, "game", "hd").is_err());
+        assert_eq!(read(dir.path().join("game/data/config.ini")), "original");
+        let state = read_state(&mods, "game").unwrap();
+        assert!(!state.mods[0].enabled);
+        assert!(state.deployed.is_empty());
+    }
+
+    #[test]
+    fn exports_and_imports_profiles() {
+        let dir = TestDir::new();
+        let config = game(dir.path(), "copy");
+        let mods = dir.path().join("mods");
+        add_mod(dir.path(), "hd", &[("textures/rock.png", "rock")]);
+        add_mod(dir.path(), "ui", &[("ui/font.ttf", "font")]);
+        add_mod(dir.path(), "maps", &[("maps/island.map", "island")]);
+        deploy_set(&config, &mods, "game", &[String::from("hd"), String::from("ui"), String::from("maps")]).unwrap();
+        save_profile(&mods, "game", "pack").unwrap();
+        let export = toml::to_string(&export_profile(&mods, "game", "pack").unwrap()).unwrap();
+        let export: ProfileExport = toml::from_str(&export).unwrap();
+
+        // Somewhere else, with one mod the same, one changed and one missing.
+        let other = TestDir::new();
+        let other_mods = other.path().join("mods");
+        add_mod(other.path(), "hd", &[("textures/rock.png", "rock")]);
+        add_mod(other.path(), "ui", &[("ui/font.ttf", "another font")]);
+        let report = import_profile(&other_mods, "game", &export, "friends").unwrap();
+        let report: Vec<(&str, &str)> = report.iter()
+            .map(|(name, status)| (name.as_str(), match status {
+                ImportStatus::Identical => "identical",
+                ImportStatus::Missing => "missing",
+                ImportStatus::Different => "different"
+            }))
+            .collect();
+        assert_eq!(report, [("hd", "identical"), ("ui", "different"), ("maps", "missing")]);
+        let state = read_state(&other_mods, "game").unwrap();
+        assert_eq!(state.profiles[0].name, "friends");
+        assert_eq!(state.profiles[0].mods, ["hd", "ui", "maps"]);
+        // Applying it fails until the missing mod is added.
+        let other_config = game(other.path(), "copy");
+        assert!(apply_profile(&other_config, &other_mods, "game", "friends").is_err());
+    }
+
+    #[test]
+    fn keeps_targets_inside_the_game_folder() {
+        let dir = TestDir::new();
+        let mods = dir.path().join("mods");
+        let source = dir.path().join("source");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("mod.dll"), "mod").unwrap();
+        for target in ["../../..", "bin/../..", "/etc", "\\Windows", "C:\\Windows"] {
+            assert!(add(&mods, "game", &source, "mod", target).is_err(), "`{target}` was accepted");
+        }
+        add(&mods, "game", &source, "mod", "bin\\./plugins/").unwrap();
+        assert_eq!(read_state(&mods, "game").unwrap().mods[0].target, "bin/plugins");
+    }
+}
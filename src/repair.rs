@@ -0,0 +1,256 @@
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::fs;
+use std::io;
+
+use crate::{data_dir, saves, Config};
+
+/// What an alias target looked like while it still existed, keyed by its path in
+/// `fingerprints.toml`. Used to recognise the executable after it got moved.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Fingerprint {
+    pub size: u64,
+    pub sha256: String
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
+pub enum Match {
+    /// Same file name only.
+    Name,
+    /// Same file name and size.
+    Size,
+    /// Same file name and contents.
+    Hash
+}
+
+pub struct Candidate {
+    pub path: PathBuf,
+    pub quality: Match
+}
+
+
+fn fingerprints_path() -> PathBuf {
+    data_dir().join("fingerprints.toml")
+}
+
+
+fn read_fingerprints(file: &Path) -> HashMap<String, Fingerprint> {
+    fs::read_to_string(file)
+        .ok()
+        .and_then(|contents| toml::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+
+/// Remembers the size and hash of `path`, replacing what was known about it before
+/// (a game update changes the executable).
+pub fn record_fingerprint(path: &str) -> io::Result<()> {
+    record_fingerprint_in(&fingerprints_path(), path)
+}
+
+
+/// `record_fingerprint` with the fingerprints stored in `file`.
+fn record_fingerprint_in(file: &Path, path: &str) -> io::Result<()> {
+    let mut fingerprints = read_fingerprints(file);
+    let size = fs::metadata(path)?.len();
+    let sha256 = saves::sha256_file(Path::new(path))?;
+    let fingerprint = Fingerprint { size, sha256 };
+    if fingerprints.get(path) == Some(&fingerprint) {
+        return Ok(());
+    }
+    fingerprints.insert(path.to_owned(), fingerprint);
+    fs::write(file, toml::to_string(&fingerprints).unwrap())
+}
+
+
+pub fn fingerprint(path: &str) -> Option<Fingerprint> {
+    read_fingerprints(&fingerprints_path()).remove(path)
+}
+
+
+/// Folders to search for moved executables.
+pub fn library_roots(config: &Config) -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    if !config.games_dir.is_empty() {
+        roots.push(PathBuf::from(&config.games_dir));
+    }
+    let mut libraries: Vec<_> = config.libraries.values().collect();
+    libraries.sort();
+    roots.extend(libraries.into_iter().map(PathBuf::from));
+    roots
+}
+
+
+fn find_by_name(dir: &Path, name: &str, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let path = entry.path();
+        // Symlinked folders are skipped so a link loop can't trap the search.
+        if file_type.is_dir() {
+            find_by_name(&path, name, found);
+        } else if file_type.is_file() && entry.file_name().to_string_lossy().eq_ignore_ascii_case(name) {
+            found.push(path);
+        }
+    }
+}
+
+
+/// Files under `roots` named like `old_path`, the best matches first.
+pub fn find_candidates(old_path: &str, roots: &[PathBuf]) -> Vec<Candidate> {
+    find_candidates_in(&fingerprints_path(), old_path, roots)
+}
+
+
+/// `find_candidates` with the fingerprints stored in `file`.
+fn find_candidates_in(file: &Path, old_path: &str, roots: &[PathBuf]) -> Vec<Candidate> {
+    let Some(name) = Path::new(old_path).file_name() else {
+        return Vec::new();
+    };
+    let name = name.to_string_lossy();
+    let mut found = Vec::new();
+    for root in roots {
+        find_by_name(root, &name, &mut found);
+    }
+    found.sort();
+    found.dedup();
+
+    let known = read_fingerprints(file).remove(old_path);
+    let mut candidates: Vec<Candidate> = found.into_iter()
+        .map(|path| {
+            let quality = match &known {
+                Some(known) if fs::metadata(&path).is_ok_and(|meta| meta.len() == known.size) => {
+                    if saves::sha256_file(&path).is_ok_and(|sha256| sha256 == known.sha256) {
+                        Match::Hash
+                    } else {
+                        Match::Size
+                    }
+                }
+                _ => Match::Name
+            };
+            Candidate { path, quality }
+        })
+        .collect();
+    candidates.sort_by_key(|candidate| std::cmp::Reverse(candidate.quality));
+    candidates
+}
+
+
+/// Compares paths the way a user would, ignoring which slash was used.
+fn normalize(path: &str) -> String {
+    path.replace('\\', "/").trim_end_matches('/').to_owned()
+}
+
+
+/// `path` with the `from` prefix swapped for `to`, or `None` if it's not under `from`.
+pub fn relocate_path(path: &str, from: &str, to: &str) -> Option<String> {
+    let from = normalize(from);
+    let normalized = normalize(path);
+    let rest = normalized.strip_prefix(&from)?;
+    if !rest.is_empty() && !rest.starts_with('/') {
+        return None;
+    }
+    let to = to.trim_end_matches(['/', '\\']);
+    // Keep using whatever separator the new prefix uses.
+    let separator = if to.contains('\\') && !to.contains('/') { "\\" } else { "/" };
+    Some(format!("{to}{}", rest.replace('/', separator)))
+}
+
+
+/// Rewrites every alias, ignore entry, games_dir and library under `from` to be under `to`.
+/// Returns the `(alias, old, new)` changes made to aliases.
+pub fn relocate(config: &mut Config, from: &str, to: &str) -> Vec<(String, String, String)> {
+    let mut changes = Vec::new();
+    for (alias, entry) in config.aliases.iter_mut() {
+        if let Some(new) = relocate_path(&entry.path, from, to) {
+            changes.push((alias.clone(), entry.path.clone(), new.clone()));
+            entry.path = new;
+        }
+    }
+    for path in config.autoadd_ignore.iter_mut() {
+        if let Some(new) = relocate_path(path, from, to) {
+            *path = new;
+        }
+    }
+    if let Some(new) = relocate_path(&config.games_dir, from, to) {
+        config.games_dir = new;
+    }
+    for root in config.libraries.values_mut() {
+        if let Some(new) = relocate_path(root, from, to) {
+            *root = new;
+        }
+    }
+    changes.sort();
+    changes
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use crate::Alias;
+
+    #[test]
+    fn relocates_paths_under_a_prefix() {
+        assert_eq!(relocate_path("/mnt/old/hk/hk.exe", "/mnt/old", "/mnt/new").as_deref(), Some("/mnt/new/hk/hk.exe"));
+        assert_eq!(relocate_path("/mnt/old", "/mnt/old/", "/mnt/new").as_deref(), Some("/mnt/new"));
+        assert_eq!(relocate_path("/mnt/older/hk.exe", "/mnt/old", "/mnt/new"), None);
+        assert_eq!(relocate_path("/games/hk.exe", "/mnt/old", "/mnt/new"), None);
+        assert_eq!(relocate_path("D:\\Games\\hk\\hk.exe", "D:/Games", "E:\\Games\\").as_deref(), Some("E:\\Games\\hk\\hk.exe"));
+    }
+
+    #[test]
+    fn relocates_everything_under_a_folder() {
+        let mut config: Config = toml::from_str(crate::DEFAULT_CONFIG).unwrap();
+        config.games_dir = String::from("/mnt/old");
+        config.aliases.insert(String::from("hk"), Alias::new(String::from("/mnt/old/hk/hk.exe")));
+        config.aliases.insert(String::from("sl"), Alias::new(String::from("/opt/sl/sl")));
+        config.autoadd_ignore = vec![String::from("/mnt/old/tools/setup.exe")];
+        config.libraries.insert(String::from("extra"), String::from("/mnt/old/extra"));
+
+        let changes = relocate(&mut config, "/mnt/old", "/mnt/new");
+        assert_eq!(changes, [(String::from("hk"), String::from("/mnt/old/hk/hk.exe"), String::from("/mnt/new/hk/hk.exe"))]);
+        assert_eq!(config.aliases["sl"].path, "/opt/sl/sl");
+        assert_eq!(config.autoadd_ignore, ["/mnt/new/tools/setup.exe"]);
+        assert_eq!(config.games_dir, "/mnt/new");
+        assert_eq!(config.libraries["extra"], "/mnt/new/extra");
+    }
+
+    #[test]
+    fn ranks_candidates_by_their_fingerprint() {
+        let dir = TestDir::new();
+        let fingerprints = dir.path().join("fingerprints.toml");
+        let old = dir.path().join("old/Game.exe");
+        fs::create_dir_all(old.parent().unwrap()).unwrap();
+        fs::write(&old, "version 1").unwrap();
+        let old = old.display().to_string();
+        record_fingerprint_in(&fingerprints, &old).unwrap();
+        // A game update changes the executable, the fingerprint follows.
+        fs::write(&old, "version 2").unwrap();
+        record_fingerprint_in(&fingerprints, &old).unwrap();
+        fs::remove_dir_all(dir.path().join("old")).unwrap();
+
+        let library = dir.path().join("library");
+        for (folder, contents) in [("moved", "version 2"), ("outdated", "version 1"), ("other", "something else")] {
+            fs::create_dir_all(library.join(folder)).unwrap();
+            fs::write(library.join(folder).join("game.EXE"), contents).unwrap();
+        }
+        fs::write(library.join("other/readme.txt"), "").unwrap();
+
+        let candidates: Vec<(PathBuf, Match)> = find_candidates_in(&fingerprints, &old, std::slice::from_ref(&library))
+            .into_iter()
+            .map(|candidate| (candidate.path, candidate.quality))
+            .collect();
+        assert_eq!(candidates, [
+            (library.join("moved/game.EXE"), Match::Hash),
+            (library.join("outdated/game.EXE"), Match::Size),
+            (library.join("other/game.EXE"), Match::Name)
+        ]);
+    }
+}
@@ -0,0 +1,498 @@
+use std::time::{UNIX_EPOCH, SystemTime};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+use std::fs;
+use std::io;
+
+use crate::{data_dir, sync, Config};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SaveSettings {
+    /// How many backups to keep per alias, older ones get deleted.
+    #[serde(default = "default_keep")]
+    pub keep: usize,
+    /// Back up the saves every time a game exits, unless the alias says otherwise.
+    #[serde(default)]
+    pub auto_backup: bool,
+    /// A folder shared between machines (Syncthing, Nextcloud, a NAS mount...) to mirror backups into.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync_dir: Option<String>,
+    /// What to do when the synced saves are newer or diverged before launching: `warn` or `prompt`.
+    #[serde(default = "default_on_conflict")]
+    pub on_conflict: String
+}
+
+impl Default for SaveSettings {
+    fn default() -> Self {
+        SaveSettings { keep: default_keep(), auto_backup: false, sync_dir: None, on_conflict: default_on_conflict() }
+    }
+}
+
+fn default_on_conflict() -> String {
+    String::from("warn")
+}
+
+fn default_keep() -> usize {
+    10
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct ManifestEntry {
+    /// Where the file lives on disk, starting with `${HOME}`, `${WINEPREFIX}` or `${game_dir}`
+    /// when possible so the manifest stays valid on other machines.
+    pub source: String,
+    /// Where the file is stored inside the archive.
+    pub archived: String,
+    pub size: u64,
+    /// Unix timestamp (seconds) of the last modification.
+    pub modified: u64,
+    pub sha256: String
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Manifest {
+    pub alias: String,
+    /// Local time of the backup, which is also its name.
+    pub created: String,
+    /// Unix timestamp (seconds) of the backup. Unlike `created` it doesn't depend on the time
+    /// zone, so it's what orders backups synced from other machines.
+    #[serde(default)]
+    pub created_at: u64,
+    pub files: Vec<ManifestEntry>
+}
+
+pub struct Backup {
+    pub timestamp: String,
+    pub archive: PathBuf,
+    pub manifest: Manifest
+}
+
+
+pub fn home_dir() -> String {
+    std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .unwrap_or_default()
+}
+
+
+/// Replaces `${name}` with the matching value from `vars`, or from the environment if
+/// there's no such variable. `${env:NAME}` always reads the environment.
+/// Unknown variables are left untouched.
+pub fn expand_vars<K: Borrow<str> + Hash + Eq>(path: &str, vars: &HashMap<K, String>) -> String {
+    let mut result = String::new();
+    let mut rest = path;
+    while let Some(start) = rest.find("${") {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + end];
+        result.push_str(&rest[..start]);
+        let value = match name.strip_prefix("env:") {
+            Some(name) => std::env::var(name).ok(),
+            None => vars.get(name).cloned().or_else(|| std::env::var(name).ok())
+        };
+        match value {
+            Some(value) => result.push_str(&value),
+            None => result.push_str(&rest[start..=start + end])
+        }
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+
+/// The opposite of `expand_vars`, replaces the longest matching variable prefix with `${name}`.
+pub fn portable_path<K: Borrow<str> + Hash + Eq>(path: &str, vars: &HashMap<K, String>) -> String {
+    let best = vars.iter()
+        .filter(|(_, value)| !value.is_empty() && path.starts_with(value.as_str()))
+        .filter(|(_, value)| path[value.len()..].is_empty() || path[value.len()..].starts_with(['/', '\\']))
+        .max_by_key(|(_, value)| value.len());
+    match best {
+        Some((name, value)) => format!("${{{}}}{}", name.borrow(), &path[value.len()..]),
+        None => path.to_owned()
+    }
+}
+
+
+pub fn save_vars(config: &Config, alias: &str) -> HashMap<&'static str, String> {
+    let home = home_dir();
+    let wineprefix = std::env::var("WINEPREFIX")
+        .unwrap_or_else(|_| Path::new(&home).join(".wine").display().to_string());
+    let game_dir = config.alias_path(alias)
+        .and_then(|path| Path::new(&path).parent().map(|dir| dir.display().to_string()))
+        .unwrap_or_default();
+
+    HashMap::from([
+        ("HOME", home),
+        ("WINEPREFIX", wineprefix),
+        ("game_dir", game_dir)
+    ])
+}
+
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            collect_files(&entry?.path(), files)?;
+        }
+    } else if path.is_file() {
+        files.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+
+/// Every file matched by the save locations of `alias`, with directories walked recursively.
+pub fn save_files(config: &Config, alias: &str) -> io::Result<Vec<PathBuf>> {
+    // The values are escaped so a `[` in a folder name isn't taken as a glob pattern.
+    let vars: HashMap<&str, String> = save_vars(config, alias).into_iter()
+        .map(|(name, value)| (name, glob::Pattern::escape(&value)))
+        .collect();
+    let mut files = Vec::new();
+    for location in &config.aliases[alias].saves {
+        let pattern = expand_vars(location, &vars);
+        let paths = glob::glob(&pattern).map_err(|err| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid save location `{location}`: {err}"))
+        })?;
+        for path in paths.flatten() {
+            collect_files(&path, &mut files)?;
+        }
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0; 65536];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize().iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+
+fn manifest_entry(path: &Path, archived: String, vars: &HashMap<&str, String>) -> io::Result<ManifestEntry> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata.modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0);
+    Ok(ManifestEntry {
+        source: portable_path(&path.display().to_string(), vars),
+        archived,
+        size: metadata.len(),
+        modified,
+        sha256: sha256_file(path)?
+    })
+}
+
+
+pub fn backups_dir(alias: &str) -> PathBuf {
+    data_dir().join("saves").join(alias)
+}
+
+
+/// Describes the save files of `alias` as they are on disk right now.
+pub fn current_manifest(config: &Config, alias: &str) -> io::Result<(Manifest, Vec<PathBuf>)> {
+    let vars = save_vars(config, alias);
+    let files = save_files(config, alias)?;
+    let now = chrono::Local::now();
+    let mut manifest = Manifest {
+        alias: alias.to_owned(),
+        created: now.format("%Y-%m-%d_%H-%M-%S").to_string(),
+        created_at: now.timestamp().max(0) as u64,
+        files: Vec::new()
+    };
+    for (idx, file) in files.iter().enumerate() {
+        let name = file.file_name().unwrap().to_string_lossy();
+        manifest.files.push(manifest_entry(file, format!("files/{idx}/{name}"), &vars)?);
+    }
+    Ok((manifest, files))
+}
+
+
+/// Packs every save file of `alias` into `saves/<alias>/<timestamp>.tar.gz`, writes the
+/// manifest next to it, prunes backups past `keep` and mirrors it to the sync folder.
+pub fn backup(config: &Config, alias: &str) -> io::Result<Backup> {
+    backup_into(config, alias, &backups_dir(alias))
+}
+
+
+/// `backup` with the local backups of `alias` in `dir`.
+fn backup_into(config: &Config, alias: &str, dir: &Path) -> io::Result<Backup> {
+    let (manifest, files) = current_manifest(config, alias)?;
+    if files.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("No save files found for `{alias}`")));
+    }
+
+    fs::create_dir_all(dir)?;
+    // Backups made within the same second, say an automatic and a manual one, get a number.
+    let mut n = 0;
+    let (timestamp, file) = loop {
+        let timestamp = if n == 0 { manifest.created.clone() } else { format!("{}_{n}", manifest.created) };
+        match File::options().write(true).create_new(true).open(dir.join(format!("{timestamp}.tar.gz"))) {
+            Ok(file) => break (timestamp, file),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => n += 1,
+            Err(err) => return Err(err)
+        }
+    };
+    let archive = dir.join(format!("{timestamp}.tar.gz"));
+
+    let mut builder = tar::Builder::new(GzEncoder::new(file, flate2::Compression::default()));
+    for (file, entry) in files.iter().zip(&manifest.files) {
+        builder.append_path_with_name(file, &entry.archived)?;
+    }
+
+    let contents = toml::to_string(&manifest).unwrap();
+    let mut header = tar::Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs());
+    header.set_cksum();
+    builder.append_data(&mut header, "manifest.toml", contents.as_bytes())?;
+    builder.into_inner()?.finish()?;
+
+    fs::write(dir.join(format!("{timestamp}.toml")), contents)?;
+    prune(dir, config.saves.keep)?;
+
+    let backup = Backup { timestamp, archive, manifest };
+    if let Some(sync_dir) = &config.saves.sync_dir {
+        sync::mirror(Path::new(sync_dir), &backup, config.saves.keep)?;
+    }
+    Ok(backup)
+}
+
+
+/// The name to store `backup` under in `dir`: its own, unless a different backup already has
+/// it there (made in the same second on another machine), then the first free `<name>_<n>`.
+pub fn free_backup_name(dir: &Path, backup: &Backup) -> io::Result<String> {
+    let taken = |name: &str| dir.join(format!("{name}.tar.gz"));
+    if !taken(&backup.timestamp).exists() || sha256_file(&taken(&backup.timestamp))? == sha256_file(&backup.archive)? {
+        return Ok(backup.timestamp.clone());
+    }
+    Ok((1..)
+        .map(|n| format!("{}_{n}", backup.timestamp))
+        .find(|name| !taken(name).exists())
+        .unwrap())
+}
+
+
+/// What backup names are ordered by: the timestamp, then the number backups made within
+/// the same second get, compared as a number so `_10` comes after `_9`.
+fn name_order(name: &str) -> (&str, u64) {
+    match name.rsplit_once('_').map(|(timestamp, n)| (timestamp, n.parse())) {
+        Some((timestamp, Ok(n))) => (timestamp, n),
+        _ => (name, 0)
+    }
+}
+
+
+/// Backups of `alias` from the oldest to the newest.
+pub fn list_backups(alias: &str) -> io::Result<Vec<Backup>> {
+    list_backups_in(&backups_dir(alias))
+}
+
+
+/// Backups stored in `dir` from the oldest to the newest. Backups from before manifests had
+/// `created_at` come first, among themselves in the order of their names.
+pub fn list_backups_in(dir: &Path) -> io::Result<Vec<Backup>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let Some(timestamp) = name.strip_suffix(".tar.gz") else {
+            continue;
+        };
+        let manifest = match fs::read_to_string(dir.join(format!("{timestamp}.toml"))) {
+            Ok(contents) => toml::from_str(&contents)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?,
+            Err(_) => read_archived_manifest(&path)?
+        };
+        backups.push(Backup { timestamp: timestamp.to_owned(), archive: path, manifest });
+    }
+    backups.sort_by(|a, b| {
+        (a.manifest.created_at, name_order(&a.timestamp)).cmp(&(b.manifest.created_at, name_order(&b.timestamp)))
+    });
+    Ok(backups)
+}
+
+
+/// Reads the manifest stored inside an archive, in case the copy next to it got lost.
+fn read_archived_manifest(archive: &Path) -> io::Result<Manifest> {
+    let mut tar = tar::Archive::new(GzDecoder::new(File::open(archive)?));
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.as_ref() == Path::new("manifest.toml") {
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents)?;
+            return toml::from_str(&contents).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err));
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::InvalidData, format!("`{}` has no manifest", archive.display())))
+}
+
+
+pub fn prune(dir: &Path, keep: usize) -> io::Result<()> {
+    let backups = list_backups_in(dir)?;
+    let excess = backups.len().saturating_sub(keep.max(1));
+    for backup in &backups[..excess] {
+        fs::remove_file(&backup.archive)?;
+        let manifest = backup.archive.with_file_name(format!("{}.toml", backup.timestamp));
+        if manifest.exists() {
+            fs::remove_file(manifest)?;
+        }
+    }
+    Ok(())
+}
+
+
+/// Picks the newest backup, or the newest one whose timestamp starts with `at`.
+pub fn find_backup(alias: &str, at: Option<&str>) -> io::Result<Backup> {
+    let backups = list_backups(alias)?;
+    backups.into_iter()
+        .rev()
+        .find(|backup| at.is_none_or(|at| backup.timestamp.starts_with(at)))
+        .ok_or_else(|| match at {
+            Some(at) => io::Error::new(io::ErrorKind::NotFound, format!("No backup of `{alias}` matches `{at}`")),
+            None => io::Error::new(io::ErrorKind::NotFound, format!("`{alias}` has no backups"))
+        })
+}
+
+
+/// Extracts every file of `backup` back to where it was backed up from.
+pub fn restore(config: &Config, alias: &str, backup: &Backup) -> io::Result<()> {
+    let vars = save_vars(config, alias);
+    let targets: HashMap<&str, String> = backup.manifest.files.iter()
+        .map(|file| (file.archived.as_str(), expand_vars(&file.source, &vars)))
+        .collect();
+
+    let mut tar = tar::Archive::new(GzDecoder::new(File::open(&backup.archive)?));
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let archived = entry.path()?.to_string_lossy().into_owned();
+        let Some(source) = targets.get(archived.as_str()) else {
+            continue;
+        };
+        let source = Path::new(source.as_str());
+        if let Some(parent) = source.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        entry.unpack(source)?;
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use crate::Alias;
+
+    /// A config with the alias `game` for `<dir>/game/game.exe`, which saves into `<dir>/game/saves`.
+    fn game(dir: &Path) -> Config {
+        fs::create_dir_all(dir.join("game/saves/slot1")).unwrap();
+        fs::write(dir.join("game/saves/slot1/save.dat"), "level 3").unwrap();
+        fs::write(dir.join("game/saves/settings.ini"), "volume=5").unwrap();
+        let mut config: Config = toml::from_str(crate::DEFAULT_CONFIG).unwrap();
+        let entry = Alias {
+            saves: vec![String::from("${game_dir}/saves")],
+            ..Alias::new(dir.join("game/game.exe").display().to_string())
+        };
+        config.aliases.insert(String::from("game"), entry);
+        config
+    }
+
+    #[test]
+    fn expands_known_variables_only() {
+        let vars = HashMap::from([("HOME", String::from("/home/me")), ("game_dir", String::from("/games/hk"))]);
+        assert_eq!(expand_vars("${HOME}/.local/share/${game_dir}", &vars), "/home/me/.local/share//games/hk");
+        assert_eq!(expand_vars("${nope}/saves/${HOME}", &vars), "${nope}/saves//home/me");
+        assert_eq!(expand_vars("${env:PATH}", &vars), std::env::var("PATH").unwrap());
+        assert_eq!(expand_vars("${env:PLZ_SURELY_UNSET}/x", &vars), "${env:PLZ_SURELY_UNSET}/x");
+        assert_eq!(expand_vars("${HOME", &vars), "${HOME");
+    }
+
+    #[test]
+    fn makes_paths_portable() {
+        let mut config: Config = toml::from_str(crate::DEFAULT_CONFIG).unwrap();
+        config.games_dir = String::from("/games/");
+        config.libraries.insert(String::from("ssd"), String::from("/games/ssd"));
+        let vars = config.path_vars();
+        // The longest prefix wins, and only whole folder names count.
+        assert_eq!(portable_path("/games/ssd/hk/hk.exe", &vars), "${library:ssd}/hk/hk.exe");
+        assert_eq!(portable_path("/games/sl/sl", &vars), "${games_dir}/sl/sl");
+        assert_eq!(portable_path("/games/ssd2/hk.exe", &vars), "${games_dir}/ssd2/hk.exe");
+        assert_eq!(portable_path("/opt/hk.exe", &vars), "/opt/hk.exe");
+
+        config.aliases.insert(String::from("hk"), Alias::new(portable_path("/games/ssd/hk/hk.exe", &vars)));
+        config.libraries.insert(String::from("ssd"), String::from("/mnt/ssd"));
+        assert_eq!(config.alias_path("hk").as_deref(), Some("/mnt/ssd/hk/hk.exe"));
+        assert_eq!(config.alias_path("sl"), None);
+    }
+
+    #[test]
+    fn restores_what_was_backed_up() {
+        let dir = TestDir::new();
+        let config = game(dir.path());
+        let saves = dir.path().join("game/saves");
+        let backups = dir.path().join("backups");
+
+        let backup = backup_into(&config, "game", &backups).unwrap();
+        let sources: Vec<&str> = backup.manifest.files.iter().map(|file| file.source.as_str()).collect();
+        assert_eq!(sources, ["${game_dir}/saves/settings.ini", "${game_dir}/saves/slot1/save.dat"]);
+        fs::write(saves.join("slot1/save.dat"), "level 1").unwrap();
+        fs::remove_file(saves.join("settings.ini")).unwrap();
+        // Another one right away gets a name of its own and is the newest.
+        let second = backup_into(&config, "game", &backups).unwrap();
+        assert_ne!(second.timestamp, backup.timestamp);
+
+        let listed = list_backups_in(&backups).unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[1].timestamp, second.timestamp);
+        restore(&config, "game", &listed[0]).unwrap();
+        assert_eq!(fs::read_to_string(saves.join("slot1/save.dat")).unwrap(), "level 3");
+        assert_eq!(fs::read_to_string(saves.join("settings.ini")).unwrap(), "volume=5");
+    }
+
+    #[test]
+    fn prunes_all_but_the_newest_backups() {
+        let dir = TestDir::new();
+        let names = ["2026-01-01_09-59-59", "2026-01-01_10-00-00"].map(String::from).into_iter()
+            .chain((1..=10).map(|n| format!("2026-01-01_10-00-00_{n}")));
+        for name in names {
+            let manifest = Manifest { alias: String::from("game"), created: name.clone(), created_at: 0, files: Vec::new() };
+            fs::write(dir.path().join(format!("{name}.tar.gz")), "").unwrap();
+            fs::write(dir.path().join(format!("{name}.toml")), toml::to_string(&manifest).unwrap()).unwrap();
+        }
+
+        prune(dir.path(), 3).unwrap();
+        let left: Vec<String> = list_backups_in(dir.path()).unwrap().into_iter().map(|backup| backup.timestamp).collect();
+        assert_eq!(left, ["2026-01-01_10-00-00_8", "2026-01-01_10-00-00_9", "2026-01-01_10-00-00_10"]);
+        // The manifests next to them went too.
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 6);
+    }
+}
@@ -0,0 +1,64 @@
+use std::path::{Path, PathBuf};
+use std::fs;
+use std::io;
+
+use crate::Config;
+
+/// Uninstallers and crash handlers that ship next to games but are never what you want to run.
+const EXECUTABLE_BLACKLIST: [&str; 3] = ["unins000.exe", "UnityCrashHandler64.exe", "UnityCrashHandler32.exe"];
+
+fn is_executable(path: &Path) -> bool {
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    path.is_file() && name.ends_with(".exe") && !EXECUTABLE_BLACKLIST.contains(&name)
+}
+
+
+/// Whether `path` already has an alias or was skipped by an earlier autoadd.
+pub fn is_known(config: &Config, path: &Path) -> bool {
+    let path = path.display().to_string();
+    config.autoadd_ignore.contains(&path) || config.aliases.values().any(|alias| config.expand_path(&alias.path) == path)
+}
+
+
+/// The executables in `folder`, or if there are none, the ones found in its subfolders.
+fn search(folder: &Path, found: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut executables = Vec::new();
+    let mut folders = Vec::new();
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+        if is_executable(&path) {
+            executables.push(path);
+        } else if path.is_dir() {
+            folders.push(path);
+        }
+    }
+
+    if !executables.is_empty() {
+        found.extend(executables);
+    } else {
+        for folder in folders {
+            search(&folder, found)?;
+        }
+    }
+    Ok(())
+}
+
+
+/// Executables in `games_dir` that don't have an alias yet and weren't skipped before,
+/// for `alias autoadd`. Every game folder is searched until a level with executables is found.
+pub fn find_new_executables(config: &Config) -> io::Result<Vec<PathBuf>> {
+    if config.games_dir.is_empty() {
+        return Err(io::Error::other("games_dir is empty, please set it first."));
+    }
+    let mut found = Vec::new();
+    for entry in fs::read_dir(&config.games_dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            search(&path, &mut found)?;
+        } else if is_executable(&path) {
+            found.push(path);
+        }
+    }
+    found.retain(|path| !is_known(config, path));
+    Ok(found)
+}
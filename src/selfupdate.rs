@@ -0,0 +1,300 @@
+use flate2::read::GzDecoder;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use sha2::{Digest, Sha256};
+use std::io::{Cursor, Read};
+use crate::http::Client;
+use std::fs;
+use std::io;
+
+use crate::updates::{Asset, Release};
+
+/// Names this OS goes by in release asset names.
+fn os_names() -> Vec<&'static str> {
+    match std::env::consts::OS {
+        "windows" => vec!["windows"],
+        "macos" => vec!["macos", "darwin", "apple"],
+        other => vec![other]
+    }
+}
+
+
+/// Names this architecture goes by in release asset names.
+fn arch_names() -> Vec<&'static str> {
+    match std::env::consts::ARCH {
+        "x86_64" => vec!["x86_64", "amd64", "x64"],
+        "aarch64" => vec!["aarch64", "arm64"],
+        "x86" => vec!["i686", "i386", "x86"],
+        other => vec![other]
+    }
+}
+
+
+fn is_checksum(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.ends_with(".sha256") || name.contains("sha256sums") || name.contains("checksums")
+}
+
+
+/// The asset built for this OS and architecture. A Windows `.exe` counts as built for
+/// Windows even without the OS in its name, and an asset without an architecture in
+/// its name only counts if it's the only one for this OS.
+pub fn pick_asset(assets: &[Asset]) -> Option<&Asset> {
+    let for_os: Vec<&Asset> = assets.iter()
+        .filter(|asset| !is_checksum(&asset.name))
+        .filter(|asset| {
+            let name = asset.name.to_lowercase();
+            os_names().iter().any(|os| name.contains(os)) || (cfg!(windows) && name.ends_with(".exe"))
+        })
+        .collect();
+    let for_arch = for_os.iter().find(|asset| {
+        let name = asset.name.to_lowercase();
+        arch_names().iter().any(|arch| name.contains(arch))
+    });
+    match (for_arch, for_os.as_slice()) {
+        (Some(asset), _) => Some(asset),
+        (None, [only]) => Some(only),
+        _ => None
+    }
+}
+
+
+/// Finds the published SHA-256 of `asset`, either in `<asset>.sha256` or in a
+/// `SHA256SUMS`/`checksums.txt` style list.
+pub async fn published_checksum(client: &Client, release: &Release, asset: &Asset) -> Result<String, String> {
+    let own = format!("{}.sha256", asset.name);
+    let source = release.assets.iter().find(|candidate| candidate.name == own)
+        .or_else(|| release.assets.iter().find(|candidate| is_checksum(&candidate.name)))
+        .ok_or_else(|| format!("The release doesn't publish a SHA-256 checksum for `{}`", asset.name))?;
+    let text = String::from_utf8_lossy(&download(client, &source.browser_download_url).await?).into_owned();
+    parse_checksum(&text, &asset.name)
+        .ok_or_else(|| format!("`{}` has no checksum for `{}`", source.name, asset.name))
+}
+
+
+/// Reads `sha256sum` output, or a file with nothing but the hash in it.
+pub fn parse_checksum(text: &str, name: &str) -> Option<String> {
+    let is_hash = |word: &str| word.len() == 64 && word.chars().all(|c| c.is_ascii_hexdigit());
+    let mut words = text.split_whitespace();
+    if let (Some(hash), None) = (words.next(), words.next()) {
+        return Some(hash.to_lowercase()).filter(|hash| is_hash(hash));
+    }
+    text.lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(_, file)| file.trim().trim_start_matches('*') == name)
+        .map(|(hash, _)| hash.to_lowercase())
+        .filter(|hash| is_hash(hash))
+}
+
+
+pub async fn download(client: &Client, url: &str) -> Result<Vec<u8>, String> {
+    let res = client.download(url).await.map_err(|err| err.to_string())?;
+    if !res.status().is_success() {
+        return Err(format!("Downloading `{}` failed with {}", url, res.status()));
+    }
+    res.bytes().await.map(|bytes| bytes.to_vec()).map_err(|err| err.to_string())
+}
+
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+
+fn exe_name() -> &'static str {
+    if cfg!(windows) { "plz.exe" } else { "plz" }
+}
+
+
+/// The plz binary inside `asset`, which can also be a `.zip` or `.tar.gz`.
+/// Nothing else in an archive is used, so a config shipped with it can't replace yours.
+pub fn extract_binary(name: &str, bytes: Vec<u8>) -> Result<Vec<u8>, String> {
+    let name = name.to_lowercase();
+    let missing = || format!("`{}` is not in the archive", exe_name());
+    if name.ends_with(".zip") {
+        let mut zip = zip::ZipArchive::new(Cursor::new(bytes)).map_err(|err| err.to_string())?;
+        for idx in 0..zip.len() {
+            let mut file = zip.by_index(idx).map_err(|err| err.to_string())?;
+            if file.enclosed_name().is_some_and(|path| path.file_name().is_some_and(|file| file == exe_name())) {
+                let mut binary = Vec::new();
+                file.read_to_end(&mut binary).map_err(|err| err.to_string())?;
+                return Ok(binary);
+            }
+        }
+        Err(missing())
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        let mut tar = tar::Archive::new(GzDecoder::new(Cursor::new(bytes)));
+        for entry in tar.entries().map_err(|err| err.to_string())? {
+            let mut entry = entry.map_err(|err| err.to_string())?;
+            if entry.path().ok().and_then(|path| path.file_name().map(|file| file == exe_name())).unwrap_or(false) {
+                let mut binary = Vec::new();
+                entry.read_to_end(&mut binary).map_err(|err| err.to_string())?;
+                return Ok(binary);
+            }
+        }
+        Err(missing())
+    } else {
+        Ok(bytes)
+    }
+}
+
+
+fn rollback_path(exe: &Path) -> PathBuf {
+    exe.with_file_name(format!("{}.old", exe.file_name().unwrap().to_string_lossy()))
+}
+
+
+/// Replaces the executable at `exe` with `binary`, keeping the previous one next to it
+/// as `<name>.old`. Only the executable is touched, the config and data next to it stay.
+pub fn install(exe: &Path, binary: &[u8]) -> io::Result<PathBuf> {
+    let new = exe.with_file_name(format!(".{}.new", exe.file_name().unwrap().to_string_lossy()));
+    let old = rollback_path(exe);
+    fs::write(&new, binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&new, fs::Permissions::from_mode(0o755))?;
+    }
+
+    // Make sure it runs on this machine before it takes over.
+    let works = Command::new(&new).arg("--version").output().is_ok_and(|output| output.status.success());
+    if !works {
+        fs::remove_file(&new).ok();
+        return Err(io::Error::other("The new version doesn't run on this machine"));
+    }
+
+    // Windows can't overwrite a running executable but it can rename it, so the
+    // rename doubles as the rollback copy there. Elsewhere the rename over it is atomic.
+    if cfg!(windows) {
+        if old.exists() {
+            fs::remove_file(&old)?;
+        }
+        fs::rename(exe, &old)?;
+    } else {
+        fs::copy(exe, &old)?;
+    }
+    if let Err(err) = fs::rename(&new, exe) {
+        if cfg!(windows) {
+            fs::rename(&old, exe).ok();
+        }
+        fs::remove_file(&new).ok();
+        return Err(err);
+    }
+    Ok(old)
+}
+
+
+/// Puts the rollback copy back in place, keeping the current version as the new rollback copy.
+pub fn rollback(exe: &Path) -> io::Result<PathBuf> {
+    let old = rollback_path(exe);
+    if !old.is_file() {
+        return Err(io::Error::other(format!("There's no previous version at `{}`", old.display())));
+    }
+    install(exe, &fs::read(&old)?)
+}
+
+
+/// Downloads `asset` and checks it against the checksum published with the release,
+/// returning the plz binary in it.
+pub async fn download_verified(client: &Client, release: &Release, asset: &Asset) -> Result<Vec<u8>, String> {
+    let expected = published_checksum(client, release, asset).await?;
+    let bytes = download(client, &asset.browser_download_url).await?;
+    let actual = sha256_hex(&bytes);
+    if actual != expected {
+        return Err(format!("Checksum mismatch for `{}`, expected {} but got {}", asset.name, expected, actual));
+    }
+    extract_binary(&asset.name, bytes)
+}
+
+
+/// Replaces the executable at `exe` with the binary from `asset`, once it matches its
+/// published checksum. Returns where the previous version is kept, like `install`.
+pub async fn update(client: &Client, release: &Release, asset: &Asset, exe: &Path) -> Result<PathBuf, String> {
+    let binary = download_verified(client, release, asset).await?;
+    install(exe, &binary).map_err(|err| format!("Failed to replace `{}`. {}", exe.display(), err))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{self, HttpSettings};
+    use crate::test_dir::TestDir;
+    use crate::test_server;
+
+    fn asset(name: &str) -> Asset {
+        Asset { name: name.to_owned(), browser_download_url: String::new() }
+    }
+
+    #[test]
+    fn picks_the_asset_for_this_platform() {
+        let ours = format!("plz-{}-{}.tar.gz", os_names()[0], arch_names()[0]);
+        let assets = vec![
+            asset("plz-plan9-mips.tar.gz"),
+            asset(&format!("{ours}.sha256")),
+            asset(&ours)
+        ];
+        assert_eq!(pick_asset(&assets).unwrap().name, ours);
+        assert!(pick_asset(&[asset("plz-plan9-mips.tar.gz")]).is_none());
+    }
+
+    #[test]
+    fn parses_checksum_files() {
+        let hash = "ab".repeat(32);
+        assert_eq!(parse_checksum(&format!("{hash}\n"), "plz.tar.gz"), Some(hash.clone()));
+        let list = format!("{}  other.zip\n{hash} *plz.tar.gz\n", "cd".repeat(32));
+        assert_eq!(parse_checksum(&list, "plz.tar.gz"), Some(hash));
+        assert_eq!(parse_checksum(&list, "missing.zip"), None);
+        assert_eq!(parse_checksum("not a hash", "plz.tar.gz"), None);
+    }
+
+    #[test]
+    fn extracts_only_the_binary() {
+        let mut archive = tar::Builder::new(flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default()));
+        for (name, contents) in [("plz/config.toml", &b"games_dir = \"\""[..]), (&format!("plz/{}", exe_name()), &b"binary"[..])] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o755);
+            archive.append_data(&mut header, name, contents).unwrap();
+        }
+        let bytes = archive.into_inner().unwrap().finish().unwrap();
+        assert_eq!(extract_binary("plz.tar.gz", bytes).unwrap(), b"binary");
+    }
+
+    #[tokio::test]
+    async fn verifies_downloads_against_the_published_checksum() {
+        // Passes the check in `install` that it runs, without being a real plz.
+        let binary = b"#!/bin/sh\nexit 0\n".to_vec();
+        let sums = format!("{0}  plz-linux\n{0}  plz-tampered\n", sha256_hex(&binary));
+        let base = test_server::serve(vec![
+            ("/plz-linux", 200, binary.clone()),
+            ("/plz-tampered", 200, b"#!/bin/sh\nexit 1\n".to_vec()),
+            ("/SHA256SUMS", 200, sums.into_bytes())
+        ]).await;
+        let asset = |name: &str| Asset { name: name.to_owned(), browser_download_url: format!("{base}/{name}") };
+        let release = Release {
+            tag_name: String::from("v9.9.9"),
+            draft: false,
+            assets: vec![asset("plz-linux"), asset("plz-tampered"), asset("SHA256SUMS")]
+        };
+        let client = http::client(&HttpSettings::default()).unwrap();
+        assert_eq!(download_verified(&client, &release, &release.assets[0]).await.unwrap(), binary);
+
+        let dir = TestDir::new();
+        let exe = dir.path().join("plz");
+        fs::write(&exe, "old plz").unwrap();
+        let err = update(&client, &release, &release.assets[1], &exe).await.unwrap_err();
+        assert!(err.starts_with("Checksum mismatch for `plz-tampered`"), "{err}");
+        let files: Vec<_> = fs::read_dir(dir.path()).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        assert_eq!(files, ["plz"]);
+        assert_eq!(fs::read(&exe).unwrap(), b"old plz");
+
+        #[cfg(unix)]
+        {
+            let old = update(&client, &release, &release.assets[0], &exe).await.unwrap();
+            assert_eq!(fs::read(&exe).unwrap(), binary);
+            assert_eq!(fs::read(old).unwrap(), b"old plz");
+        }
+        assert!(download(&client, &format!("{base}/missing")).await.is_err());
+    }
+}
@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::fs;
+use std::io;
+
+use crate::saves::{self, Backup, Manifest};
+use crate::Config;
+
+#[derive(Debug, PartialEq)]
+pub enum SyncStatus {
+    /// The local saves match the newest synced snapshot.
+    InSync,
+    /// Nothing has been synced for this alias yet.
+    NotSynced,
+    /// The local saves changed since the last snapshot, the next backup will sync them.
+    LocalNewer,
+    /// Another machine synced newer saves and the local ones haven't changed since.
+    SyncNewer,
+    /// Both the local and the synced saves changed since they last matched.
+    Diverged
+}
+
+
+pub fn alias_sync_dir(sync_dir: &Path, alias: &str) -> PathBuf {
+    sync_dir.join(alias)
+}
+
+
+/// Copies a backup into `<sync_dir>/<alias>/` and prunes old snapshots there.
+pub fn mirror(sync_dir: &Path, backup: &Backup, keep: usize) -> io::Result<()> {
+    let dir = alias_sync_dir(sync_dir, &backup.manifest.alias);
+    fs::create_dir_all(&dir)?;
+    let name = saves::free_backup_name(&dir, backup)?;
+    let manifest = backup.archive.with_file_name(format!("{}.toml", backup.timestamp));
+    // Copy into a temporary name first so a sync client never picks up half a file.
+    for (source, extension) in [(&backup.archive, "tar.gz"), (&manifest, "toml")] {
+        let partial = dir.join(format!(".{name}.{extension}.partial"));
+        fs::copy(source, &partial)?;
+        fs::rename(&partial, dir.join(format!("{name}.{extension}")))?;
+    }
+    saves::prune(&dir, keep)
+}
+
+
+/// Newest snapshot in the sync folder for `alias`.
+pub fn latest_synced(sync_dir: &Path, alias: &str) -> io::Result<Option<Backup>> {
+    Ok(saves::list_backups_in(&alias_sync_dir(sync_dir, alias))?.pop())
+}
+
+
+fn same_files(a: &Manifest, b: &Manifest) -> bool {
+    let hashes = |manifest: &Manifest| -> HashMap<String, String> {
+        manifest.files.iter().map(|file| (file.source.clone(), file.sha256.clone())).collect()
+    };
+    hashes(a) == hashes(b)
+}
+
+
+/// Compares the saves on disk with the newest synced snapshot, using the last local
+/// backup as the common ancestor. Without one, the modification times decide.
+pub fn compare(current: &Manifest, last_local: Option<&Manifest>, synced: &Manifest) -> SyncStatus {
+    if same_files(current, synced) {
+        return SyncStatus::InSync;
+    }
+
+    if let Some(last_local) = last_local {
+        let local_changed = !same_files(current, last_local);
+        let sync_changed = !same_files(synced, last_local);
+        return match (local_changed, sync_changed) {
+            (false, _) => SyncStatus::SyncNewer,
+            (true, false) => SyncStatus::LocalNewer,
+            (true, true) => SyncStatus::Diverged
+        };
+    }
+
+    let local: HashMap<&str, (&str, u64)> = current.files.iter()
+        .map(|file| (file.source.as_str(), (file.sha256.as_str(), file.modified)))
+        .collect();
+    let mut sync_newer = false;
+    let mut local_newer = current.files.iter()
+        .any(|file| !synced.files.iter().any(|synced| synced.source == file.source));
+    for file in &synced.files {
+        match local.get(file.source.as_str()) {
+            Some((sha256, _)) if *sha256 == file.sha256 => {},
+            Some((_, modified)) if *modified > file.modified => local_newer = true,
+            _ => sync_newer = true
+        }
+    }
+    match (local_newer, sync_newer) {
+        (false, true) => SyncStatus::SyncNewer,
+        (true, false) => SyncStatus::LocalNewer,
+        _ => SyncStatus::Diverged
+    }
+}
+
+
+/// Sync status of `alias`, or `None` if there's no sync folder or no save locations.
+pub fn status(config: &Config, alias: &str) -> io::Result<Option<(SyncStatus, Option<Backup>)>> {
+    let Some(sync_dir) = &config.saves.sync_dir else {
+        return Ok(None);
+    };
+    if config.aliases[alias].saves.is_empty() {
+        return Ok(None);
+    }
+    let Some(synced) = latest_synced(Path::new(sync_dir), alias)? else {
+        return Ok(Some((SyncStatus::NotSynced, None)));
+    };
+    let (current, _) = saves::current_manifest(config, alias)?;
+    let last_local = saves::list_backups(alias)?.pop();
+    let status = compare(&current, last_local.as_ref().map(|backup| &backup.manifest), &synced.manifest);
+    Ok(Some((status, Some(synced))))
+}
+
+
+/// Copies the newest synced snapshot into the local backups and restores it.
+pub fn pull(config: &Config, alias: &str, synced: &Backup) -> io::Result<()> {
+    pull_into(config, alias, synced, &saves::backups_dir(alias))
+}
+
+
+/// `pull` with the local backups of `alias` in `dir`.
+fn pull_into(config: &Config, alias: &str, synced: &Backup, dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let name = saves::free_backup_name(dir, synced)?;
+    let manifest = synced.archive.with_file_name(format!("{}.toml", synced.timestamp));
+    for (source, extension) in [(&synced.archive, "tar.gz"), (&manifest, "toml")] {
+        if source.exists() {
+            fs::copy(source, dir.join(format!("{name}.{extension}")))?;
+        }
+    }
+    saves::restore(config, alias, synced)?;
+    saves::prune(dir, config.saves.keep)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use crate::Alias;
+    use flate2::write::GzEncoder;
+
+    fn manifest(alias: &str, files: &[(&str, &str, u64)]) -> Manifest {
+        let files = files.iter().enumerate()
+            .map(|(idx, (source, sha256, modified))| saves::ManifestEntry {
+                source: source.to_string(),
+                archived: format!("files/{idx}/save.dat"),
+                size: sha256.len() as u64,
+                modified: *modified,
+                sha256: sha256.to_string()
+            })
+            .collect();
+        Manifest { alias: alias.to_owned(), created: String::new(), created_at: 0, files }
+    }
+
+    /// A backup of `alias` in `dir` made at `created_at` with a single save file
+    /// `${game_dir}/save.dat` that has `hash` as its hash and its contents.
+    fn backup_in(dir: &Path, alias: &str, timestamp: &str, created_at: u64, hash: &str) -> Backup {
+        fs::create_dir_all(dir).unwrap();
+        let manifest = Manifest {
+            created: timestamp.to_owned(),
+            created_at,
+            ..manifest(alias, &[("${game_dir}/save.dat", hash, 0)])
+        };
+        let archive = dir.join(format!("{timestamp}.tar.gz"));
+        let mut builder = tar::Builder::new(GzEncoder::new(fs::File::create(&archive).unwrap(), flate2::Compression::default()));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(hash.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, "files/0/save.dat", hash.as_bytes()).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+        fs::write(dir.join(format!("{timestamp}.toml")), toml::to_string(&manifest).unwrap()).unwrap();
+        Backup { timestamp: timestamp.to_owned(), archive, manifest }
+    }
+
+    fn synced_names(sync_dir: &Path, alias: &str) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(alias_sync_dir(sync_dir, alias)).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn mirrors_backups_and_prunes_old_ones() {
+        let tmp = TestDir::new();
+        let sync_dir = tmp.path().join("sync");
+        assert!(latest_synced(&sync_dir, "hk").unwrap().is_none());
+
+        let backups = [
+            ("2024-06-20_12-00-00", 1718884800, "aaa"),
+            ("2024-06-21_12-00-00", 1718971200, "bbb"),
+            ("2024-06-22_12-00-00", 1719057600, "ccc")
+        ];
+        for (timestamp, created_at, hash) in backups {
+            let backup = backup_in(&tmp.path().join("local"), "hk", timestamp, created_at, hash);
+            mirror(&sync_dir, &backup, 2).unwrap();
+        }
+        assert_eq!(synced_names(&sync_dir, "hk"), [
+            "2024-06-21_12-00-00.tar.gz", "2024-06-21_12-00-00.toml",
+            "2024-06-22_12-00-00.tar.gz", "2024-06-22_12-00-00.toml"
+        ]);
+        let latest = latest_synced(&sync_dir, "hk").unwrap().unwrap();
+        assert_eq!(latest.timestamp, "2024-06-22_12-00-00");
+        assert_eq!(latest.manifest.files[0].sha256, "ccc");
+    }
+
+    #[test]
+    fn keeps_backups_from_the_same_second_apart() {
+        let tmp = TestDir::new();
+        let sync_dir = tmp.path().join("sync");
+        let desktop = backup_in(&tmp.path().join("desktop"), "hk", "2024-06-20_12-00-00", 1718884800, "aaa");
+        let laptop = backup_in(&tmp.path().join("laptop"), "hk", "2024-06-20_12-00-00", 1718884800, "bbb");
+
+        mirror(&sync_dir, &desktop, 10).unwrap();
+        mirror(&sync_dir, &laptop, 10).unwrap();
+        // Mirroring the same backup again doesn't make another copy.
+        mirror(&sync_dir, &desktop, 10).unwrap();
+        let synced: Vec<String> = saves::list_backups_in(&alias_sync_dir(&sync_dir, "hk")).unwrap()
+            .into_iter()
+            .map(|backup| backup.timestamp)
+            .collect();
+        assert_eq!(synced, ["2024-06-20_12-00-00", "2024-06-20_12-00-00_1"]);
+        assert_eq!(latest_synced(&sync_dir, "hk").unwrap().unwrap().manifest.files[0].sha256, "bbb");
+    }
+
+    #[test]
+    fn picks_the_latest_snapshot_regardless_of_time_zones() {
+        let tmp = TestDir::new();
+        let sync_dir = tmp.path().join("sync");
+        // 21:00 in Tokyo, then an hour later 09:00 in New York.
+        let tokyo = backup_in(&tmp.path().join("tokyo"), "hk", "2024-06-20_21-00-00", 1718884800, "aaa");
+        let new_york = backup_in(&tmp.path().join("new-york"), "hk", "2024-06-20_09-00-00", 1718888400, "bbb");
+
+        mirror(&sync_dir, &tokyo, 10).unwrap();
+        mirror(&sync_dir, &new_york, 10).unwrap();
+        let latest = latest_synced(&sync_dir, "hk").unwrap().unwrap();
+        assert_eq!(latest.timestamp, "2024-06-20_09-00-00");
+        assert_eq!(latest.manifest.files[0].sha256, "bbb");
+        // Pruning goes by the same order.
+        mirror(&sync_dir, &tokyo, 1).unwrap();
+        assert_eq!(synced_names(&sync_dir, "hk"), ["2024-06-20_09-00-00.tar.gz", "2024-06-20_09-00-00.toml"]);
+    }
+
+    #[test]
+    fn compares_with_the_last_local_backup() {
+        let old = manifest("hk", &[("${HOME}/save.dat", "old", 100)]);
+        let new = manifest("hk", &[("${HOME}/save.dat", "new", 200)]);
+        let other = manifest("hk", &[("${HOME}/save.dat", "other", 300)]);
+
+        assert_eq!(compare(&new, Some(&old), &new), SyncStatus::InSync);
+        assert_eq!(compare(&new, Some(&old), &old), SyncStatus::LocalNewer);
+        assert_eq!(compare(&old, Some(&old), &new), SyncStatus::SyncNewer);
+        assert_eq!(compare(&new, Some(&old), &other), SyncStatus::Diverged);
+    }
+
+    #[test]
+    fn compares_modification_times_without_a_local_backup() {
+        let old = manifest("hk", &[("${HOME}/save.dat", "old", 100)]);
+        let new = manifest("hk", &[("${HOME}/save.dat", "new", 200)]);
+        let more = manifest("hk", &[("${HOME}/save.dat", "old", 100), ("${HOME}/extra.dat", "extra", 50)]);
+
+        assert_eq!(compare(&old, None, &old), SyncStatus::InSync);
+        assert_eq!(compare(&new, None, &old), SyncStatus::LocalNewer);
+        assert_eq!(compare(&old, None, &new), SyncStatus::SyncNewer);
+        assert_eq!(compare(&more, None, &old), SyncStatus::LocalNewer);
+        assert_eq!(compare(&new, None, &more), SyncStatus::Diverged);
+    }
+
+    #[test]
+    fn pulls_the_synced_saves() {
+        let tmp = TestDir::new();
+        let game_dir = tmp.path().join("game");
+        let mut config: Config = toml::from_str(crate::DEFAULT_CONFIG).unwrap();
+        config.aliases.insert(String::from("hk"), Alias::new(game_dir.join("hk.exe").display().to_string()));
+        let sync_dir = tmp.path().join("sync");
+        mirror(&sync_dir, &backup_in(&tmp.path().join("laptop"), "hk", "2024-06-20_12-00-00", 1718884800, "from the laptop"), 10).unwrap();
+
+        let local = tmp.path().join("local");
+        let synced = latest_synced(&sync_dir, "hk").unwrap().unwrap();
+        pull_into(&config, "hk", &synced, &local).unwrap();
+        assert_eq!(fs::read_to_string(game_dir.join("save.dat")).unwrap(), "from the laptop");
+        let pulled = saves::list_backups_in(&local).unwrap();
+        assert_eq!(pulled.len(), 1);
+        assert_eq!(pulled[0].timestamp, "2024-06-20_12-00-00");
+    }
+}
@@ -0,0 +1,53 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::selfupdate::sha256_hex;
+
+/// Serves `(path, status, body)` routes over HTTP until the test ends, standing in for
+/// remote APIs. A route with a query only matches that exact query, one without matches any.
+/// Unknown paths get a 404. Every answer has an `ETag`, and a request with a
+/// matching `If-None-Match` gets a 304. Returns the base URL, like `http://127.0.0.1:1234`.
+pub async fn serve(routes: Vec<(&str, u16, Vec<u8>)>) -> String {
+    let routes: Arc<HashMap<String, (u16, Vec<u8>)>> = Arc::new(
+        routes.into_iter().map(|(path, status, body)| (path.to_owned(), (status, body))).collect()
+    );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let routes = routes.clone();
+            tokio::spawn(async move {
+                // Requests without a body end with the headers.
+                let mut request = Vec::new();
+                let mut buf = [0; 4096];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match socket.read(&mut buf).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => request.extend_from_slice(&buf[..n])
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let path = request.split_whitespace().nth(1).unwrap_or("/");
+                let route = routes.get(path).or_else(|| routes.get(path.split('?').next().unwrap()));
+                let (mut status, mut body) = route.cloned().unwrap_or((404, b"Not Found".to_vec()));
+                let etag = format!("\"{}\"", &sha256_hex(&body)[..16]);
+                let if_none_match = request.lines()
+                    .filter_map(|line| line.split_once(':'))
+                    .find(|(name, _)| name.eq_ignore_ascii_case("if-none-match"))
+                    .map(|(_, value)| value.trim());
+                if status == 200 && if_none_match == Some(etag.as_str()) {
+                    (status, body) = (304, Vec::new());
+                }
+                let head = format!(
+                    "HTTP/1.1 {status} Mock\r\nContent-Length: {}\r\nETag: {etag}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                socket.write_all(head.as_bytes()).await.ok();
+                socket.write_all(&body).await.ok();
+            });
+        }
+    });
+    format!("http://{addr}")
+}
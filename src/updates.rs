@@ -0,0 +1,288 @@
+use serde::{Serialize, Deserialize};
+use tokio::task::JoinHandle;
+use std::time::Duration;
+use semver::Version;
+use std::path::{Path, PathBuf};
+use std::fs;
+
+use crate::history::now;
+use crate::http::{self, Client};
+use crate::{data_dir, Config};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct UpdateSettings {
+    /// How often to ask for the latest release, in hours. In between, the last answer is reused.
+    #[serde(default = "default_interval_hours")]
+    pub interval_hours: u64,
+    /// Where to ask for the latest release, anything answering like the GitHub releases API works.
+    #[serde(default = "default_release_url")]
+    pub release_url: String,
+    /// How long plz may wait for the answer before exiting, in milliseconds.
+    #[serde(default = "default_max_exit_wait_ms")]
+    pub max_exit_wait_ms: u64,
+    /// `stable`, or `prerelease` to also get betas and release candidates.
+    #[serde(default = "default_channel")]
+    pub channel: String
+}
+
+impl Default for UpdateSettings {
+    fn default() -> Self {
+        UpdateSettings {
+            interval_hours: default_interval_hours(),
+            release_url: default_release_url(),
+            max_exit_wait_ms: default_max_exit_wait_ms(),
+            channel: default_channel()
+        }
+    }
+}
+
+fn default_interval_hours() -> u64 {
+    24
+}
+
+fn default_release_url() -> String {
+    String::from("https://api.github.com/repos/Bocz3k/plz/releases/latest")
+}
+
+fn default_max_exit_wait_ms() -> u64 {
+    500
+}
+
+fn default_channel() -> String {
+    String::from("stable")
+}
+
+pub const CHANNELS: [&str; 2] = ["stable", "prerelease"];
+
+#[derive(Deserialize, Clone)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub assets: Vec<Asset>
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Asset {
+    pub name: String,
+    pub browser_download_url: String
+}
+
+/// The last answer of the release API, kept in `update_check.toml`.
+#[derive(Serialize, Deserialize)]
+struct UpdateCache {
+    /// Unix timestamp (seconds) of the last check.
+    checked: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    latest: Option<String>
+}
+
+pub enum UpdateCheck {
+    /// Checked recently, this is the latest tag seen then.
+    Cached(Option<String>),
+    /// Asking the release API in the background.
+    Pending(JoinHandle<Result<Release, String>>)
+}
+
+
+pub fn cache_path() -> PathBuf {
+    data_dir().join("update_check.toml")
+}
+
+
+fn read_cache(path: &Path) -> Option<UpdateCache> {
+    fs::read_to_string(path).ok().and_then(|contents| toml::from_str(&contents).ok())
+}
+
+
+fn save_cache(path: &Path, latest: Option<String>) {
+    let cache = UpdateCache { checked: now(), latest };
+    fs::write(path, toml::to_string(&cache).unwrap()).ok();
+}
+
+
+/// Parses tags like `v1.2.3` or `1.3.0-beta.1`.
+pub fn parse_version(tag: &str) -> Option<Version> {
+    Version::parse(tag.trim().trim_start_matches('v')).ok()
+}
+
+
+/// Whether `latest` is a newer version than `current`. Tags that aren't semver never are.
+pub fn is_newer(latest: &str, current: &str) -> bool {
+    match (parse_version(latest), parse_version(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false
+    }
+}
+
+
+async fn get_json<T: serde::de::DeserializeOwned>(client: &Client, url: &str) -> Result<T, String> {
+    let res = client.get(url).await.map_err(|err| err.to_string())?;
+    if !res.status().is_success() {
+        return Err(format!("The release API answered with {}", res.status()));
+    }
+    res.json().await.map_err(|err| err.to_string())
+}
+
+
+/// The newest release on the configured channel. The prerelease channel lists every
+/// release, from `release_url` without the trailing `/latest`.
+pub async fn fetch_latest(client: &Client, settings: &UpdateSettings) -> Result<Release, String> {
+    if settings.channel != "prerelease" {
+        return get_json(client, &settings.release_url).await;
+    }
+    let url = settings.release_url.trim_end_matches('/').trim_end_matches("/latest");
+    let releases: Vec<Release> = get_json(client, url).await?;
+    releases.into_iter()
+        .filter(|release| !release.draft)
+        .filter_map(|release| parse_version(&release.tag_name).map(|version| (version, release)))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, release)| release)
+        .ok_or_else(|| String::from("There are no releases yet"))
+}
+
+
+/// Starts checking for a new version in the background, unless it was checked
+/// within the interval or checking is turned off. `cache_path` is where the last answer is kept.
+pub fn start(config: &Config, cache_path: &Path) -> Option<UpdateCheck> {
+    if !config.check_for_updates {
+        return None;
+    }
+    let interval = config.updates.interval_hours * 60 * 60;
+    let cache = read_cache(cache_path);
+    if let Some(cache) = cache.as_ref().filter(|cache| now().saturating_sub(cache.checked) < interval) {
+        return Some(UpdateCheck::Cached(cache.latest.clone()));
+    }
+    // It counts as checked from now on, so a slow or failing check isn't started again on
+    // every command. Until it answers, the version known from before stays.
+    save_cache(cache_path, cache.and_then(|cache| cache.latest));
+    let settings = config.updates.clone();
+    let client = http::client(&config.http);
+    let cache_path = cache_path.to_path_buf();
+    Some(UpdateCheck::Pending(tokio::spawn(async move {
+        let release = fetch_latest(&client?, &settings).await?;
+        save_cache(&cache_path, Some(release.tag_name.clone()));
+        Ok(release)
+    })))
+}
+
+
+/// The newer version to tell about at exit, if there is one. Waits at most
+/// `max_exit_wait_ms` for a check that's still running, if it's slower (or offline, or rate
+/// limited) the version known from the last check is used and it's tried again after the interval.
+pub async fn finish(check: UpdateCheck, settings: &UpdateSettings, cache_path: &Path) -> Option<String> {
+    let latest = match check {
+        UpdateCheck::Cached(latest) => latest,
+        UpdateCheck::Pending(handle) => {
+            match tokio::time::timeout(Duration::from_millis(settings.max_exit_wait_ms), handle).await {
+                Ok(Ok(Ok(release))) => Some(release.tag_name),
+                _ => read_cache(cache_path).and_then(|cache| cache.latest)
+            }
+        }
+    };
+    latest.filter(|latest| is_newer(latest, env!("CARGO_PKG_VERSION")))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use crate::test_server;
+
+    fn settings(base: &str, channel: &str) -> UpdateSettings {
+        UpdateSettings {
+            release_url: format!("{base}/repos/Bocz3k/plz/releases/latest"),
+            channel: channel.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    fn test_client() -> Client {
+        http::client(&http::HttpSettings { retries: 0, ..Default::default() }).unwrap()
+    }
+
+    #[test]
+    fn compares_versions_as_semver() {
+        assert!(is_newer("v0.10.0", "0.9.9"));
+        assert!(is_newer("v1.0.0", "1.0.0-beta.2"));
+        assert!(!is_newer("v0.3.4", "0.3.5"));
+        assert!(!is_newer("v0.3.5", "0.3.5"));
+        assert!(!is_newer("nightly", "0.3.5"));
+    }
+
+    #[tokio::test]
+    async fn fetches_the_latest_release() {
+        let base = test_server::serve(vec![
+            ("/repos/Bocz3k/plz/releases/latest", 200, br#"{"tag_name": "v9.9.9", "name": "plz 9.9.9"}"#.to_vec())
+        ]).await;
+        let release = fetch_latest(&test_client(), &settings(&base, "stable")).await.unwrap();
+        assert_eq!(release.tag_name, "v9.9.9");
+    }
+
+    #[tokio::test]
+    async fn prerelease_channel_picks_the_highest_version() {
+        let releases = br#"[
+            {"tag_name": "v1.0.0", "prerelease": false},
+            {"tag_name": "v1.1.0-rc.1", "prerelease": true},
+            {"tag_name": "v2.0.0", "draft": true}
+        ]"#;
+        let base = test_server::serve(vec![("/repos/Bocz3k/plz/releases", 200, releases.to_vec())]).await;
+        let release = fetch_latest(&test_client(), &settings(&base, "prerelease")).await.unwrap();
+        assert_eq!(release.tag_name, "v1.1.0-rc.1");
+    }
+
+    fn config(url: String) -> Config {
+        let mut config: Config = toml::from_str(crate::DEFAULT_CONFIG).unwrap();
+        config.check_for_updates = true;
+        config.updates.release_url = url;
+        config.http.retries = 0;
+        config
+    }
+
+    #[tokio::test]
+    async fn reuses_the_last_answer_within_the_interval() {
+        let dir = TestDir::new();
+        let cache_path = dir.path().join("update_check.toml");
+        let base = test_server::serve(vec![
+            ("/repos/Bocz3k/plz/releases/latest", 200, br#"{"tag_name": "v9.9.9"}"#.to_vec())
+        ]).await;
+        let config = config(format!("{base}/repos/Bocz3k/plz/releases/latest"));
+
+        let check = start(&config, &cache_path).unwrap();
+        assert!(matches!(check, UpdateCheck::Pending(_)));
+        assert_eq!(finish(check, &config.updates, &cache_path).await.as_deref(), Some("v9.9.9"));
+        let check = start(&config, &cache_path).unwrap();
+        assert!(matches!(&check, UpdateCheck::Cached(Some(latest)) if latest == "v9.9.9"));
+        assert_eq!(finish(check, &config.updates, &cache_path).await.as_deref(), Some("v9.9.9"));
+    }
+
+    #[tokio::test]
+    async fn counts_a_check_that_timed_out_as_done() {
+        let dir = TestDir::new();
+        let cache_path = dir.path().join("update_check.toml");
+        // Takes the connection and never answers.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let config = config(format!("http://{}/releases/latest", listener.local_addr().unwrap()));
+        let old = UpdateCache { checked: now() - config.updates.interval_hours * 60 * 60, latest: Some(String::from("v9.9.9")) };
+        fs::write(&cache_path, toml::to_string(&old).unwrap()).unwrap();
+
+        let check = start(&config, &cache_path).unwrap();
+        assert!(matches!(check, UpdateCheck::Pending(_)));
+        // The version from the last check stays until one answers.
+        assert_eq!(finish(check, &config.updates, &cache_path).await.as_deref(), Some("v9.9.9"));
+        let check = start(&config, &cache_path).unwrap();
+        assert!(matches!(&check, UpdateCheck::Cached(Some(latest)) if latest == "v9.9.9"));
+        assert!(read_cache(&cache_path).unwrap().checked > old.checked);
+    }
+
+    #[tokio::test]
+    async fn reports_api_errors() {
+        let base = test_server::serve(vec![
+            ("/repos/Bocz3k/plz/releases/latest", 403, br#"{"message": "API rate limit exceeded"}"#.to_vec())
+        ]).await;
+        let err = fetch_latest(&test_client(), &settings(&base, "stable")).await.err().unwrap();
+        assert!(err.contains("403"));
+    }
+}